@@ -0,0 +1,152 @@
+//! Which project a session's directory counts towards.
+//!
+//! Sessions are stored under the raw working directory. Aliases are applied
+//! as they're read back, so changing a rule regroups history without a
+//! rescan. A git worktree counts towards its main checkout, and
+//! `[project_aliases]` rules merge or rename anything else: a rule covers a
+//! directory and everything below it, and the longest matching rule wins.
+//!
+//! A worktree is usually removed once its branch is merged, taking the
+//! `.git` file that leads to its main checkout with it. So each worktree's
+//! checkout is remembered in the store when its sessions are imported, and
+//! used once the directory is gone.
+
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::path::{Component, Path, PathBuf};
+
+use anyhow::Result;
+
+use crate::parser::Session;
+use crate::store::{ManualEntry, Store};
+
+#[derive(Default)]
+pub struct Aliases {
+    /// (from, to), longest `from` first.
+    rules: Vec<(String, String)>,
+    /// (worktree, main checkout) for worktrees seen by an import, longest
+    /// worktree first.
+    worktrees: Vec<(String, String)>,
+    /// Resolved projects, so each directory is looked at once.
+    resolved: RefCell<HashMap<String, String>>,
+}
+
+impl Aliases {
+    /// `rules` maps a directory to the project it belongs to.
+    pub fn new(rules: &HashMap<String, String>) -> Self {
+        Self { rules: longest_first(rules), ..Default::default() }
+    }
+
+    /// Also count the worktrees the store remembers towards their main
+    /// checkouts, for when they no longer exist.
+    pub fn with_worktrees(self, worktrees: &HashMap<String, String>) -> Self {
+        Self { worktrees: longest_first(worktrees), ..self }
+    }
+
+    /// The project `dir` counts towards: its main checkout if it's in a git
+    /// worktree, or was when it was imported, then rewritten by the first
+    /// matching rule.
+    pub fn resolve(&self, dir: &str) -> String {
+        if dir.is_empty() {
+            return String::new();
+        }
+        if let Some(project) = self.resolved.borrow().get(dir) {
+            return project.clone();
+        }
+        let checkout = worktree_main(Path::new(dir))
+            .map(|path| path.to_string_lossy().into_owned())
+            .or_else(|| rewrite(&self.worktrees, dir))
+            .unwrap_or_else(|| dir.to_string());
+        let project = rewrite(&self.rules, &checkout).unwrap_or(checkout);
+        self.resolved.borrow_mut().insert(dir.to_string(), project.clone());
+        project
+    }
+
+    /// Move each session to the project its directory resolves to.
+    pub fn apply(&self, mut sessions: Vec<Session>) -> Vec<Session> {
+        for session in &mut sessions {
+            session.project = self.resolve(&session.project);
+        }
+        sessions
+    }
+
+    /// Move each manual entry to the project its directory resolves to.
+    pub fn apply_manual(&self, mut entries: Vec<ManualEntry>) -> Vec<ManualEntry> {
+        for entry in &mut entries {
+            entry.project = self.resolve(&entry.project);
+        }
+        entries
+    }
+}
+
+/// Remember the main checkout of the worktree `dir` is in, if any, while
+/// it's still there to be looked at.
+pub fn remember_worktree(store: &Store, dir: &str) -> Result<()> {
+    if dir.is_empty() {
+        return Ok(());
+    }
+    if let Some((root, main)) = linked_worktree(Path::new(dir)) {
+        store.remember_worktree(&root.to_string_lossy(), &main.to_string_lossy())?;
+    }
+    Ok(())
+}
+
+/// (from, to) pairs without trailing slashes, longest `from` first.
+fn longest_first(pairs: &HashMap<String, String>) -> Vec<(String, String)> {
+    let trim = |path: &str| path.trim_end_matches('/').to_string();
+    let mut pairs: Vec<_> = pairs.iter().map(|(from, to)| (trim(from), trim(to))).collect();
+    pairs.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+    pairs
+}
+
+/// `dir` moved by the first pair whose `from` covers it.
+fn rewrite(pairs: &[(String, String)], dir: &str) -> Option<String> {
+    pairs.iter().find_map(|(from, to)| {
+        let rest = dir.strip_prefix(from.as_str())?;
+        (rest.is_empty() || rest.starts_with('/')).then(|| format!("{}{}", to, rest))
+    })
+}
+
+/// Where `dir` would be in the main checkout, if it's inside a linked git
+/// worktree.
+fn worktree_main(dir: &Path) -> Option<PathBuf> {
+    let (root, main) = linked_worktree(dir)?;
+    let rest = dir.strip_prefix(root).ok()?;
+    Some(if rest.as_os_str().is_empty() { main } else { main.join(rest) })
+}
+
+/// The root of the linked git worktree `dir` is inside, and its main
+/// checkout. A worktree's `.git` is a file pointing at its admin directory
+/// in the main repository, whose `commondir` leads back to the main `.git`.
+/// Submodules have no `commondir`, and bare repositories no checkout, so
+/// both are left alone.
+pub(crate) fn linked_worktree(dir: &Path) -> Option<(PathBuf, PathBuf)> {
+    let root = dir.ancestors().find(|root| root.join(".git").symlink_metadata().is_ok())?;
+    let dot_git = std::fs::read_to_string(root.join(".git")).ok()?;
+    let admin = root.join(dot_git.strip_prefix("gitdir:")?.trim());
+    let common = std::fs::read_to_string(admin.join("commondir")).ok()?;
+    let common = normalize(&admin.join(common.trim()));
+    if common.file_name()? != ".git" {
+        return None;
+    }
+    Some((root.to_path_buf(), common.parent()?.to_path_buf()))
+}
+
+/// Resolve `..` without touching the filesystem, so symlinked directories
+/// keep the names sessions were recorded under.
+pub(crate) fn normalize(path: &Path) -> PathBuf {
+    let mut out = PathBuf::new();
+    for component in path.components() {
+        match component {
+            Component::ParentDir => {
+                out.pop();
+            }
+            Component::CurDir => {}
+            other => out.push(other),
+        }
+    }
+    out
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,101 @@
+use super::*;
+use std::fs;
+use tempfile::tempdir;
+
+fn rules(pairs: &[(&str, &str)]) -> Aliases {
+    Aliases::new(&pairs.iter().map(|(from, to)| (from.to_string(), to.to_string())).collect())
+}
+
+/// A main checkout at `<dir>/api` with a linked worktree at `<dir>/api-fix`,
+/// laid out the way `git worktree add` leaves them.
+fn worktree(dir: &Path) {
+    fs::create_dir_all(dir.join("api/.git/worktrees/api-fix")).unwrap();
+    fs::create_dir_all(dir.join("api-fix/src")).unwrap();
+    fs::write(
+        dir.join("api-fix/.git"),
+        format!("gitdir: {}\n", dir.join("api/.git/worktrees/api-fix").display()),
+    )
+    .unwrap();
+    fs::write(dir.join("api/.git/worktrees/api-fix/commondir"), "../..\n").unwrap();
+}
+
+#[test]
+fn rule_covers_a_directory_and_below() {
+    let aliases = rules(&[("/old/api/", "/src/api")]);
+
+    assert_eq!(aliases.resolve("/old/api"), "/src/api");
+    assert_eq!(aliases.resolve("/old/api/web"), "/src/api/web");
+    assert_eq!(aliases.resolve("/old/api-v2"), "/old/api-v2");
+    assert_eq!(aliases.resolve(""), "");
+}
+
+#[test]
+fn longest_rule_wins() {
+    let aliases = rules(&[("/work", "/archive"), ("/work/api", "/src/api")]);
+
+    assert_eq!(aliases.resolve("/work/api/web"), "/src/api/web");
+    assert_eq!(aliases.resolve("/work/site"), "/archive/site");
+}
+
+#[test]
+fn worktree_resolves_to_its_main_checkout() {
+    let dir = tempdir().unwrap();
+    worktree(dir.path());
+    let aliases = Aliases::default();
+    let path = |rel: &str| dir.path().join(rel).display().to_string();
+
+    assert_eq!(aliases.resolve(&path("api-fix")), path("api"));
+    assert_eq!(aliases.resolve(&path("api-fix/src")), path("api/src"));
+    assert_eq!(aliases.resolve(&path("api")), path("api"));
+}
+
+#[test]
+fn rules_apply_after_worktrees_resolve() {
+    let dir = tempdir().unwrap();
+    worktree(dir.path());
+    let path = |rel: &str| dir.path().join(rel).display().to_string();
+    let aliases = rules(&[(&path("api"), "/src/api")]);
+
+    assert_eq!(aliases.resolve(&path("api-fix/src")), "/src/api/src");
+}
+
+#[test]
+fn removed_worktree_resolves_to_the_checkout_remembered_for_it() {
+    let dir = tempdir().unwrap();
+    worktree(dir.path());
+    let path = |rel: &str| dir.path().join(rel).display().to_string();
+    let store = Store::new(&dir.path().join("test.db")).unwrap();
+    remember_worktree(&store, &path("api-fix/src")).unwrap();
+
+    // What `git worktree remove` leaves behind.
+    fs::remove_dir_all(dir.path().join("api-fix")).unwrap();
+    fs::remove_dir_all(dir.path().join("api/.git/worktrees")).unwrap();
+
+    let aliases = Aliases::default().with_worktrees(&store.worktrees().unwrap());
+    assert_eq!(aliases.resolve(&path("api-fix")), path("api"));
+    assert_eq!(aliases.resolve(&path("api-fix/src")), path("api/src"));
+    assert_eq!(aliases.resolve(&path("api-fixes")), path("api-fixes"));
+    assert_eq!(Aliases::default().resolve(&path("api-fix")), path("api-fix"));
+}
+
+#[test]
+fn submodule_is_its_own_project() {
+    let dir = tempdir().unwrap();
+    fs::create_dir_all(dir.path().join("app/.git/modules/lib")).unwrap();
+    fs::create_dir_all(dir.path().join("app/lib")).unwrap();
+    fs::write(dir.path().join("app/lib/.git"), "gitdir: ../.git/modules/lib\n").unwrap();
+    let lib = dir.path().join("app/lib").display().to_string();
+
+    assert_eq!(Aliases::default().resolve(&lib), lib);
+}
+
+#[test]
+fn sessions_regroup_under_their_alias() {
+    let aliases = rules(&[("/old/api", "/src/api")]);
+    let session = |project: &str| Session { project: project.to_string(), ..Default::default() };
+
+    let sessions = aliases.apply(vec![session("/old/api"), session("/src/api"), session("/src/web")]);
+
+    let projects: Vec<_> = sessions.iter().map(|s| s.project.as_str()).collect();
+    assert_eq!(projects, ["/src/api", "/src/api", "/src/web"]);
+}
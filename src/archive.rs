@@ -0,0 +1,188 @@
+//! Gzipped copies of session files, kept next to the database so history
+//! survives Claude Code deleting old transcripts and can be re-parsed after
+//! a parser fix.
+//!
+//! Copies are content-addressed: `archive/<first two hex digits>/<sha256>.gz`.
+//! Only the latest copy of each file is kept. Transcripts still being written
+//! are left until they settle, so an active session isn't recompressed on
+//! every refresh.
+
+use anyhow::{Context, Result};
+use chrono::TimeDelta;
+use flate2::Compression;
+use flate2::read::GzDecoder;
+use flate2::write::GzEncoder;
+use sha2::{Digest, Sha256};
+use std::io::{BufRead, BufReader, Write};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+use crate::ingest::{self, FileError, SourceFile};
+use crate::pool::Workers;
+use crate::scanner;
+use crate::source::SessionSource;
+use crate::store::{ArchivedFile, Store};
+
+/// How long a file must go unmodified before it's archived.
+const SETTLE_TIME: TimeDelta = TimeDelta::minutes(10);
+
+pub struct Archive {
+    dir: PathBuf,
+    /// Numbers temporary files, so parallel copies don't collide.
+    next_tmp: AtomicUsize,
+}
+
+/// Outcome of one archive pass.
+#[derive(Debug, Default)]
+pub struct ArchiveSummary {
+    pub archived: usize,
+    pub failed: Vec<FileError>,
+}
+
+impl Archive {
+    pub fn new(dir: &Path) -> Self {
+        Self { dir: dir.to_path_buf(), next_tmp: AtomicUsize::new(0) }
+    }
+
+    /// Copy every settled file that changed since it was last archived.
+    /// Files are compressed on `workers`.
+    pub fn update(
+        &self,
+        store: &Store,
+        sources: &[Box<dyn SessionSource>],
+        now_ms: i64,
+        workers: &Workers,
+    ) -> Result<ArchiveSummary> {
+        let mut summary = ArchiveSummary::default();
+        let mut copies = Vec::new();
+        for file in ingest::source_files(sources)? {
+            match Self::stale_copy(store, &file, now_ms) {
+                Ok(Some(copy)) => copies.push((file, copy)),
+                Ok(None) => {}
+                Err(e) => summary.fail(file.source_path, e),
+            }
+        }
+        workers.run(
+            "archiving",
+            &copies,
+            |(file, _)| self.write(&file.path),
+            |index, sha256| {
+                let (file, copy) = &copies[index];
+                match sha256.and_then(|sha256| self.record(store, file, copy, sha256)) {
+                    Ok(()) => summary.archived += 1,
+                    Err(e) => summary.fail(file.source_path.clone(), e),
+                }
+                Ok(())
+            },
+        )?;
+        Ok(summary)
+    }
+
+    /// The file's current size and mtime and its previous copy, if it has
+    /// settled and changed since that copy was made.
+    fn stale_copy(store: &Store, file: &SourceFile, now_ms: i64) -> Result<Option<StaleCopy>> {
+        let (size, mtime_ms) = ingest::size_and_mtime(&file.path)?;
+        if now_ms - mtime_ms < SETTLE_TIME.num_milliseconds() {
+            return Ok(None);
+        }
+        let previous = store.archived_file(&file.source_path)?;
+        if previous.as_ref().is_some_and(|p| p.size == size && p.mtime_ms == mtime_ms) {
+            return Ok(None);
+        }
+        Ok(Some(StaleCopy { size, mtime_ms, previous }))
+    }
+
+    /// Point the file's entry at its new copy.
+    fn record(&self, store: &Store, file: &SourceFile, copy: &StaleCopy, sha256: String) -> Result<()> {
+        store.save_archived_file(&ArchivedFile {
+            source_path: file.source_path.clone(),
+            sha256: sha256.clone(),
+            size: copy.size,
+            mtime_ms: copy.mtime_ms,
+        })?;
+        // The superseded copy goes unless another file has the same content.
+        if let Some(previous) = &copy.previous
+            && previous.sha256 != sha256
+            && !store.archive_object_in_use(&previous.sha256)?
+        {
+            let _ = std::fs::remove_file(self.object_path(&previous.sha256));
+        }
+        Ok(())
+    }
+
+    fn object_path(&self, sha256: &str) -> PathBuf {
+        self.dir.join(&sha256[..2]).join(format!("{}.gz", sha256))
+    }
+
+    /// Store the file under its hash, returning the hash. It's streamed
+    /// through the hasher and compressor together into a temporary file,
+    /// renamed once the hash is known, so a crash never leaves a truncated
+    /// copy behind. Kept decompressed, so a re-parse reads every copy the
+    /// same way.
+    fn write(&self, source: &Path) -> Result<String> {
+        std::fs::create_dir_all(&self.dir).with_context(|| format!("creating {:?}", self.dir))?;
+        let tmp = self.dir.join(format!("incoming-{}.tmp", self.next_tmp.fetch_add(1, Ordering::Relaxed)));
+        let mut encoder = GzEncoder::new(
+            std::fs::File::create(&tmp).with_context(|| format!("creating {:?}", tmp))?,
+            Compression::default(),
+        );
+        let mut hasher = Sha256::new();
+        let mut reader = scanner::open_transcript(source)?;
+        loop {
+            let chunk = reader.fill_buf().with_context(|| format!("reading {:?}", source))?;
+            if chunk.is_empty() {
+                break;
+            }
+            hasher.update(chunk);
+            encoder.write_all(chunk)?;
+            let consumed = chunk.len();
+            reader.consume(consumed);
+        }
+        encoder.finish()?.sync_all()?;
+        let sha256: String = hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect();
+
+        let path = self.object_path(&sha256);
+        if path.exists() {
+            std::fs::remove_file(&tmp).with_context(|| format!("removing {:?}", tmp))?;
+        } else {
+            let dir = path.parent().expect("object path has a parent");
+            std::fs::create_dir_all(dir).with_context(|| format!("creating {:?}", dir))?;
+            std::fs::rename(&tmp, &path).with_context(|| format!("renaming {:?}", tmp))?;
+        }
+        Ok(sha256)
+    }
+
+    /// The archived bytes with this hash, decompressed as they're read.
+    pub fn open(&self, sha256: &str) -> Result<impl BufRead + use<>> {
+        let path = self.object_path(sha256);
+        let file = std::fs::File::open(&path).with_context(|| format!("opening {:?}", path))?;
+        Ok(BufReader::new(GzDecoder::new(file)))
+    }
+}
+
+/// A settled file's state, and the archived copy it supersedes.
+struct StaleCopy {
+    size: u64,
+    mtime_ms: i64,
+    previous: Option<ArchivedFile>,
+}
+
+impl ArchiveSummary {
+    fn fail(&mut self, source_path: String, e: anyhow::Error) {
+        self.failed.push(FileError { source_path, message: format!("archiving: {:#}", e) });
+    }
+}
+
+/// The configured source a stored path belongs to: the one with the longest
+/// matching prefix, since an unlabelled Claude directory has none.
+pub fn source_for<'a>(sources: &'a [Box<dyn SessionSource>], source_path: &str) -> Option<&'a dyn SessionSource> {
+    sources
+        .iter()
+        .map(|source| (source, source.source_path("")))
+        .filter(|(_, prefix)| source_path.starts_with(prefix.as_str()))
+        .max_by_key(|(_, prefix)| prefix.len())
+        .map(|(source, _)| source.as_ref())
+}
+
+#[cfg(test)]
+mod tests;
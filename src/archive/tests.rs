@@ -0,0 +1,77 @@
+use super::*;
+use crate::source::{Claude, Codex};
+use std::fs;
+use std::io::Read;
+use tempfile::{tempdir, TempDir};
+
+const LINE: &str = r#"{"type":"user","timestamp":"2024-01-15T10:00:00Z","cwd":"/work/project"}"#;
+
+/// A projects dir with one session file, a store and an archive, all in one
+/// temp dir.
+fn setup() -> (TempDir, Vec<Box<dyn SessionSource>>, Store, Archive) {
+    let dir = tempdir().unwrap();
+    let projects = dir.path().join("projects");
+    fs::create_dir_all(projects.join("-work-project")).unwrap();
+    fs::write(projects.join("-work-project/s1.jsonl"), format!("{}\n", LINE)).unwrap();
+    let store = Store::new(&dir.path().join("test.db")).unwrap();
+    let archive = Archive::new(&dir.path().join("archive"));
+    let sources: Vec<Box<dyn SessionSource>> = vec![Box::new(Claude::new(&projects, None))];
+    (dir, sources, store, archive)
+}
+
+/// Milliseconds after the file was last written.
+fn after_write(dir: &TempDir, minutes: i64) -> i64 {
+    let (_, mtime_ms) = ingest::size_and_mtime(&dir.path().join("projects/-work-project/s1.jsonl")).unwrap();
+    mtime_ms + TimeDelta::minutes(minutes).num_milliseconds()
+}
+
+#[test]
+fn settled_file_is_archived_once() {
+    let (dir, sources, store, archive) = setup();
+
+    assert_eq!(archive.update(&store, &sources, after_write(&dir, 11), &Workers::default()).unwrap().archived, 1);
+    assert_eq!(archive.update(&store, &sources, after_write(&dir, 12), &Workers::default()).unwrap().archived, 0);
+
+    let archived = store.archived_file("-work-project/s1.jsonl").unwrap().unwrap();
+    let mut contents = String::new();
+    archive.open(&archived.sha256).unwrap().read_to_string(&mut contents).unwrap();
+    assert_eq!(contents, format!("{}\n", LINE));
+}
+
+#[test]
+fn file_still_being_written_waits() {
+    let (dir, sources, store, archive) = setup();
+
+    assert_eq!(archive.update(&store, &sources, after_write(&dir, 1), &Workers::default()).unwrap().archived, 0);
+    assert!(store.archived_file("-work-project/s1.jsonl").unwrap().is_none());
+}
+
+#[test]
+fn changed_file_replaces_its_old_copy() {
+    let (dir, sources, store, archive) = setup();
+    archive.update(&store, &sources, after_write(&dir, 11), &Workers::default()).unwrap();
+    let old = store.archived_file("-work-project/s1.jsonl").unwrap().unwrap();
+
+    fs::write(dir.path().join("projects/-work-project/s1.jsonl"), format!("{}\n{}\n", LINE, LINE)).unwrap();
+    archive.update(&store, &sources, after_write(&dir, 11), &Workers::default()).unwrap();
+
+    let new = store.archived_file("-work-project/s1.jsonl").unwrap().unwrap();
+    assert_ne!(new.sha256, old.sha256);
+    assert!(archive.open(&old.sha256).is_err());
+    assert!(archive.open(&new.sha256).is_ok());
+}
+
+#[test]
+fn stored_paths_map_back_to_their_source() {
+    let sources: Vec<Box<dyn SessionSource>> = vec![
+        Box::new(Claude::new(Path::new("/p"), None)),
+        Box::new(Claude::new(Path::new("/w"), Some("work"))),
+        Box::new(Codex::new(Path::new("/c"))),
+    ];
+
+    let root = |path| source_for(&sources, path).map(|s| s.root());
+
+    assert_eq!(root("-work-api/s1.jsonl"), Some(Path::new("/p")));
+    assert_eq!(root("work:-work-api/s1.jsonl"), Some(Path::new("/w")));
+    assert_eq!(root("codex:2025/10/01/rollout-1.jsonl"), Some(Path::new("/c")));
+}
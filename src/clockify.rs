@@ -44,6 +44,7 @@ pub(crate) fn post_time_entry(
     start: DateTime<Utc>,
     end: DateTime<Utc>,
     workspace_id: &str,
+    description: &str,
 ) -> Result<String> {
     // Get API key from keyring
     let api_key = crate::secrets::get_secret("clockify_api_key")
@@ -54,7 +55,7 @@ pub(crate) fn post_time_entry(
         project_id: project_id.to_string(),
         start: start.to_rfc3339(),
         end: end.to_rfc3339(),
-        description: "Development".to_string(),
+        description: description.to_string(),
     };
 
     // POST to Clockify
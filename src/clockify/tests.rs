@@ -35,7 +35,7 @@ fn test_post_time_entry_success() {
     let end = now + chrono::Duration::minutes(30);
 
     // POST the entry
-    let result = post_time_entry(TEST_PROJECT_ID, start, end, TEST_WORKSPACE_ID);
+    let result = post_time_entry(TEST_PROJECT_ID, start, end, TEST_WORKSPACE_ID, "Development");
 
     // Should succeed and return an entry ID
     assert!(result.is_ok(), "POST should succeed: {:?}", result.err());
@@ -56,7 +56,7 @@ fn test_post_time_entry_invalid_project() {
     let end = now + chrono::Duration::minutes(30);
 
     // POST with a bogus project ID
-    let result = post_time_entry("invalid-project-id-123", start, end, TEST_WORKSPACE_ID);
+    let result = post_time_entry("invalid-project-id-123", start, end, TEST_WORKSPACE_ID, "Development");
 
     // Should fail
     assert!(result.is_err(), "POST with invalid project should fail");
@@ -0,0 +1,279 @@
+//! The `doctor` subcommand: what the parser makes of each session file, plus
+//! config, database and keychain checks.
+//!
+//! Lines go through the same `SessionSource` parsing as an import, so what
+//! counts as parsed here is exactly what ingest keeps. Only the reasons
+//! given for a skipped line are worked out separately.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use std::collections::BTreeMap;
+use std::fmt;
+use std::path::Path;
+
+use crate::parser;
+use crate::scanner;
+use crate::secrets;
+use crate::source::SessionSource;
+use crate::store::Store;
+
+/// Why a line didn't become a message or summary.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum SkipReason {
+    InvalidJson,
+    MissingType,
+    MissingTimestamp,
+    BadTimestamp,
+    /// A known type we deliberately don't track.
+    Ignored,
+    UnknownType,
+}
+
+impl fmt::Display for SkipReason {
+    fn fmt(&self, f: &mut fmt::Formatter) -> fmt::Result {
+        f.write_str(match self {
+            SkipReason::InvalidJson => "invalid JSON",
+            SkipReason::MissingType => "missing type",
+            SkipReason::MissingTimestamp => "missing timestamp",
+            SkipReason::BadTimestamp => "unparseable timestamp",
+            SkipReason::Ignored => "ignored type",
+            SkipReason::UnknownType => "unknown type",
+        })
+    }
+}
+
+/// What the parser made of one session file.
+#[derive(Debug, Default, PartialEq)]
+pub struct FileDiagnostics {
+    /// Non-blank lines read.
+    pub lines: usize,
+    pub parsed: usize,
+    /// Lines too long to parse whole, which lose their content.
+    pub oversized: usize,
+    pub skipped: BTreeMap<SkipReason, usize>,
+    /// Counts per unrecognised `type` value.
+    pub unknown_types: BTreeMap<String, usize>,
+    /// Parsed lines missing fields we rely on, keyed by what's missing. Usually
+    /// the first sign that the transcript format has changed.
+    pub drift: BTreeMap<&'static str, usize>,
+}
+
+impl FileDiagnostics {
+    /// Anything beyond the line types we skip on purpose.
+    pub fn has_issues(&self) -> bool {
+        self.skipped.keys().any(|&reason| reason != SkipReason::Ignored) || !self.drift.is_empty()
+    }
+
+    fn add(&mut self, other: &FileDiagnostics) {
+        self.lines += other.lines;
+        self.parsed += other.parsed;
+        self.oversized += other.oversized;
+        for (reason, n) in &other.skipped {
+            *self.skipped.entry(*reason).or_default() += n;
+        }
+        for (ty, n) in &other.unknown_types {
+            *self.unknown_types.entry(ty.clone()).or_default() += n;
+        }
+        for (field, n) in &other.drift {
+            *self.drift.entry(field).or_default() += n;
+        }
+    }
+
+    /// Parse one line as `source` would during an import.
+    pub fn diagnose_line(&mut self, source: &dyn SessionSource, line: &str) {
+        if line.trim().is_empty() {
+            return;
+        }
+        self.lines += 1;
+        match source.parse_line(line) {
+            Some(parsed) => self.parsed(source, &parsed),
+            None => match serde_json::from_str::<serde_json::Value>(line) {
+                Ok(value) => self.explain_skip(source, |key| value.get(key)?.as_str().map(str::to_string)),
+                Err(_) => self.skip(SkipReason::InvalidJson),
+            },
+        }
+    }
+
+    /// Parse a line too long to read whole as `source` would, from the
+    /// fields scanned out of it.
+    pub fn diagnose_oversized(&mut self, source: &dyn SessionSource, fields: &parser::ScannedFields) {
+        self.lines += 1;
+        self.oversized += 1;
+        match source.parse_oversized(fields) {
+            Some(parsed) => self.parsed(source, &parsed),
+            None => self.explain_skip(source, |key| fields.get(key).map(str::to_string)),
+        }
+    }
+
+    fn parsed(&mut self, source: &dyn SessionSource, line: &parser::Line) {
+        self.parsed += 1;
+        for field in source.missing_fields(line) {
+            self.drift(field);
+        }
+    }
+
+    /// Best guess at why the parser passed over a line, from its top-level
+    /// `type` and `timestamp`.
+    fn explain_skip(&mut self, source: &dyn SessionSource, field: impl Fn(&str) -> Option<String>) {
+        let Some(ty) = field("type") else {
+            return self.skip(SkipReason::MissingType);
+        };
+        if source.ignored_types().contains(&ty.as_str()) {
+            return self.skip(SkipReason::Ignored);
+        }
+        match field("timestamp") {
+            None => self.skip(SkipReason::MissingTimestamp),
+            Some(t) if t.parse::<DateTime<Utc>>().is_err() => self.skip(SkipReason::BadTimestamp),
+            Some(_) => {
+                *self.unknown_types.entry(ty).or_default() += 1;
+                self.skip(SkipReason::UnknownType);
+            }
+        }
+    }
+
+    fn skip(&mut self, reason: SkipReason) {
+        *self.skipped.entry(reason).or_default() += 1;
+    }
+
+    fn drift(&mut self, field: &'static str) {
+        *self.drift.entry(field).or_default() += 1;
+    }
+}
+
+/// Read a file the way an import would. A document that doesn't parse at
+/// all is an error, as it is for ingest.
+pub fn diagnose_file(source: &dyn SessionSource, path: &Path) -> Result<FileDiagnostics> {
+    let mut diagnostics = FileDiagnostics::default();
+    if !source.append_only() {
+        let mut text = String::new();
+        std::io::Read::read_to_string(&mut scanner::open_transcript(path)?, &mut text)
+            .with_context(|| format!("reading {:?}", path))?;
+        let lines = source.parse_document(&text)?;
+        diagnostics.lines = lines.len();
+        for line in &lines {
+            diagnostics.parsed(source, line);
+        }
+        return Ok(diagnostics);
+    }
+    let mut lines = scanner::LineReader::new(scanner::open_transcript(path)?);
+    while let Some((line, _, _)) = lines.next_line().with_context(|| format!("reading {:?}", path))? {
+        match line {
+            scanner::TranscriptLine::Text(text) => diagnostics.diagnose_line(source, text),
+            scanner::TranscriptLine::Oversized(fields) => diagnostics.diagnose_oversized(source, &fields),
+        }
+    }
+    Ok(diagnostics)
+}
+
+fn format_diagnostics(d: &FileDiagnostics) -> String {
+    let mut out = format!("{} lines, {} parsed", d.lines, d.parsed);
+    if d.oversized > 0 {
+        out += &format!(" ({} oversized, content skipped)", d.oversized);
+    }
+    for (reason, n) in &d.skipped {
+        out += &format!(", {} {}", n, reason);
+    }
+    if !d.unknown_types.is_empty() {
+        let types: Vec<_> = d.unknown_types.iter().map(|(ty, n)| format!("{} ×{}", ty, n)).collect();
+        out += &format!("\n      unknown types: {}", types.join(", "));
+    }
+    if !d.drift.is_empty() {
+        let fields: Vec<_> = d.drift.iter().map(|(field, n)| format!("{} ×{}", field, n)).collect();
+        out += &format!("\n      missing: {}", fields.join(", "));
+    }
+    out
+}
+
+fn status(ok: bool) -> &'static str {
+    if ok { "ok  " } else { "FAIL" }
+}
+
+/// Print every check. Problems are reported, not returned, so one broken
+/// piece doesn't hide the rest.
+pub(crate) fn run_doctor(
+    config_path: &Path,
+    config: Result<Option<&crate::SyncConfig>, &anyhow::Error>,
+    db_path: &Path,
+    sources: &[Box<dyn SessionSource>],
+) -> Result<()> {
+    println!("# Config");
+    match config {
+        Ok(sync) => {
+            if config_path.exists() {
+                println!("  {}  {}", status(true), config_path.display());
+            } else {
+                println!("  --    {} not created yet, using defaults", config_path.display());
+            }
+            match sync {
+                None => println!("  --    no [sync] section"),
+                Some(sync) => match crate::sync::check_config(sync) {
+                    Ok(()) => println!("  {}  [sync]", status(true)),
+                    Err(e) => println!("  {}  [sync]: {:#}", status(false), e),
+                },
+            }
+        }
+        Err(e) => println!("  {}  {:#}", status(false), e),
+    }
+
+    println!("\n# Database");
+    if !db_path.exists() {
+        println!("  --    {} not created yet", db_path.display());
+    } else {
+        match Store::new(db_path).and_then(|store| store.health()) {
+            Ok(health) => println!(
+                "  {}  {}: integrity {}, schema v{}, {} sessions from {} files",
+                status(health.integrity == "ok"),
+                db_path.display(),
+                health.integrity,
+                health.schema_version,
+                health.sessions,
+                health.source_files,
+            ),
+            Err(e) => println!("  {}  {}: {:#}", status(false), db_path.display(), e),
+        }
+    }
+
+    println!("\n# Keychain");
+    match secrets::get_secret("clockify_api_key") {
+        Ok(_) => println!("  {}  clockify_api_key found", status(true)),
+        Err(e) => println!("  {}  {:#}", status(false), e),
+    }
+
+    for source in sources {
+        diagnose_source(source.as_ref());
+    }
+
+    Ok(())
+}
+
+fn diagnose_source(source: &dyn SessionSource) {
+    let root = source.root();
+    println!("\n# Session files ({}: {})", source.name(), root.display());
+    if !root.exists() {
+        println!("  --    not found, skipped");
+        return;
+    }
+    let mut files = source.find_files();
+    files.sort();
+    let mut total = FileDiagnostics::default();
+    let mut unreadable = 0;
+    for path in &files {
+        let name = path.strip_prefix(root).unwrap_or(path).display();
+        match diagnose_file(source, path) {
+            Ok(d) => {
+                if d.has_issues() {
+                    println!("  {}  {}: {}", status(false), name, format_diagnostics(&d));
+                }
+                total.add(&d);
+            }
+            Err(e) => {
+                unreadable += 1;
+                println!("  {}  {}: {:#}", status(false), name, e);
+            }
+        }
+    }
+    println!("  {} files ({} unreadable): {}", files.len(), unreadable, format_diagnostics(&total));
+}
+
+#[cfg(test)]
+mod tests;
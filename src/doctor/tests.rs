@@ -0,0 +1,113 @@
+use super::*;
+use crate::source::{Claude, Codex, Gemini};
+use anyhow::Result;
+use tempfile::tempdir;
+
+const USER: &str = r#"{"type":"user","timestamp":"2024-01-15T10:00:00Z","cwd":"/work/api","uuid":"u1","sessionId":"s1","message":{"content":"hi"}}"#;
+
+fn claude() -> Claude {
+    Claude::new(Path::new("/p"), None)
+}
+
+fn diagnose(lines: &[&str]) -> FileDiagnostics {
+    let mut d = FileDiagnostics::default();
+    for line in lines {
+        d.diagnose_line(&claude(), line);
+    }
+    d
+}
+
+#[test]
+fn well_formed_lines_have_no_issues() {
+    let d = diagnose(&[
+        USER,
+        r#"{"type":"summary","summary":"Fix the login flow","leafUuid":"u1"}"#,
+        r#"{"type":"file-history-snapshot","messageId":"m1"}"#,
+        "",
+    ]);
+
+    assert_eq!(d.lines, 3);
+    assert_eq!(d.parsed, 2);
+    assert_eq!(d.skipped, BTreeMap::from([(SkipReason::Ignored, 1)]));
+    assert!(!d.has_issues());
+}
+
+#[test]
+fn skipped_lines_are_counted_by_reason() {
+    let d = diagnose(&[
+        "{not json",
+        r#"{"timestamp":"2024-01-15T10:00:00Z"}"#,
+        r#"{"type":"user","cwd":"/work/api"}"#,
+        r#"{"type":"user","timestamp":"yesterday"}"#,
+        r#"{"type":"hologram","timestamp":"2024-01-15T10:00:00Z"}"#,
+    ]);
+
+    assert_eq!(d.parsed, 0);
+    assert_eq!(
+        d.skipped,
+        BTreeMap::from([
+            (SkipReason::InvalidJson, 1),
+            (SkipReason::MissingType, 1),
+            (SkipReason::MissingTimestamp, 1),
+            (SkipReason::BadTimestamp, 1),
+            (SkipReason::UnknownType, 1),
+        ])
+    );
+    assert_eq!(d.unknown_types, BTreeMap::from([("hologram".to_string(), 1)]));
+    assert!(d.has_issues());
+}
+
+#[test]
+fn missing_fields_are_reported_as_drift() {
+    let d = diagnose(&[
+        r#"{"type":"assistant","timestamp":"2024-01-15T10:00:00Z","message":{"usage":{"input":10}}}"#,
+    ]);
+
+    assert_eq!(d.parsed, 1);
+    assert_eq!(
+        d.drift,
+        BTreeMap::from([("cwd", 1), ("sessionId", 1), ("usage token counts", 1), ("uuid", 1)])
+    );
+    assert!(d.has_issues());
+}
+
+#[test]
+fn diagnose_file_reads_every_line() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("s1.jsonl");
+    std::fs::write(&path, format!("{}\n{}\n{{\"type\":\"us", USER, USER))?;
+
+    let d = diagnose_file(&claude(), &path)?;
+
+    assert_eq!(d.lines, 3);
+    assert_eq!(d.parsed, 2);
+    assert_eq!(d.skipped, BTreeMap::from([(SkipReason::InvalidJson, 1)]));
+    Ok(())
+}
+
+#[test]
+fn other_sources_are_read_with_their_own_parser() -> Result<()> {
+    let dir = tempdir()?;
+    let rollout = dir.path().join("rollout-1.jsonl");
+    std::fs::write(
+        &rollout,
+        r#"{"timestamp":"2025-10-01T10:00:00Z","type":"turn_context","payload":{"cwd":"/work/api","model":"gpt-5"}}
+{"type":"turn_context","payload":{}}
+"#,
+    )?;
+    let chat = dir.path().join("session-1.json");
+    std::fs::write(
+        &chat,
+        r#"{"sessionId":"s1","projectHash":"abc","messages":[{"id":"m1","type":"user","timestamp":"2025-10-01T10:00:00Z","content":"hi"}]}"#,
+    )?;
+
+    let codex = diagnose_file(&Codex::new(dir.path()), &rollout)?;
+    let gemini = diagnose_file(&Gemini::new(dir.path()), &chat)?;
+
+    assert_eq!((codex.lines, codex.parsed), (2, 1));
+    assert_eq!(codex.skipped, BTreeMap::from([(SkipReason::MissingTimestamp, 1)]));
+    assert_eq!((gemini.lines, gemini.parsed), (1, 1));
+    assert!(!gemini.has_issues());
+    Ok(())
+}
+
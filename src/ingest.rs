@@ -0,0 +1,438 @@
+//! Incremental import of session files into the store.
+//!
+//! Each file's size, mtime and parsed byte offset are remembered along with
+//! the parser's running totals, so a refresh only reads lines appended since
+//! the last pass and folds them into the existing session.
+//!
+//! Messages are deduplicated across files by uuid: a resumed conversation
+//! replays earlier history into a new file, and only the first file to claim
+//! a message gets credited for it.
+//!
+//! Subagent transcripts (`agent-*.jsonl`) are stored like sessions but tied
+//! to the session that spawned them, and contribute tokens only: a subagent
+//! runs inside its parent's turn, whose wall-clock time already covers it.
+//! A subagent is often written, and so read, before its parent; it's tied
+//! to the parent at the end of the pass.
+//!
+//! Files come from every enabled `SessionSource`. Sources that rewrite their
+//! files in place are re-read whole instead of resumed.
+//!
+//! Files are read and parsed in parallel, but folded into the store one at a
+//! time in the order above, so dedup doesn't depend on which thread finished
+//! first. Lines reach the store in chunks while their file is still being
+//! read, so a long transcript is never held whole.
+
+use anyhow::{Context, Result};
+use chrono::TimeDelta;
+use std::fs::File;
+use std::io::{BufRead, BufReader, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::time::UNIX_EPOCH;
+
+use crate::alias;
+use crate::archive::{self, Archive};
+use crate::parser;
+use crate::pool::Workers;
+use crate::scanner;
+use crate::source::SessionSource;
+use crate::store::{FileState, Store};
+
+/// Outcome of one ingest pass.
+#[derive(Debug, Default)]
+pub struct IngestSummary {
+    /// Files whose session rows changed.
+    pub updated: usize,
+    /// Files that couldn't be read or parsed. They're skipped, not fatal, and
+    /// retried on the next pass.
+    pub failed: Vec<FileError>,
+}
+
+#[derive(Debug)]
+pub struct FileError {
+    pub source_path: String,
+    pub message: String,
+}
+
+/// A session file on disk and the key its rows are stored under.
+pub(crate) struct SourceFile<'a> {
+    pub(crate) source: &'a dyn SessionSource,
+    pub(crate) path: PathBuf,
+    pub(crate) source_path: String,
+}
+
+/// Every file the sources find, oldest first.
+pub(crate) fn source_files(sources: &[Box<dyn SessionSource>]) -> Result<Vec<SourceFile<'_>>> {
+    let mut files = Vec::new();
+    for source in sources {
+        for path in source.find_files() {
+            let relative = path
+                .strip_prefix(source.root())
+                .with_context(|| format!("stripping prefix from {:?}", path))?
+                .to_string_lossy()
+                .into_owned();
+            files.push(SourceFile { source: source.as_ref(), source_path: source.source_path(&relative), path });
+        }
+    }
+    files.sort_by_cached_key(|f| std::fs::metadata(&f.path).and_then(|m| m.modified()).ok());
+    Ok(files)
+}
+
+/// Size and mtime (ms since the epoch) of a file, to tell whether it changed.
+pub(crate) fn size_and_mtime(path: &Path) -> Result<(u64, i64)> {
+    let metadata = std::fs::metadata(path).with_context(|| format!("reading metadata of {:?}", path))?;
+    let mtime_ms = metadata
+        .modified()
+        .ok()
+        .and_then(|t| t.duration_since(UNIX_EPOCH).ok())
+        .map(|d| d.as_millis() as i64)
+        .unwrap_or(0);
+    Ok((metadata.len(), mtime_ms))
+}
+
+/// Bring the store up to date with every session file the sources find.
+/// Files are read and parsed on `workers`, then folded into the store in
+/// one transaction, in file order.
+pub fn ingest(
+    store: &Store,
+    sources: &[Box<dyn SessionSource>],
+    idle_threshold: TimeDelta,
+    workers: &Workers,
+) -> Result<IngestSummary> {
+    // Oldest first, so the original transcript claims its messages before
+    // any resumed copy does.
+    let session_files = source_files(sources)?;
+
+    store.in_transaction(|| {
+        let mut summary = IngestSummary::default();
+        let mut jobs = Vec::new();
+        let mut states = Vec::new();
+        for file in session_files {
+            match plan(store, &file, idle_threshold) {
+                Ok(Some(state)) => {
+                    jobs.push((file, state.offset));
+                    states.push(Some(state));
+                }
+                Ok(None) => {}
+                Err(e) => summary.record(file.source_path, Err(e)),
+            }
+        }
+        workers.stream(
+            "parsing",
+            &jobs,
+            |(file, offset), emit| {
+                let tail = open_tail(&file.path, *offset)
+                    .and_then(|tail| read_tail(file.source, &file.path, tail, &mut |lines| emit(Ok(Chunk::Lines(lines)))));
+                emit(tail.map(Chunk::Done));
+            },
+            |index, read| {
+                let (file, _) = &jobs[index];
+                let state = states[index].take().expect("each file is merged once");
+                let result = fold(store, file.source, &file.source_path, &file.path, state, read);
+                summary.record(file.source_path.clone(), result);
+                Ok(())
+            },
+        )?;
+        link_subagents(store)?;
+        Ok(summary)
+    })
+}
+
+/// Throw away every parsed session and rebuild from archived copies, oldest
+/// first. Files still on disk are picked up where their archived copy ends
+/// by the next `ingest`.
+pub fn reparse_from_archive(
+    store: &Store,
+    sources: &[Box<dyn SessionSource>],
+    archive: &Archive,
+    idle_threshold: TimeDelta,
+    workers: &Workers,
+) -> Result<IngestSummary> {
+    store.in_transaction(|| {
+        store.clear_sessions()?;
+        let archived = store.archived_files()?;
+        let mut summary = IngestSummary::default();
+        let source_for = |source_path: &str| {
+            archive::source_for(sources, source_path)
+                .with_context(|| format!("no configured source for {}", source_path))
+        };
+        workers.stream(
+            "reparsing",
+            &archived,
+            |archived, emit| {
+                let name = Path::new(&archived.source_path);
+                let tail = source_for(&archived.source_path).and_then(|source| {
+                    read_tail(source, name, archive.open(&archived.sha256)?, &mut |lines| emit(Ok(Chunk::Lines(lines))))
+                });
+                emit(tail.map(Chunk::Done));
+            },
+            |index, read| {
+                let archived = &archived[index];
+                let result = source_for(&archived.source_path).and_then(|source| {
+                    let state = FileState {
+                        size: archived.size,
+                        mtime_ms: archived.mtime_ms,
+                        offset: 0,
+                        accumulator: parser::SessionAccumulator::new(idle_threshold),
+                    };
+                    let name = Path::new(&archived.source_path);
+                    fold(store, source, &archived.source_path, name, state, read)
+                });
+                summary.record(archived.source_path.clone(), result);
+                Ok(())
+            },
+        )?;
+        link_subagents(store)?;
+        Ok(summary)
+    })
+}
+
+impl IngestSummary {
+    fn record(&mut self, source_path: String, result: Result<bool>) {
+        match result {
+            Ok(true) => self.updated += 1,
+            Ok(false) => {}
+            Err(e) => self.failed.push(FileError { source_path, message: format!("{:#}", e) }),
+        }
+    }
+}
+
+/// Where to pick a file up from, or None if it's unchanged since the last
+/// pass.
+fn plan(store: &Store, file: &SourceFile, idle_threshold: TimeDelta) -> Result<Option<FileState>> {
+    let (size, mtime_ms) = size_and_mtime(&file.path)?;
+
+    let prior = store
+        .file_state(&file.source_path)?
+        .filter(|s| s.accumulator.is_compatible(idle_threshold));
+    if let Some(prior) = &prior
+        && prior.size == size
+        && prior.mtime_ms == mtime_ms
+    {
+        return Ok(None);
+    }
+    // Resume from the stored offset only if the file looks appended-to; a
+    // shrunken file or one built with different settings starts over. A
+    // compressed file can't be read from an offset, so it always starts
+    // over; they're rarely rewritten anyway.
+    let compressed = scanner::is_compressed(&file.path);
+    let resumable = |s: &FileState| s.offset <= size && file.source.append_only() && !compressed;
+    let (offset, accumulator) = match prior.filter(resumable) {
+        Some(s) => (s.offset, s.accumulator),
+        None => (0, parser::SessionAccumulator::new(idle_threshold)),
+    };
+    Ok(Some(FileState { size, mtime_ms, offset, accumulator }))
+}
+
+/// A file's bytes from `offset` on. Compressed files are only ever read
+/// from the start.
+fn open_tail(path: &Path, offset: u64) -> Result<Box<dyn BufRead>> {
+    if scanner::is_compressed(path) {
+        return scanner::open_transcript(path);
+    }
+    let mut file = File::open(path).with_context(|| format!("opening {:?}", path))?;
+    file.seek(SeekFrom::Start(offset))
+        .with_context(|| format!("seeking in {:?}", path))?;
+    Ok(Box::new(BufReader::new(file)))
+}
+
+/// Complete lines handed to the store at a time, so a long transcript is
+/// never held whole.
+const CHUNK_LINES: usize = 256;
+
+/// What a worker hands over while reading a file.
+enum Chunk {
+    /// The next complete lines, in order.
+    Lines(Vec<parser::Line>),
+    /// Reading finished.
+    Done(Tail),
+}
+
+/// How reading the end of a file finished.
+struct Tail {
+    /// Bytes the complete lines took up.
+    consumed: u64,
+    /// A trailing line with no newline yet (Claude mid-write).
+    partial: Option<parser::Line>,
+}
+
+/// Parse everything `tail` holds, handing complete lines to `emit` in
+/// chunks as they're read. Stops early once `emit` returns false.
+fn read_tail(
+    source: &dyn SessionSource,
+    file_path: &Path,
+    mut tail: impl BufRead,
+    emit: &mut dyn FnMut(Vec<parser::Line>) -> bool,
+) -> Result<Tail> {
+    let mut end = Tail { consumed: 0, partial: None };
+    if source.append_only() {
+        let mut lines = scanner::LineReader::new(tail);
+        let mut chunk = Vec::new();
+        while let Some((line, len, complete)) =
+            lines.next_line().with_context(|| format!("reading {:?}", file_path))?
+        {
+            let line = match line {
+                scanner::TranscriptLine::Text(text) => source.parse_line(text),
+                // A half-written oversized line is just skipped this pass.
+                scanner::TranscriptLine::Oversized(_) if !complete => None,
+                scanner::TranscriptLine::Oversized(fields) => source.parse_oversized(&fields),
+            };
+            if !complete {
+                end.partial = line;
+                break;
+            }
+            chunk.extend(line);
+            end.consumed += len;
+            if chunk.len() == CHUNK_LINES && !emit(std::mem::take(&mut chunk)) {
+                return Ok(end);
+            }
+        }
+        if !chunk.is_empty() {
+            emit(chunk);
+        }
+    } else {
+        // A rewritten document only parses whole.
+        let mut text = String::new();
+        tail.read_to_string(&mut text)
+            .with_context(|| format!("decoding {:?}", file_path))?;
+        emit(source.parse_document(&text).with_context(|| format!("parsing {:?}", file_path))?);
+        end.consumed = text.len() as u64;
+    }
+    Ok(end)
+}
+
+/// Fold a file's newly read lines into its saved state as they arrive and
+/// replace the file's session rows. Returns false if that left it with no
+/// sessions. If reading fails partway, none of the file's lines are kept.
+fn fold(
+    store: &Store,
+    source: &dyn SessionSource,
+    source_path: &str,
+    file_path: &Path,
+    state: FileState,
+    read: &mut dyn Iterator<Item = Result<Chunk>>,
+) -> Result<bool> {
+    store.in_savepoint(|| fold_read(store, source, source_path, file_path, state, read))
+}
+
+fn fold_read(
+    store: &Store,
+    source: &dyn SessionSource,
+    source_path: &str,
+    file_path: &Path,
+    state: FileState,
+    read: &mut dyn Iterator<Item = Result<Chunk>>,
+) -> Result<bool> {
+    let FileState { size, mtime_ms, mut offset, mut accumulator } = state;
+    if offset == 0 {
+        store.remove_messages(source_path)?;
+    }
+
+    // Only complete lines advance the offset. A trailing partial line is
+    // folded into this pass's session but not the saved state, so it's
+    // re-read once its newline lands. Whole-document files have no partial
+    // line.
+    let tail = loop {
+        match read.next().context("reading stopped before the end of the file")?? {
+            Chunk::Lines(lines) => {
+                for line in lines {
+                    fold_line(store, source_path, &mut accumulator, line)?;
+                }
+            }
+            Chunk::Done(tail) => break tail,
+        }
+    };
+    offset += tail.consumed;
+
+    // The partial line isn't claimed until it's complete, but still defers
+    // to a file that already owns it.
+    let pending = match tail.partial {
+        Some(parser::Line::Message(message)) => match &message.uuid {
+            Some(uuid) if store.message_owner(uuid)?.is_some_and(|owner| owner != source_path) => None,
+            _ => Some(message),
+        },
+        _ => None,
+    };
+    let snapshot = pending.map(|message| {
+        let mut snapshot = accumulator.clone();
+        snapshot.push(&message);
+        snapshot
+    });
+    let current = snapshot.as_ref().unwrap_or(&accumulator);
+    let mut sessions = current.sessions();
+    if source.is_subagent_file(file_path) {
+        let parent = subagent_parent(store, source_path, current.session_id())?;
+        for session in &mut sessions {
+            session.duration = TimeDelta::zero();
+            session.intervals.clear();
+            session.parent = Some(parent.clone());
+        }
+    }
+
+    for session in &sessions {
+        alias::remember_worktree(store, &session.project)?;
+    }
+    store.replace_sessions(source_path, &sessions)?;
+    store.save_file_state(
+        source_path,
+        &FileState {
+            size,
+            mtime_ms,
+            offset,
+            accumulator,
+        },
+    )?;
+
+    Ok(!sessions.is_empty())
+}
+
+/// Fold one complete line, unless another file already claimed its
+/// message.
+fn fold_line(
+    store: &Store,
+    source_path: &str,
+    accumulator: &mut parser::SessionAccumulator,
+    line: parser::Line,
+) -> Result<()> {
+    match line {
+        parser::Line::Message(message) => {
+            if let Some(uuid) = &message.uuid
+                && !store.claim_message(uuid, source_path)?
+            {
+                return Ok(());
+            }
+            let attribution = accumulator.push(&message);
+            store.record_message(source_path, &message, &attribution)?;
+        }
+        parser::Line::Summary(summary) => accumulator.push_summary(&summary),
+    }
+    Ok(())
+}
+
+/// Source path of the session a subagent transcript belongs to: the stored
+/// file in the same project folder with messages from `session_id`, however
+/// it's compressed. Empty if it isn't stored (yet).
+fn subagent_parent(store: &Store, source_path: &str, session_id: Option<&str>) -> Result<String> {
+    let Some(session_id) = session_id else {
+        return Ok(String::new());
+    };
+    let folder = |path: &str| Path::new(path).components().next().map(|dir| dir.as_os_str().to_owned());
+    Ok(store
+        .session_files(session_id)?
+        .into_iter()
+        .find(|path| !scanner::is_subagent_file(Path::new(path)) && folder(path) == folder(source_path))
+        .unwrap_or_default())
+}
+
+/// Tie subagents read before their parent to it, now that it's stored.
+fn link_subagents(store: &Store) -> Result<()> {
+    for (source_path, session_id) in store.unlinked_subagents()? {
+        let parent = subagent_parent(store, &source_path, Some(&session_id))?;
+        if !parent.is_empty() {
+            store.set_parent(&source_path, &parent)?;
+        }
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,568 @@
+use super::*;
+use anyhow::Result;
+use chrono::TimeDelta;
+use std::fs;
+use std::io::Write;
+use std::path::PathBuf;
+use tempfile::{tempdir, TempDir};
+
+const IDLE: TimeDelta = TimeDelta::minutes(15);
+
+fn line(timestamp: &str, output_tokens: u64) -> String {
+    format!(
+        r#"{{"type":"assistant","timestamp":"{}","cwd":"/work/project","message":{{"usage":{{"input_tokens":10,"output_tokens":{}}}}}}}"#,
+        timestamp, output_tokens
+    ) + "\n"
+}
+
+/// A projects dir with one project folder, plus an open store in the same temp dir.
+fn setup() -> Result<(TempDir, PathBuf, Store)> {
+    let dir = tempdir()?;
+    let projects = dir.path().join("projects");
+    fs::create_dir_all(projects.join("-work-project"))?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+    Ok((dir, projects, store))
+}
+
+fn claude(projects: &Path) -> Vec<Box<dyn SessionSource>> {
+    vec![Box::new(crate::source::Claude::new(projects, None))]
+}
+
+fn append(path: &Path, contents: &str) -> Result<()> {
+    let mut file = fs::OpenOptions::new().create(true).append(true).open(path)?;
+    file.write_all(contents.as_bytes())?;
+    Ok(())
+}
+
+fn all_sessions(store: &Store) -> Result<Vec<parser::Session>> {
+    store.query_range("2000-01-01T00:00:00Z".parse()?, "2100-01-01T00:00:00Z".parse()?)
+}
+
+#[test]
+fn imports_new_file() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    let file = projects.join("-work-project/session-1.jsonl");
+    append(&file, &(line("2026-02-03T10:00:00Z", 5) + &line("2026-02-03T10:05:00Z", 7)))?;
+
+    assert_eq!(ingest(&store, &claude(&projects), IDLE, &Workers::default())?.updated, 1);
+
+    let sessions = all_sessions(&store)?;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].duration.num_seconds(), 300);
+    assert_eq!(sessions[0].output_tokens, 12);
+    Ok(())
+}
+
+#[test]
+fn unchanged_file_is_skipped() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    let file = projects.join("-work-project/session-1.jsonl");
+    append(&file, &line("2026-02-03T10:00:00Z", 5))?;
+
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    assert_eq!(ingest(&store, &claude(&projects), IDLE, &Workers::default())?.updated, 0);
+    Ok(())
+}
+
+#[test]
+fn appended_lines_fold_into_existing_session() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    let file = projects.join("-work-project/session-1.jsonl");
+    append(&file, &line("2026-02-03T10:00:00Z", 5))?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    append(&file, &line("2026-02-03T10:10:00Z", 7))?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    let sessions = all_sessions(&store)?;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].duration.num_seconds(), 600);
+    assert_eq!(sessions[0].output_tokens, 12);
+
+    let state = store.file_state("-work-project/session-1.jsonl")?.unwrap();
+    assert_eq!(state.offset, fs::metadata(&file)?.len());
+    Ok(())
+}
+
+#[test]
+fn oversized_line_keeps_its_usage_and_the_lines_after_it() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    let file = projects.join("-work-project/session-1.jsonl");
+    let huge = format!(
+        r#"{{"type":"assistant","timestamp":"2026-02-03T10:00:00Z","cwd":"/work/project","message":{{"content":[{{"type":"text","text":"{}"}}],"usage":{{"input_tokens":10,"output_tokens":9}}}}}}"#,
+        "x".repeat(parser::MAX_LINE_BYTES)
+    ) + "\n";
+    append(&file, &(huge + &line("2026-02-03T10:05:00Z", 5)))?;
+
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    let sessions = all_sessions(&store)?;
+    assert_eq!(sessions[0].output_tokens, 14);
+    assert_eq!(sessions[0].duration.num_seconds(), 300);
+    let state = store.file_state("-work-project/session-1.jsonl")?.unwrap();
+    assert_eq!(state.offset, fs::metadata(&file)?.len());
+    Ok(())
+}
+
+#[test]
+fn partial_trailing_line_is_not_consumed() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    let file = projects.join("-work-project/session-1.jsonl");
+    let first = line("2026-02-03T10:00:00Z", 5);
+    let second = line("2026-02-03T10:05:00Z", 7);
+    append(&file, &(first.clone() + second.trim_end()))?;
+
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    // Complete JSON without its newline still shows up in the session...
+    assert_eq!(all_sessions(&store)?[0].output_tokens, 12);
+    // ...but the offset stops at the last newline so it's re-read next pass.
+    let state = store.file_state("-work-project/session-1.jsonl")?.unwrap();
+    assert_eq!(state.offset, first.len() as u64);
+
+    append(&file, &("\n".to_string() + &line("2026-02-03T10:06:00Z", 1)))?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    let sessions = all_sessions(&store)?;
+    assert_eq!(sessions[0].output_tokens, 13);
+    assert_eq!(sessions[0].duration.num_seconds(), 360);
+    Ok(())
+}
+
+#[test]
+fn truncated_file_is_reparsed_from_start() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    let file = projects.join("-work-project/session-1.jsonl");
+    append(&file, &(line("2026-02-03T10:00:00Z", 5) + &line("2026-02-03T10:05:00Z", 7)))?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    fs::write(&file, line("2026-02-03T11:00:00Z", 3))?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    let sessions = all_sessions(&store)?;
+    assert_eq!(sessions[0].output_tokens, 3);
+    assert_eq!(sessions[0].duration.num_seconds(), 0);
+    Ok(())
+}
+
+#[test]
+fn changed_idle_threshold_reparses_from_start() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    let file = projects.join("-work-project/session-1.jsonl");
+    append(&file, &(line("2026-02-03T10:00:00Z", 5) + &line("2026-02-03T10:20:00Z", 7)))?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+    assert_eq!(all_sessions(&store)?[0].duration.num_seconds(), 0);
+
+    ingest(&store, &claude(&projects), TimeDelta::minutes(30), &Workers::default())?;
+
+    assert_eq!(all_sessions(&store)?[0].duration.num_seconds(), 1200);
+    Ok(())
+}
+
+// --- Resumed session dedup ----------------------------------------------
+
+fn line_with_uuid(uuid: &str, timestamp: &str, output_tokens: u64) -> String {
+    format!(
+        r#"{{"type":"assistant","uuid":"{}","timestamp":"{}","cwd":"/work/project","message":{{"usage":{{"input_tokens":10,"output_tokens":{}}}}}}}"#,
+        uuid, timestamp, output_tokens
+    ) + "\n"
+}
+
+#[test]
+fn resumed_session_does_not_recount_replayed_history() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    let history = line_with_uuid("u1", "2026-02-03T10:00:00Z", 5) + &line_with_uuid("u2", "2026-02-03T10:05:00Z", 7);
+    append(&projects.join("-work-project/original.jsonl"), &history)?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    // The resumed file replays u1/u2 with their original timestamps, then continues.
+    let resumed = history + &line_with_uuid("u3", "2026-02-04T09:00:00Z", 11) + &line_with_uuid("u4", "2026-02-04T09:02:00Z", 13);
+    append(&projects.join("-work-project/resumed.jsonl"), &resumed)?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    let sessions = all_sessions(&store)?;
+    assert_eq!(sessions.len(), 2);
+    let total_output: u64 = sessions.iter().map(|s| s.output_tokens).sum();
+    assert_eq!(total_output, 5 + 7 + 11 + 13);
+    let resumed = sessions.iter().find(|s| s.output_tokens == 24).unwrap();
+    assert_eq!(resumed.start, "2026-02-04T09:00:00Z".parse::<chrono::DateTime<chrono::Utc>>()?);
+    assert_eq!(resumed.duration.num_seconds(), 120);
+    Ok(())
+}
+
+#[test]
+fn parallel_import_credits_replayed_history_to_the_oldest_file() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    let history = line_with_uuid("u1", "2026-02-03T10:00:00Z", 5);
+    let oldest = std::time::SystemTime::now() - std::time::Duration::from_secs(3600);
+    for i in 0..16 {
+        let path = projects.join(format!("-work-project/copy-{:02}.jsonl", i));
+        append(&path, &history)?;
+        fs::File::options()
+            .write(true)
+            .open(&path)?
+            .set_modified(oldest + std::time::Duration::from_secs(i))?;
+    }
+
+    ingest(&store, &claude(&projects), IDLE, &Workers { threads: 8, ..Default::default() })?;
+
+    assert_eq!(store.message_owner("u1")?.as_deref(), Some("-work-project/copy-00.jsonl"));
+    assert_eq!(all_sessions(&store)?.len(), 1);
+    Ok(())
+}
+
+/// Messages recorded in the store, over all time.
+fn message_count(store: &Store) -> Result<usize> {
+    let rollups = store.message_rollups(
+        chrono::DateTime::UNIX_EPOCH,
+        "2100-01-01T00:00:00Z".parse()?,
+        crate::store::Bucket::Day,
+        None,
+    )?;
+    Ok(rollups.iter().map(|r| r.messages).sum())
+}
+
+#[test]
+fn messages_are_recorded_once_per_claim_and_reparse() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    let history = line_with_uuid("u1", "2026-02-03T10:00:00Z", 5) + &line_with_uuid("u2", "2026-02-03T10:05:00Z", 7);
+    append(&projects.join("-work-project/original.jsonl"), &history)?;
+    append(&projects.join("-work-project/resumed.jsonl"), &(history.clone() + &line_with_uuid("u3", "2026-02-04T09:00:00Z", 11)))?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+    assert_eq!(message_count(&store)?, 3);
+
+    // A new idle threshold re-reads both files from the start, replacing
+    // their messages.
+    ingest(&store, &claude(&projects), TimeDelta::minutes(30), &Workers::default())?;
+
+    assert_eq!(message_count(&store)?, 3);
+    Ok(())
+}
+
+#[test]
+fn fully_replayed_file_has_no_session() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    let history = line_with_uuid("u1", "2026-02-03T10:00:00Z", 5);
+    append(&projects.join("-work-project/original.jsonl"), &history)?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    append(&projects.join("-work-project/fork.jsonl"), &history)?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    assert_eq!(all_sessions(&store)?.len(), 1);
+    Ok(())
+}
+
+#[test]
+fn reparsing_a_file_keeps_its_own_claims() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    let file = projects.join("-work-project/original.jsonl");
+    append(&file, &line_with_uuid("u1", "2026-02-03T10:00:00Z", 5))?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    // Threshold change forces a from-scratch reparse of the same file.
+    ingest(&store, &claude(&projects), TimeDelta::minutes(30), &Workers::default())?;
+
+    assert_eq!(all_sessions(&store)?[0].output_tokens, 5);
+    Ok(())
+}
+
+// --- Per-cwd segments ---------------------------------------------------
+
+#[test]
+fn session_that_changes_directory_gets_a_row_per_directory() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    let file = projects.join("-work-project/session-1.jsonl");
+    let moved = line("2026-02-03T10:05:00Z", 7).replace("/work/project", "/work/sibling");
+    append(&file, &(line("2026-02-03T10:00:00Z", 5) + &moved))?;
+
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    let mut sessions = all_sessions(&store)?;
+    sessions.sort_by(|a, b| a.project.cmp(&b.project));
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[0].project, "/work/project");
+    assert_eq!(sessions[0].output_tokens, 5);
+    assert_eq!(sessions[1].project, "/work/sibling");
+    assert_eq!(sessions[1].output_tokens, 7);
+    assert_eq!(sessions[1].duration.num_seconds(), 300);
+    Ok(())
+}
+
+// --- Subagent transcripts -----------------------------------------------
+
+fn agent_line(session_id: &str, timestamp: &str, output_tokens: u64) -> String {
+    format!(
+        r#"{{"type":"assistant","isSidechain":true,"sessionId":"{}","timestamp":"{}","cwd":"/work/project","message":{{"usage":{{"input_tokens":10,"output_tokens":{}}}}}}}"#,
+        session_id, timestamp, output_tokens
+    ) + "\n"
+}
+
+fn session_line(session_id: &str, timestamp: &str, output_tokens: u64) -> String {
+    agent_line(session_id, timestamp, output_tokens).replace(r#""isSidechain":true,"#, "")
+}
+
+#[test]
+fn subagent_tokens_count_without_time() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    let nested = projects.join("-work-project/abc/subagents");
+    fs::create_dir_all(&nested)?;
+    // Subagents finish before their parent does, so they're read first.
+    append(&nested.join("agent-1.jsonl"), &(agent_line("abc", "2026-02-03T10:01:00Z", 100) + &agent_line("abc", "2026-02-03T10:06:00Z", 200)))?;
+    append(&projects.join("-work-project/agent-2.jsonl"), &agent_line("abc", "2026-02-03T10:08:00Z", 400))?;
+    append(&projects.join("-work-project/abc.jsonl"), &(session_line("abc", "2026-02-03T10:00:00Z", 5) + &session_line("abc", "2026-02-03T10:10:00Z", 7)))?;
+    let earlier = std::time::SystemTime::now() - std::time::Duration::from_secs(60);
+    for agent in [nested.join("agent-1.jsonl"), projects.join("-work-project/agent-2.jsonl")] {
+        fs::File::options().write(true).open(agent)?.set_modified(earlier)?;
+    }
+
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    let sessions = all_sessions(&store)?;
+    assert_eq!(sessions.len(), 3);
+    let total_output: u64 = sessions.iter().map(|s| s.output_tokens).sum();
+    assert_eq!(total_output, 5 + 7 + 100 + 200 + 400);
+    let total_secs: i64 = sessions.iter().map(|s| s.duration.num_seconds()).sum();
+    assert_eq!(total_secs, 600, "only the parent's time counts");
+    let subagents: Vec<_> = sessions.iter().filter(|s| s.parent.is_some()).collect();
+    assert_eq!(subagents.len(), 2);
+    assert!(subagents.iter().all(|s| s.parent.as_deref() == Some("-work-project/abc.jsonl")));
+    Ok(())
+}
+
+#[test]
+fn subagent_parent_is_the_stored_file_whatever_its_compression() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    fs::create_dir_all(projects.join("-other-project"))?;
+    let parent = session_line("abc", "2026-02-03T10:00:00Z", 5) + &session_line("abc", "2026-02-03T10:10:00Z", 7);
+    fs::write(projects.join("-work-project/abc.jsonl.gz"), gzip(&parent)?)?;
+    // Same session id in another project folder isn't the parent.
+    append(&projects.join("-other-project/abc.jsonl"), &session_line("abc", "2026-02-03T09:00:00Z", 1))?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    append(&projects.join("-work-project/agent-1.jsonl"), &agent_line("abc", "2026-02-03T10:05:00Z", 100))?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    let sessions = all_sessions(&store)?;
+    let subagent = sessions.iter().find(|s| s.parent.is_some()).expect("subagent stored");
+    assert_eq!(subagent.parent.as_deref(), Some("-work-project/abc.jsonl.gz"));
+    Ok(())
+}
+
+fn gzip(text: &str) -> Result<Vec<u8>> {
+    let mut encoder = flate2::write::GzEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(text.as_bytes())?;
+    Ok(encoder.finish()?)
+}
+
+// --- Titles -------------------------------------------------------------
+
+#[test]
+fn summary_line_becomes_session_title() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    let file = projects.join("-work-project/session-1.jsonl");
+    let prompt = r#"{"type":"user","timestamp":"2026-02-03T09:59:00Z","cwd":"/work/project","message":{"role":"user","content":"Why does login loop?"}}"#;
+    append(&file, &(prompt.to_string() + "\n" + &line("2026-02-03T10:00:00Z", 5)))?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+    assert_eq!(all_sessions(&store)?[0].title.as_deref(), Some("Why does login loop?"));
+
+    append(&file, "{\"type\":\"summary\",\"summary\":\"Fix login redirect loop\",\"leafUuid\":\"x\"}\n")?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    assert_eq!(all_sessions(&store)?[0].title.as_deref(), Some("Fix login redirect loop"));
+    Ok(())
+}
+
+#[test]
+fn unreadable_file_is_reported_not_fatal() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    append(&projects.join("-work-project/good.jsonl"), &line("2024-01-15T10:00:00Z", 5))?;
+    fs::write(projects.join("-work-project/bad.jsonl.gz"), b"not gzip\n")?;
+
+    let summary = ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    assert_eq!(summary.updated, 1);
+    assert_eq!(summary.failed.len(), 1);
+    assert_eq!(summary.failed[0].source_path, "-work-project/bad.jsonl.gz");
+    assert!(summary.failed[0].message.contains("reading"));
+    Ok(())
+}
+
+#[test]
+fn importing_a_worktree_session_remembers_its_checkout() -> Result<()> {
+    let (dir, projects, store) = setup()?;
+    let (main, worktree) = (dir.path().join("api"), dir.path().join("api-fix"));
+    fs::create_dir_all(main.join(".git/worktrees/api-fix"))?;
+    fs::create_dir_all(&worktree)?;
+    fs::write(worktree.join(".git"), format!("gitdir: {}\n", main.join(".git/worktrees/api-fix").display()))?;
+    fs::write(main.join(".git/worktrees/api-fix/commondir"), "../..\n")?;
+    append(
+        &projects.join("-work-project/session-1.jsonl"),
+        &line("2026-02-03T10:00:00Z", 5).replace("/work/project", &worktree.display().to_string()),
+    )?;
+
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    let remembered = store.worktrees()?;
+    assert_eq!(remembered.get(&worktree.display().to_string()), Some(&main.display().to_string()));
+    Ok(())
+}
+
+#[test]
+fn file_failing_partway_keeps_none_of_its_lines() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    let count = CHUNK_LINES * 8;
+    let contents: String = (0..count)
+        .map(|i| line(&format!("2024-01-15T{:02}:{:02}:{:02}Z", i / 3600, i / 60 % 60, i % 60), 1))
+        .collect();
+    let compressed = zstd::encode_all(contents.as_bytes(), 0)?;
+    let file = projects.join("-work-project/long.jsonl.zst");
+
+    // Cut off the end, so the first chunks are folded before reading fails.
+    fs::write(&file, &compressed[..compressed.len() - 16])?;
+    let summary = ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+    assert_eq!(summary.failed.len(), 1);
+    assert_eq!(message_count(&store)?, 0);
+    assert!(all_sessions(&store)?.is_empty());
+
+    fs::write(&file, &compressed)?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+    assert_eq!(message_count(&store)?, count);
+    Ok(())
+}
+
+// --- Other sources ------------------------------------------------------
+
+#[test]
+fn codex_rollouts_are_stored_under_their_source_name() -> Result<()> {
+    let (dir, _projects, store) = setup()?;
+    let sessions = dir.path().join("codex-sessions");
+    fs::create_dir_all(sessions.join("2025/10/01"))?;
+    append(
+        &sessions.join("2025/10/01/rollout-1.jsonl"),
+        concat!(
+            r#"{"timestamp":"2025-10-01T10:00:00Z","type":"session_meta","payload":{"id":"c1","cwd":"/work/api"}}"#, "\n",
+            r#"{"timestamp":"2025-10-01T10:00:01Z","type":"turn_context","payload":{"cwd":"/work/api","model":"gpt-5-codex"}}"#, "\n",
+            r#"{"timestamp":"2025-10-01T10:02:00Z","type":"event_msg","payload":{"type":"token_count","info":{"total_token_usage":{"total_tokens":150},"last_token_usage":{"input_tokens":100,"cached_input_tokens":40,"output_tokens":50}}}}"#, "\n",
+        ),
+    )?;
+    let sources: Vec<Box<dyn SessionSource>> = vec![Box::new(crate::source::Codex::new(&sessions))];
+
+    assert_eq!(ingest(&store, &sources, IDLE, &Workers::default())?.updated, 1);
+
+    let sessions = all_sessions(&store)?;
+    assert_eq!(sessions[0].project, "/work/api");
+    assert_eq!(sessions[0].duration, TimeDelta::minutes(2));
+    assert_eq!(sessions[0].models["gpt-5-codex"].cache_read_input_tokens, 40);
+    assert!(store.file_state("codex:2025/10/01/rollout-1.jsonl")?.is_some());
+    Ok(())
+}
+
+fn gemini_chat(messages: &str) -> String {
+    format!(r#"{{"sessionId":"g1","projectHash":"abc123","messages":[{}]}}"#, messages)
+}
+
+#[test]
+fn rewritten_gemini_chat_is_reparsed_whole() -> Result<()> {
+    let (dir, _projects, store) = setup()?;
+    let tmp = dir.path().join("gemini-tmp");
+    fs::create_dir_all(tmp.join("abc123/chats"))?;
+    let file = tmp.join("abc123/chats/session-1.json");
+    let reply = |id: &str, ts: &str| {
+        format!(r#"{{"id":"{}","timestamp":"{}","type":"gemini","model":"gemini-2.5-pro","tokens":{{"input":10,"output":5}}}}"#, id, ts)
+    };
+    let sources: Vec<Box<dyn SessionSource>> = vec![Box::new(crate::source::Gemini::new(&tmp))];
+
+    fs::write(&file, gemini_chat(&reply("m1", "2025-10-01T10:00:00Z")))?;
+    ingest(&store, &sources, IDLE, &Workers::default())?;
+    fs::write(
+        &file,
+        gemini_chat(&[reply("m1", "2025-10-01T10:00:00Z"), reply("m2", "2025-10-01T10:05:00Z")].join(",")),
+    )?;
+    ingest(&store, &sources, IDLE, &Workers::default())?;
+
+    let sessions = all_sessions(&store)?;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].project, "gemini:abc123");
+    assert_eq!(sessions[0].output_tokens, 10);
+    assert_eq!(sessions[0].duration, TimeDelta::minutes(5));
+    Ok(())
+}
+
+#[test]
+fn same_relative_path_under_two_projects_dirs_does_not_collide() -> Result<()> {
+    let (dir, projects, store) = setup()?;
+    let work = dir.path().join("work-projects");
+    fs::create_dir_all(work.join("-work-project"))?;
+    append(&projects.join("-work-project/s1.jsonl"), &line("2024-01-15T10:00:00Z", 5))?;
+    append(&work.join("-work-project/s1.jsonl"), &line("2024-01-15T11:00:00Z", 7))?;
+    let sources: Vec<Box<dyn SessionSource>> = vec![
+        Box::new(crate::source::Claude::new(&projects, None)),
+        Box::new(crate::source::Claude::new(&work, Some("work"))),
+    ];
+
+    assert_eq!(ingest(&store, &sources, IDLE, &Workers::default())?.updated, 2);
+
+    assert!(store.file_state("-work-project/s1.jsonl")?.is_some());
+    assert!(store.file_state("work:-work-project/s1.jsonl")?.is_some());
+    assert_eq!(all_sessions(&store)?.iter().map(|s| s.output_tokens).sum::<u64>(), 12);
+    Ok(())
+}
+
+// --- Archive ------------------------------------------------------------
+
+#[test]
+fn reparse_from_archive_restores_deleted_transcripts() -> Result<()> {
+    let (dir, projects, store) = setup()?;
+    let file = projects.join("-work-project/s1.jsonl");
+    append(&file, &(line("2024-01-15T10:00:00Z", 5) + &line("2024-01-15T10:10:00Z", 7)))?;
+    let archive = Archive::new(&dir.path().join("archive"));
+    let (_, mtime_ms) = size_and_mtime(&file)?;
+    archive.update(&store, &claude(&projects), mtime_ms + TimeDelta::hours(1).num_milliseconds(), &Workers::default())?;
+    fs::remove_file(&file)?;
+
+    let summary = reparse_from_archive(&store, &claude(&projects), &archive, IDLE, &Workers::default())?;
+
+    assert_eq!(summary.updated, 1);
+    let sessions = all_sessions(&store)?;
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].output_tokens, 12);
+    assert_eq!(sessions[0].duration, TimeDelta::minutes(10));
+    Ok(())
+}
+
+#[test]
+fn live_file_resumes_after_its_archived_copy() -> Result<()> {
+    let (dir, projects, store) = setup()?;
+    let file = projects.join("-work-project/s1.jsonl");
+    append(&file, &line("2024-01-15T10:00:00Z", 5))?;
+    let archive = Archive::new(&dir.path().join("archive"));
+    let (_, mtime_ms) = size_and_mtime(&file)?;
+    archive.update(&store, &claude(&projects), mtime_ms + TimeDelta::hours(1).num_milliseconds(), &Workers::default())?;
+    append(&file, &line("2024-01-15T10:05:00Z", 7))?;
+
+    reparse_from_archive(&store, &claude(&projects), &archive, IDLE, &Workers::default())?;
+    ingest(&store, &claude(&projects), IDLE, &Workers::default())?;
+
+    assert_eq!(all_sessions(&store)?[0].output_tokens, 12);
+    Ok(())
+}
+
+#[test]
+fn compressed_transcript_is_imported() -> Result<()> {
+    let (_dir, projects, store) = setup()?;
+    let contents = line("2024-01-15T10:00:00Z", 5) + &line("2024-01-15T10:04:00Z", 7);
+    fs::write(
+        projects.join("-work-project/old.jsonl.zst"),
+        zstd::encode_all(contents.as_bytes(), 0)?,
+    )?;
+
+    assert_eq!(ingest(&store, &claude(&projects), IDLE, &Workers::default())?.updated, 1);
+    assert_eq!(ingest(&store, &claude(&projects), IDLE, &Workers::default())?.updated, 0);
+
+    let sessions = all_sessions(&store)?;
+    assert_eq!(sessions[0].output_tokens, 12);
+    assert_eq!(sessions[0].duration, TimeDelta::minutes(4));
+    Ok(())
+}
@@ -1,10 +1,20 @@
+mod alias;
+mod ingest;
+mod manual;
 mod parser;
+mod pool;
+mod pricing;
+mod report;
 mod scanner;
 mod spinner;
 mod secrets;
 mod store;
+mod source;
 mod sync;
+mod archive;
 mod clockify;
+mod doctor;
+mod watcher;
 
 use anyhow::{Context, Result};
 use chrono::{DateTime, Local, TimeDelta, TimeZone, Utc};
@@ -23,7 +33,7 @@ use ratatui::{
     widgets::{Block, BorderType, Borders, Cell, Paragraph, Row, Table},
     Frame, Terminal,
 };
-use std::collections::HashMap;
+use std::collections::{BTreeMap, HashMap};
 use std::io::{self, Write};
 use std::path::Path;
 use std::sync::mpsc;
@@ -34,6 +44,36 @@ const DEFAULT_CONFIG: &str = r#"# Claude Tracker Configuration
 
 # Minutes of inactivity before a gap is considered idle time (excluded from duration)
 idle_timeout_minutes = 15
+
+# Files read in parallel during a scan. Defaults to the number of CPUs.
+# scan_threads = 4
+
+# Agent tools whose sessions to track. Missing log directories are skipped.
+# sources = ["claude", "codex", "gemini"]
+
+# Claude Code projects directories. Defaults to $CLAUDE_CONFIG_DIR/projects,
+# else ~/.claude/projects. With more than one, all but one need a label.
+# [[projects_dirs]]
+# path = "~/.claude/projects"
+#
+# [[projects_dirs]]
+# path = "~/.claude-work/projects"
+# label = "work"
+
+# Count sessions in one directory towards another project, e.g. after a
+# rename or for a second checkout. A rule covers the directory and everything
+# below it. Git worktrees count towards their main checkout without a rule,
+# even after they're removed.
+# [project_aliases]
+# "~/src/api-old" = "~/src/api"
+
+# Cost estimates use built-in list prices (USD per million tokens). Override or
+# add models by id prefix; the longest matching prefix wins.
+# [pricing."claude-sonnet-4"]
+# input = 3.0
+# output = 15.0
+# cache_write = 3.75
+# cache_read = 0.30
 "#;
 
 #[derive(serde::Deserialize)]
@@ -43,6 +83,9 @@ pub(crate) struct SyncConfig {
     pub(crate) work_day_start: String,
     pub(crate) work_day_end: String,
     pub(crate) project_mapping: HashMap<String, String>,
+    /// What to put in each entry's description.
+    #[serde(default)]
+    pub(crate) description: sync::DescriptionSource,
 }
 
 #[derive(serde::Deserialize)]
@@ -50,6 +93,19 @@ struct Config {
     #[serde(default = "default_idle_timeout_minutes")]
     idle_timeout_minutes: u64,
     sync: Option<SyncConfig>,
+    #[serde(default)]
+    pricing: HashMap<String, pricing::ModelPrice>,
+    #[serde(default = "source::all_kinds")]
+    sources: Vec<source::Kind>,
+    /// Empty means the default location.
+    #[serde(default)]
+    projects_dirs: Vec<source::ProjectsDir>,
+    /// None means one per CPU.
+    #[serde(default)]
+    scan_threads: Option<usize>,
+    /// Directory to the project it counts towards.
+    #[serde(default)]
+    project_aliases: HashMap<String, String>,
 }
 
 fn default_idle_timeout_minutes() -> u64 {
@@ -61,10 +117,77 @@ impl Default for Config {
         Self {
             idle_timeout_minutes: default_idle_timeout_minutes(),
             sync: None,
+            pricing: HashMap::new(),
+            sources: source::all_kinds(),
+            projects_dirs: Vec::new(),
+            scan_threads: None,
+            project_aliases: HashMap::new(),
         }
     }
 }
 
+/// Where Claude Code keeps transcripts when config.toml doesn't say.
+fn default_projects_dir() -> Result<std::path::PathBuf> {
+    if let Some(dir) = std::env::var_os("CLAUDE_CONFIG_DIR") {
+        return Ok(Path::new(&dir).join("projects"));
+    }
+    let home = std::env::var("HOME").context("HOME env var not set")?;
+    Ok(Path::new(&home).join(".claude").join("projects"))
+}
+
+/// The configured Claude Code projects directories, with `~` expanded.
+fn projects_dirs(config: &Config) -> Result<Vec<source::ProjectsDir>> {
+    if config.projects_dirs.is_empty() {
+        return Ok(vec![source::ProjectsDir { path: default_projects_dir()?, label: None }]);
+    }
+    let home = std::env::var("HOME").context("HOME env var not set")?;
+    let dirs: Vec<_> = config
+        .projects_dirs
+        .iter()
+        .map(|dir| source::ProjectsDir {
+            path: expand_home(&dir.path, Path::new(&home)),
+            label: dir.label.clone(),
+        })
+        .collect();
+    source::check_projects_dirs(&dirs)?;
+    Ok(dirs)
+}
+
+/// The configured project aliases, with `~` expanded on both sides, and
+/// the worktrees `store` remembers.
+fn project_aliases(config: &Config, store: &store::Store) -> Result<alias::Aliases> {
+    let home = std::env::var("HOME").context("HOME env var not set")?;
+    let expand = |path: &String| expand_home(Path::new(path), Path::new(&home)).to_string_lossy().into_owned();
+    Ok(alias::Aliases::new(
+        &config.project_aliases.iter().map(|(from, to)| (expand(from), expand(to))).collect(),
+    )
+    .with_worktrees(&store.worktrees()?))
+}
+
+/// Confirm a manual entry, and warn about days it won't be synced for.
+fn print_logged(logged: &manual::Logged) {
+    println!("{}", logged.message);
+    for day in &logged.synced {
+        eprintln!("warning: {} was already synced to Clockify; this entry won't be pushed there", day);
+    }
+}
+
+fn scan_threads(config: &Config) -> usize {
+    config.scan_threads.unwrap_or_else(pool::default_threads).max(1)
+}
+
+fn expand_home(path: &Path, home: &Path) -> std::path::PathBuf {
+    match path.strip_prefix("~") {
+        Ok(rest) => home.join(rest),
+        Err(_) => path.to_path_buf(),
+    }
+}
+
+fn session_sources(config: &Config) -> Result<Vec<Box<dyn source::SessionSource>>> {
+    let home = std::env::var("HOME").context("HOME env var not set")?;
+    Ok(source::from_kinds(&config.sources, Path::new(&home), &projects_dirs(config)?))
+}
+
 fn config_path() -> Result<std::path::PathBuf> {
     let home = std::env::var("HOME").context("HOME env var not set")?;
     Ok(Path::new(&home)
@@ -122,6 +245,16 @@ impl Timeframe {
         }
     }
 
+    /// Parse a `--timeframe` value: "today", "7d" or "30d".
+    fn from_arg(arg: &str) -> Option<Self> {
+        match arg {
+            "today" => Some(Timeframe::Today),
+            "7d" => Some(Timeframe::Last7Days),
+            "30d" => Some(Timeframe::Last30Days),
+            _ => None,
+        }
+    }
+
     fn label(&self) -> &'static str {
         match self {
             Timeframe::Today => "Today",
@@ -161,51 +294,166 @@ impl Timeframe {
 
 struct ProjectSummary {
     project: String,
+    /// Shortest path suffix that no other listed project shares.
+    name: String,
     total_minutes: i64,
+    /// Time logged by hand, on top of `total_minutes`. Negative when
+    /// adjusted down.
+    manual_minutes: i64,
     last_activity: DateTime<Utc>,
     input_tokens: u64,
     output_tokens: u64,
     cache_creation_input_tokens: u64,
     cache_read_input_tokens: u64,
+    models: BTreeMap<String, parser::TokenUsage>,
+    /// Active time per git branch ("" for none).
+    branches: BTreeMap<String, TimeDelta>,
+    /// Share of the token totals spent by subagents.
+    subagent: parser::TokenUsage,
+    tools: BTreeMap<String, parser::ToolStats>,
+    lines: parser::LineChanges,
+    /// Conversations in the window, newest first. Subagents aren't listed.
+    sessions: Vec<SessionEntry>,
+}
+
+struct SessionEntry {
+    title: Option<String>,
+    start: DateTime<Utc>,
+    minutes: i64,
+    /// Logged by hand rather than read from a transcript.
+    manual: bool,
+}
+
+impl ProjectSummary {
+    fn new(project: &str, last_activity: DateTime<Utc>) -> Self {
+        Self {
+            project: project.to_string(),
+            name: String::new(),
+            total_minutes: 0,
+            manual_minutes: 0,
+            last_activity,
+            input_tokens: 0,
+            output_tokens: 0,
+            cache_creation_input_tokens: 0,
+            cache_read_input_tokens: 0,
+            models: BTreeMap::new(),
+            branches: BTreeMap::new(),
+            subagent: parser::TokenUsage::default(),
+            tools: BTreeMap::new(),
+            lines: parser::LineChanges::default(),
+            sessions: Vec::new(),
+        }
+    }
 }
 
-/// Group sessions by project path, sum durations, track latest end time.
-/// Sorted alphabetically by last path segment for stable display order.
-fn aggregate_sessions(sessions: &[parser::Session]) -> Vec<ProjectSummary> {
+/// Group sessions and manual time by project path, sum durations, track
+/// latest end time. Sorted alphabetically by short name for stable display
+/// order.
+fn aggregate_sessions(sessions: &[parser::Session], manual: &[store::ManualEntry]) -> Vec<ProjectSummary> {
     let mut map: HashMap<String, ProjectSummary> = HashMap::new();
 
     for session in sessions {
         let entry = map
             .entry(session.project.clone())
-            .or_insert_with(|| ProjectSummary {
-                project: session.project.clone(),
-                total_minutes: 0,
-                last_activity: session.end,
-                input_tokens: 0,
-                output_tokens: 0,
-                cache_creation_input_tokens: 0,
-                cache_read_input_tokens: 0,
-            });
+            .or_insert_with(|| ProjectSummary::new(&session.project, session.end));
         entry.total_minutes += session.duration.num_seconds() / 60;
+        entry.lines.add(&session.lines);
         entry.input_tokens += session.input_tokens;
         entry.output_tokens += session.output_tokens;
         entry.cache_creation_input_tokens += session.cache_creation_input_tokens;
         entry.cache_read_input_tokens += session.cache_read_input_tokens;
+        for (model, usage) in &session.models {
+            entry.models.entry(model.clone()).or_default().add(usage);
+        }
+        for (branch, time) in session.branch_durations() {
+            *entry.branches.entry(branch).or_insert(TimeDelta::zero()) += time;
+        }
+        for (tool, stats) in &session.tools {
+            entry.tools.entry(tool.clone()).or_default().add(stats);
+        }
+        if session.parent.is_some() {
+            entry.subagent.add(&parser::TokenUsage {
+                input_tokens: session.input_tokens,
+                output_tokens: session.output_tokens,
+                cache_creation_input_tokens: session.cache_creation_input_tokens,
+                cache_read_input_tokens: session.cache_read_input_tokens,
+            });
+        } else {
+            entry.sessions.push(SessionEntry {
+                title: session.title.clone(),
+                start: session.start,
+                minutes: session.duration.num_seconds() / 60,
+                manual: false,
+            });
+        }
         if session.end > entry.last_activity {
             entry.last_activity = session.end;
         }
     }
+    // Manual time isn't agent activity, so it doesn't move `last_activity`.
+    for manual in manual {
+        let entry = map
+            .entry(manual.project.clone())
+            .or_insert_with(|| ProjectSummary::new(&manual.project, manual.end));
+        let minutes = manual.duration.num_seconds() / 60;
+        entry.manual_minutes += minutes;
+        entry.sessions.push(SessionEntry { title: manual.note.clone(), start: manual.start, minutes, manual: true });
+    }
 
     let mut summaries: Vec<_> = map.into_values().collect();
-    summaries.sort_by(|a, b| last_segment(&a.project).cmp(last_segment(&b.project)));
+    let projects: Vec<&str> = summaries.iter().map(|s| s.project.as_str()).collect();
+    let names = short_names(&projects);
+    for (summary, name) in summaries.iter_mut().zip(names) {
+        summary.name = name;
+        summary.sessions.sort_by_key(|s| std::cmp::Reverse(s.start));
+    }
+    summaries.sort_by(|a, b| a.name.cmp(&b.name).then_with(|| a.project.cmp(&b.project)));
     summaries
 }
 
-pub(crate) fn last_segment(path: &str) -> &str {
-    Path::new(path)
-        .file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or(path)
+/// Project rows for the timeframe, from the store.
+fn load_summaries(store: &store::Store, aliases: &alias::Aliases, timeframe: Timeframe) -> Result<Vec<ProjectSummary>> {
+    let (start, end) = timeframe.boundaries();
+    Ok(aggregate_sessions(
+        &aliases.apply(store.query_range(start, end)?),
+        &aliases.apply_manual(store.manual_entries(start, end)?),
+    ))
+}
+
+/// The fewest trailing path segments that tell each project apart from the
+/// rest, e.g. "client-a/api" and "client-b/api" where both end in "api". A
+/// path that's the tail of another is shown in full.
+pub(crate) fn short_names(projects: &[&str]) -> Vec<String> {
+    let segments: Vec<Vec<&str>> = projects
+        .iter()
+        .map(|p| p.split('/').filter(|s| !s.is_empty()).collect())
+        .collect();
+    segments
+        .iter()
+        .enumerate()
+        .map(|(i, own)| {
+            (1..=own.len())
+                .map(|n| &own[own.len() - n..])
+                .find(|tail| !segments.iter().enumerate().any(|(j, other)| j != i && other.ends_with(tail)))
+                .map_or_else(|| projects[i].to_string(), |tail| tail.join("/"))
+        })
+        .collect()
+}
+
+/// Compact model name for the breakdown rows:
+/// "claude-opus-4-1-20250805" → "opus-4-1".
+fn model_label(model: &str) -> &str {
+    let name = model.strip_prefix("claude-").unwrap_or(model);
+    match name.rsplit_once('-') {
+        Some((base, date)) if date.len() == 8 && date.bytes().all(|b| b.is_ascii_digit()) => base,
+        _ => name,
+    }
+}
+
+/// Subagent figure shown in the table: novel input plus output, like the
+/// Input and Output columns (cache reads excluded).
+fn subagent_tokens(usage: &parser::TokenUsage) -> u64 {
+    usage.input_tokens + usage.cache_creation_input_tokens + usage.output_tokens
 }
 
 fn format_tokens(n: u64) -> String {
@@ -230,6 +478,39 @@ fn format_tokens(n: u64) -> String {
     }
 }
 
+/// "+1.2k / −300", or "–" when nothing was edited.
+fn format_lines(lines: &parser::LineChanges) -> String {
+    if lines.added == 0 && lines.removed == 0 {
+        return "–".to_string();
+    }
+    format!("+{} / −{}", format_tokens(lines.added), format_tokens(lines.removed))
+}
+
+/// Signed manual time: "+45m" or "−30m".
+fn format_manual(minutes: i64) -> String {
+    if minutes < 0 {
+        format!("−{}m", -minutes)
+    } else {
+        format!("+{}m", minutes)
+    }
+}
+
+/// Manual time in its own colour, so it isn't mistaken for tracked time.
+fn manual_cell(minutes: i64) -> Cell<'static> {
+    if minutes == 0 {
+        return Cell::new("");
+    }
+    Cell::new(Text::from(format_manual(minutes)).alignment(Alignment::Right)).style(Style::new().fg(Color::Yellow))
+}
+
+fn format_cost(usd: f64) -> String {
+    if usd >= 1_000.0 {
+        format!("${:.1}k", usd / 1_000.0)
+    } else {
+        format!("${:.2}", usd)
+    }
+}
+
 // --- Rendering ----------------------------------------------------------
 
 enum PendingAction {
@@ -237,6 +518,72 @@ enum PendingAction {
     Config,
 }
 
+/// What the screen is showing, driven by keypresses.
+struct ViewState {
+    timeframe: Timeframe,
+    show_models: bool,
+    /// Highlighted project row, once the cursor has been moved.
+    selected: Option<usize>,
+    /// Showing the selected project's detail instead of the table.
+    detail: bool,
+    /// Naming projects by full path rather than short name.
+    full_paths: bool,
+    /// Problem from the last scan, shown above the footer until a scan succeeds.
+    notice: Option<String>,
+    /// How far the initial import has got, shown in place of the spinner.
+    progress: Option<pool::Progress>,
+}
+
+impl ViewState {
+    fn new() -> Self {
+        Self {
+            timeframe: Timeframe::Today,
+            show_models: false,
+            selected: None,
+            detail: false,
+            full_paths: false,
+            notice: None,
+            progress: None,
+        }
+    }
+
+    /// Record the outcome of a scan: clear the notice, or describe what failed.
+    fn scan_finished(&mut self, result: &Result<ingest::IngestSummary>) {
+        self.progress = None;
+        self.notice = match result {
+            Ok(summary) => match summary.failed.as_slice() {
+                [] => None,
+                [only] => Some(format!("Skipped {}: {}", only.source_path, only.message)),
+                failed => Some(format!(
+                    "Skipped {} unreadable session files (run `claude-tracker doctor`)",
+                    failed.len()
+                )),
+            },
+            Err(e) => Some(format!("Scan failed: {:#}", e)),
+        };
+    }
+
+    /// Move the cursor by `delta` rows. The first move lands on the top row.
+    fn move_selection(&mut self, delta: isize, len: usize) {
+        self.selected = match (self.selected, len) {
+            (_, 0) => None,
+            (None, _) => Some(0),
+            (Some(i), len) => Some(i.saturating_add_signed(delta).min(len - 1)),
+        };
+    }
+
+    /// How a project is labelled: its short name, or full path once toggled.
+    fn project_label<'a>(&self, summary: &'a ProjectSummary) -> &'a str {
+        if self.full_paths { &summary.project } else { &summary.name }
+    }
+
+    /// Keep the cursor on a row after the project list is re-queried.
+    fn clamp_selection(&mut self, len: usize) {
+        self.selected = self.selected.filter(|_| len > 0).map(|i| i.min(len - 1));
+        self.detail &= self.selected.is_some();
+    }
+}
+
 enum KeyOutcome {
     Quit,
     OpenConfig,
@@ -267,7 +614,15 @@ fn handle_key(pending: &mut Option<PendingAction>, code: KeyCode) -> KeyOutcome
     }
 }
 
-fn render(f: &mut Frame, summaries: &[ProjectSummary], spinner: &spinner::Spinner, pending: &Option<PendingAction>, timeframe_label: &str) {
+fn render(f: &mut Frame, summaries: &[ProjectSummary], spinner: &spinner::Spinner, pending: &Option<PendingAction>, view: &ViewState, pricing: &pricing::PricingTable) {
+    if view.detail
+        && let Some(summary) = view.selected.and_then(|i| summaries.get(i))
+    {
+        return render_detail(f, summary, spinner, pending, view);
+    }
+    let show_models = view.show_models;
+    let timeframe_label = view.timeframe.label();
+
     let most_recent_idx = summaries
         .iter()
         .enumerate()
@@ -275,14 +630,26 @@ fn render(f: &mut Frame, summaries: &[ProjectSummary], spinner: &spinner::Spinne
         .map(|(i, _)| i);
 
     let total_minutes: i64 = summaries.iter().map(|s| s.total_minutes).sum();
+    let total_manual_minutes: i64 = summaries.iter().map(|s| s.manual_minutes).sum();
     let total_input_tokens: u64 = summaries.iter().map(|s| s.input_tokens + s.cache_creation_input_tokens).sum();
     let total_output_tokens: u64 = summaries.iter().map(|s| s.output_tokens).sum();
     let total_cache_read_tokens: u64 = summaries.iter().map(|s| s.cache_read_input_tokens).sum();
+    let total_subagent_tokens: u64 = summaries.iter().map(|s| subagent_tokens(&s.subagent)).sum();
+    let mut total_lines = parser::LineChanges::default();
+    for s in summaries {
+        total_lines.add(&s.lines);
+    }
+    let total_cost: f64 = summaries.iter().map(|s| pricing.total_cost(&s.models)).sum();
+    let model_rows: usize = if show_models {
+        summaries.iter().map(|s| s.models.len()).sum()
+    } else {
+        0
+    };
 
     let chunks = Layout::vertical([
         Constraint::Length(1),                       // header
         Constraint::Length(1),                       // blank
-        Constraint::Length(summaries.len() as u16 + 3), // table + header + border
+        Constraint::Length((summaries.len() + model_rows) as u16 + 3), // table + header + border
         Constraint::Length(1),                       // blank
         Constraint::Length(1),                       // totals
         Constraint::Length(1),                       // blank
@@ -293,7 +660,7 @@ fn render(f: &mut Frame, summaries: &[ProjectSummary], spinner: &spinner::Spinne
 
     // Header
     f.render_widget(
-        Paragraph::new(format!("claude-tracker  {}", spinner.current()))
+        Paragraph::new(header(spinner, view))
             .style(Style::new().bold()),
         chunks[0],
     );
@@ -302,8 +669,8 @@ fn render(f: &mut Frame, summaries: &[ProjectSummary], spinner: &spinner::Spinne
     let rows: Vec<Row> = summaries
         .iter()
         .enumerate()
-        .map(|(i, s)| {
-            let name = last_segment(&s.project);
+        .flat_map(|(i, s)| {
+            let name = view.project_label(s);
             let name_cell = if Some(i) == most_recent_idx {
                 Cell::new(Text::from(Line::from(vec![
                     Span::styled("  ● ", Style::new().fg(Color::Green)),
@@ -312,13 +679,38 @@ fn render(f: &mut Frame, summaries: &[ProjectSummary], spinner: &spinner::Spinne
             } else {
                 Cell::new(format!("    {}", name))
             };
-            Row::new([
+            let row_style = if Some(i) == view.selected {
+                Style::new().reversed()
+            } else {
+                Style::new()
+            };
+            let project_row = Row::new([
                 name_cell,
                 Cell::new(Text::from(format!("{}m ({}h {}m)", s.total_minutes, s.total_minutes / 60, s.total_minutes % 60)).alignment(Alignment::Right)),
+                manual_cell(s.manual_minutes),
+                Cell::new(Text::from(format_lines(&s.lines)).alignment(Alignment::Right)),
                 Cell::new(Text::from(format_tokens(s.input_tokens + s.cache_creation_input_tokens)).alignment(Alignment::Right)),
                 Cell::new(Text::from(format_tokens(s.output_tokens)).alignment(Alignment::Right)),
                 Cell::new(Text::from(format_tokens(s.cache_read_input_tokens)).alignment(Alignment::Right)),
+                Cell::new(Text::from(format_tokens(subagent_tokens(&s.subagent))).alignment(Alignment::Right)),
+                Cell::new(Text::from(format_cost(pricing.total_cost(&s.models))).alignment(Alignment::Right)),
             ])
+            .style(row_style);
+            let model_rows = s.models.iter().filter(|_| show_models).map(|(model, usage)| {
+                Row::new([
+                    Cell::new(format!("      └ {}", model_label(model))),
+                    Cell::new(""),
+                    Cell::new(""),
+                    Cell::new(""),
+                    Cell::new(Text::from(format_tokens(usage.input_tokens + usage.cache_creation_input_tokens)).alignment(Alignment::Right)),
+                    Cell::new(Text::from(format_tokens(usage.output_tokens)).alignment(Alignment::Right)),
+                    Cell::new(Text::from(format_tokens(usage.cache_read_input_tokens)).alignment(Alignment::Right)),
+                    Cell::new(""),
+                    Cell::new(Text::from(format_cost(pricing.cost(model, usage))).alignment(Alignment::Right)),
+                ])
+                .style(Style::new().dim())
+            });
+            std::iter::once(project_row).chain(model_rows)
         })
         .collect();
 
@@ -331,18 +723,26 @@ fn render(f: &mut Frame, summaries: &[ProjectSummary], spinner: &spinner::Spinne
     let header = Row::new([
         Cell::new(""),
         Cell::new(Text::from("Time").alignment(Alignment::Right)).style(Style::new().italic()),
+        Cell::new(Text::from("Manual").alignment(Alignment::Right)).style(Style::new().italic()),
+        Cell::new(Text::from("Lines").alignment(Alignment::Right)).style(Style::new().italic()),
         Cell::new(Text::from("Input").alignment(Alignment::Right)).style(Style::new().italic()),
         Cell::new(Text::from("Output").alignment(Alignment::Right)).style(Style::new().italic()),
         Cell::new(Text::from("Cache").alignment(Alignment::Right)).style(Style::new().italic()),
+        Cell::new(Text::from("Agents").alignment(Alignment::Right)).style(Style::new().italic()),
+        Cell::new(Text::from("Cost").alignment(Alignment::Right)).style(Style::new().italic()),
     ])
     .style(Style::new().bold());
 
     let table = Table::new(rows, [
         Constraint::Fill(1),
         Constraint::Min(12), // "time" / "98m (1h 38m)"
+        Constraint::Min(6),  // "manual" / "+120m"
+        Constraint::Min(12), // "lines" / "+1.2k / −300"
         Constraint::Min(5),  // "input" / "30.8M"
         Constraint::Min(6),  // "output" / "2.9k"
         Constraint::Min(5),  // "cache" / "44.2M"
+        Constraint::Min(6),  // "agents" / "1.2M"
+        Constraint::Min(7),  // "cost" / "$123.45"
     ])
     .header(header)
     .block(block)
@@ -353,27 +753,219 @@ fn render(f: &mut Frame, summaries: &[ProjectSummary], spinner: &spinner::Spinne
     // Totals
     f.render_widget(
         Paragraph::new(format!(
-            "  {}: {}m  ({}h {}m)  {} in  {} out  {} cache",
+            "  {}: {}m  ({}h {}m){}  {} lines  {} in  {} out  {} cache  {} agents  {}",
             timeframe_label,
             total_minutes,
             total_minutes / 60,
             total_minutes % 60,
+            if total_manual_minutes == 0 {
+                String::new()
+            } else {
+                format!("  {} manual", format_manual(total_manual_minutes))
+            },
+            format_lines(&total_lines),
             format_tokens(total_input_tokens),
             format_tokens(total_output_tokens),
             format_tokens(total_cache_read_tokens),
+            format_tokens(total_subagent_tokens),
+            format_cost(total_cost),
         )),
         chunks[4],
     );
 
-    // Footer
+    render_notice(f, chunks[5], &view.notice);
+    render_footer(f, chunks[6], pending, "  t timeframe · m models · p paths · ↑↓ select · enter details · r refresh · c config · q quit");
+}
+
+/// Title line: import progress while it's known, else the spinner.
+fn header(spinner: &spinner::Spinner, view: &ViewState) -> String {
+    match view.progress {
+        Some(p) => format!("claude-tracker  {} {}/{} files", p.task, p.done, p.total),
+        None => format!("claude-tracker  {}", spinner.current()),
+    }
+}
+
+/// Drill-down for the selected project: active time per git branch, tool
+/// usage and the sessions themselves.
+fn render_detail(f: &mut Frame, summary: &ProjectSummary, spinner: &spinner::Spinner, pending: &Option<PendingAction>, view: &ViewState) {
+    let mut branches: Vec<_> = summary.branches.iter().collect();
+    branches.sort_by(|a, b| b.1.cmp(a.1).then_with(|| a.0.cmp(b.0)));
+    let mut tools: Vec<_> = summary.tools.iter().collect();
+    tools.sort_by(|a, b| b.1.calls.cmp(&a.1.calls).then_with(|| a.0.cmp(b.0)));
+
+    let chunks = Layout::vertical([
+        Constraint::Length(1),                           // header
+        Constraint::Length(1),                           // blank
+        Constraint::Length(branches.len() as u16 + 3),   // branches + header + border
+        Constraint::Length(tools.len() as u16 + 3),      // tools + header + border
+        Constraint::Length(summary.sessions.len() as u16 + 3), // sessions + header + border
+        Constraint::Length(1),                           // blank
+        Constraint::Length(1),                           // totals
+        Constraint::Length(1),                           // blank
+        Constraint::Length(1),                           // footer
+        Constraint::Fill(1),                             // remaining
+    ])
+    .split(f.area());
+
+    f.render_widget(
+        Paragraph::new(header(spinner, view))
+            .style(Style::new().bold()),
+        chunks[0],
+    );
+
+    let rows: Vec<Row> = branches
+        .iter()
+        .map(|(branch, time)| {
+            let minutes = time.num_minutes();
+            Row::new([
+                Cell::new(format!("    {}", report::branch_label(branch))),
+                Cell::new(Text::from(format!("{}m ({}h {}m)", minutes, minutes / 60, minutes % 60)).alignment(Alignment::Right)),
+            ])
+        })
+        .collect();
+
+    let block = Block::new()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::new().fg(Color::DarkGray))
+        .title(format!(" {} ", view.project_label(summary)));
+
+    let header = Row::new([
+        Cell::new("    Branch").style(Style::new().italic()),
+        Cell::new(Text::from("Time").alignment(Alignment::Right)).style(Style::new().italic()),
+    ])
+    .style(Style::new().bold());
+
+    let table = Table::new(rows, [
+        Constraint::Fill(1),
+        Constraint::Min(12), // "time" / "98m (1h 38m)"
+    ])
+    .header(header)
+    .block(block)
+    .column_spacing(2);
+
+    f.render_widget(table, chunks[2]);
+
+    let rows: Vec<Row> = tools
+        .iter()
+        .map(|(tool, stats)| {
+            let errors = if stats.errors > 0 {
+                Cell::new(Text::from(stats.errors.to_string()).alignment(Alignment::Right)).style(Style::new().fg(Color::Red))
+            } else {
+                Cell::new(Text::from("0").alignment(Alignment::Right)).style(Style::new().dim())
+            };
+            Row::new([
+                Cell::new(format!("    {}", tool)),
+                Cell::new(Text::from(stats.calls.to_string()).alignment(Alignment::Right)),
+                errors,
+            ])
+        })
+        .collect();
+
+    let block = Block::new()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::new().fg(Color::DarkGray))
+        .title(" tools ");
+
+    let header = Row::new([
+        Cell::new("    Tool").style(Style::new().italic()),
+        Cell::new(Text::from("Calls").alignment(Alignment::Right)).style(Style::new().italic()),
+        Cell::new(Text::from("Errors").alignment(Alignment::Right)).style(Style::new().italic()),
+    ])
+    .style(Style::new().bold());
+
+    let table = Table::new(rows, [
+        Constraint::Fill(1),
+        Constraint::Min(6), // "calls" / "12345"
+        Constraint::Min(6), // "errors" / "123"
+    ])
+    .header(header)
+    .block(block)
+    .column_spacing(2);
+
+    f.render_widget(table, chunks[3]);
+
+    let rows: Vec<Row> = summary
+        .sessions
+        .iter()
+        .map(|session| {
+            let started = session.start.with_timezone(&Local).format("%a %d %b %H:%M").to_string();
+            if session.manual {
+                return Row::new([
+                    Cell::new(format!("  ✎ {}", session.title.as_deref().unwrap_or("(manual)"))),
+                    Cell::new(Text::from(started).alignment(Alignment::Right)),
+                    Cell::new(Text::from(format_manual(session.minutes)).alignment(Alignment::Right)),
+                ])
+                .style(Style::new().fg(Color::Yellow));
+            }
+            Row::new([
+                Cell::new(format!("    {}", session.title.as_deref().unwrap_or("(untitled)"))),
+                Cell::new(Text::from(started).alignment(Alignment::Right)),
+                Cell::new(Text::from(format!("{}m", session.minutes)).alignment(Alignment::Right)),
+            ])
+        })
+        .collect();
+
+    let block = Block::new()
+        .borders(Borders::ALL)
+        .border_type(BorderType::Rounded)
+        .border_style(Style::new().fg(Color::DarkGray))
+        .title(" sessions ");
+
+    let header = Row::new([
+        Cell::new("    Title").style(Style::new().italic()),
+        Cell::new(Text::from("Started").alignment(Alignment::Right)).style(Style::new().italic()),
+        Cell::new(Text::from("Time").alignment(Alignment::Right)).style(Style::new().italic()),
+    ])
+    .style(Style::new().bold());
+
+    let table = Table::new(rows, [
+        Constraint::Fill(1),
+        Constraint::Min(16), // "Tue 03 Feb 10:00"
+        Constraint::Min(5),  // "time" / "120m" / "−30m"
+    ])
+    .header(header)
+    .block(block)
+    .column_spacing(2);
+
+    f.render_widget(table, chunks[4]);
+
+    f.render_widget(
+        Paragraph::new(format!(
+            "  {}: {}m  ({}h {}m)  {}",
+            view.timeframe.label(),
+            summary.total_minutes,
+            summary.total_minutes / 60,
+            summary.total_minutes % 60,
+            summary.project,
+        )),
+        chunks[6],
+    );
+
+    render_notice(f, chunks[7], &view.notice);
+    render_footer(f, chunks[8], pending, "  esc back · t timeframe · p paths · r refresh · q quit");
+}
+
+fn render_notice(f: &mut Frame, area: ratatui::layout::Rect, notice: &Option<String>) {
+    if let Some(notice) = notice {
+        f.render_widget(
+            Paragraph::new(format!("  {}", notice)).style(Style::new().red()),
+            area,
+        );
+    }
+}
+
+/// Key hints, or the confirmation prompt for a pending action.
+fn render_footer(f: &mut Frame, area: ratatui::layout::Rect, pending: &Option<PendingAction>, hints: &str) {
     let (footer_text, footer_style) = match pending {
         Some(PendingAction::Quit) => ("  Quit? Press Enter to confirm", Style::new()),
         Some(PendingAction::Config) => ("  Open config? Press Enter to confirm", Style::new()),
-        None => ("  t timeframe · r refresh · c config · q quit", Style::new().dim()),
+        None => (hints, Style::new().dim()),
     };
     f.render_widget(
         Paragraph::new(footer_text).style(footer_style),
-        chunks[6],
+        area,
     );
 }
 
@@ -393,43 +985,37 @@ fn teardown(term: &mut Terminal<CrosstermBackend<io::Stdout>>) -> Result<()> {
     Ok(())
 }
 
-// --- Data loading -------------------------------------------------------
-
-fn scan_and_parse(
-    projects_dir: &Path,
-    idle_threshold: TimeDelta,
-) -> Result<Vec<(String, parser::Session)>> {
-    let session_files = scanner::find_session_files(projects_dir);
-    let mut results = Vec::new();
-
-    for file_path in &session_files {
-        let contents = std::fs::read_to_string(file_path)
-            .with_context(|| format!("reading {:?}", file_path))?;
-
-        let messages: Vec<parser::ParsedMessage> = contents
-            .lines()
-            .filter_map(parser::parse_message)
-            .collect();
-
-        if let Some(session) = parser::assemble_session(&messages, idle_threshold) {
-            let source_path = file_path
-                .strip_prefix(projects_dir)
-                .with_context(|| format!("stripping prefix from {:?}", file_path))?
-                .to_str()
-                .context("non-UTF8 path")?;
-            results.push((source_path.to_string(), session));
-        }
-    }
-
-    Ok(results)
-}
-
 // --- Entry point --------------------------------------------------------
 
 use std::time::{Duration, Instant};
 
+/// Sent from the background scan thread to the UI.
+enum ScanEvent {
+    Progress(pool::Progress),
+    Finished(Result<ingest::IngestSummary>),
+}
+
 const TICK_RATE: Duration = Duration::from_millis(100);
+/// Poll interval when no file watcher is available.
 const REFRESH_INTERVAL: Duration = Duration::from_secs(2);
+/// Safety-net rescan while the watcher is active, in case an event is missed.
+const WATCHED_REFRESH_INTERVAL: Duration = Duration::from_secs(60);
+
+/// Pick up new transcript lines, then archive files that have settled.
+/// Archive problems are reported alongside parse failures.
+fn refresh(
+    store: &store::Store,
+    db_path: &Path,
+    sources: &[Box<dyn source::SessionSource>],
+    idle_threshold: TimeDelta,
+    workers: &pool::Workers,
+) -> Result<ingest::IngestSummary> {
+    let mut summary = ingest::ingest(store, sources, idle_threshold, workers)?;
+    let archive = archive::Archive::new(&db_path.with_file_name("archive"));
+    let archived = archive.update(store, sources, Utc::now().timestamp_millis(), workers)?;
+    summary.failed.extend(archived.failed);
+    Ok(summary)
+}
 
 fn run_setup() -> Result<()> {
     print!("Enter Clockify API key: ");
@@ -463,7 +1049,86 @@ fn main() -> Result<()> {
                         .action(clap::ArgAction::SetTrue)
                 )
         )
-        .subcommand(Command::new("list-projects").about("List all Clockify projects with their IDs"));
+        .subcommand(Command::new("list-projects").about("List all Clockify projects with their IDs"))
+        .subcommand(
+            Command::new("report")
+                .about("Print tracked time by branch, tool calls by tool, or activity by hour or day")
+                .arg(
+                    clap::Arg::new("by")
+                        .long("by")
+                        .value_parser(["branch", "tool", "hour", "day"])
+                        .default_value("branch")
+                        .help("What to group time by")
+                )
+                .arg(
+                    clap::Arg::new("timeframe")
+                        .long("timeframe")
+                        .value_parser(["today", "7d", "30d"])
+                        .default_value("7d")
+                        .help("Window to report on")
+                )
+        )
+        .subcommand(Command::new("doctor").about("Check config, database, keychain and session files for problems"))
+        .subcommand(
+            Command::new("reparse")
+                .about("Rebuild all sessions from archived transcripts and the files on disk")
+                .arg(
+                    clap::Arg::new("from-archive")
+                        .long("from-archive")
+                        .help("Start from archived transcripts, including ones Claude Code has since deleted (the default)")
+                        .action(clap::ArgAction::SetTrue)
+                )
+                .arg(
+                    clap::Arg::new("live-only")
+                        .long("live-only")
+                        .help("Rebuild from files still on disk only, dropping sessions whose transcripts are gone")
+                        .conflicts_with("from-archive")
+                        .action(clap::ArgAction::SetTrue)
+                )
+        )
+        .subcommand(
+            Command::new("add")
+                .about("Log time that left no transcript")
+                .arg(
+                    clap::Arg::new("project")
+                        .long("project")
+                        .required(true)
+                        .help("Project directory the time counts towards")
+                )
+                .arg(
+                    clap::Arg::new("start")
+                        .long("start")
+                        .required(true)
+                        .help("Local start time: HH:MM today, or YYYY-MM-DD HH:MM")
+                )
+                .arg(
+                    clap::Arg::new("end")
+                        .long("end")
+                        .required(true)
+                        .help("Local end time: HH:MM today, or YYYY-MM-DD HH:MM")
+                )
+                .arg(clap::Arg::new("note").long("note").help("What the time was spent on"))
+        )
+        .subcommand(
+            Command::new("adjust")
+                .about("Add or take away minutes from a project's tracked time")
+                .arg(
+                    clap::Arg::new("project")
+                        .long("project")
+                        .required(true)
+                        .help("Project directory to adjust")
+                )
+                .arg(
+                    clap::Arg::new("minutes")
+                        .long("minutes")
+                        .required(true)
+                        .allow_negative_numbers(true)
+                        .value_parser(clap::value_parser!(i64))
+                        .help("Minutes to add, or take away if negative")
+                )
+                .arg(clap::Arg::new("date").long("date").help("Day to adjust, YYYY-MM-DD (default today)"))
+                .arg(clap::Arg::new("note").long("note").help("Why the time was adjusted"))
+        );
     let matches = cli.get_matches();
 
     if matches.subcommand_matches("setup").is_some() {
@@ -473,14 +1138,106 @@ fn main() -> Result<()> {
     if let Some(sync_matches) = matches.subcommand_matches("sync") {
         ensure_config_exists()?;
         let config = load_config()?;
-        let sync_config = config.sync.context("Missing [sync] section in config.toml")?;
 
         let db_path = config_path()?.with_file_name("sessions.db");
         let store = store::Store::new(&db_path)?;
+        let aliases = project_aliases(&config, &store)?;
+        let sync_config = config.sync.context("Missing [sync] section in config.toml")?;
 
         let dry_run = sync_matches.get_flag("dry-run");
 
-        return sync::run_sync(&store, &sync_config, dry_run);
+        return sync::run_sync(&store, &sync_config, &aliases, dry_run);
+    }
+
+    if matches.subcommand_matches("doctor").is_some() {
+        let path = config_path()?;
+        let config = load_config();
+        let sync_config = config.as_ref().map(|c| c.sync.as_ref());
+        let sources = session_sources(config.as_ref().unwrap_or(&Config::default()))?;
+        return doctor::run_doctor(&path, sync_config, &path.with_file_name("sessions.db"), &sources);
+    }
+
+    if let Some(add_matches) = matches.subcommand_matches("add") {
+        ensure_config_exists()?;
+        let config = load_config()?;
+        let store = store::Store::new(&config_path()?.with_file_name("sessions.db"))?;
+        let arg = |name| add_matches.get_one::<String>(name).map(String::as_str);
+        print_logged(&manual::run_add(
+            &store,
+            arg("project").expect("required"),
+            arg("start").expect("required"),
+            arg("end").expect("required"),
+            arg("note"),
+            config.sync.as_ref().map(|s| s.workspace_id.as_str()),
+        )?);
+        return Ok(());
+    }
+
+    if let Some(adjust_matches) = matches.subcommand_matches("adjust") {
+        ensure_config_exists()?;
+        let config = load_config()?;
+        let store = store::Store::new(&config_path()?.with_file_name("sessions.db"))?;
+        let arg = |name| adjust_matches.get_one::<String>(name).map(String::as_str);
+        print_logged(&manual::run_adjust(
+            &store,
+            arg("project").expect("required"),
+            *adjust_matches.get_one::<i64>("minutes").expect("required"),
+            arg("date"),
+            arg("note"),
+            config.sync.as_ref().map(|s| s.workspace_id.as_str()),
+        )?);
+        return Ok(());
+    }
+
+    if let Some(reparse_matches) = matches.subcommand_matches("reparse") {
+        ensure_config_exists()?;
+        let config = load_config()?;
+        let idle_threshold = TimeDelta::minutes(config.idle_timeout_minutes as i64);
+        let sources = session_sources(&config)?;
+        let workers = pool::Workers { threads: scan_threads(&config), ..Default::default() };
+
+        let db_path = config_path()?.with_file_name("sessions.db");
+        let store = store::Store::new(&db_path)?;
+        let from_archive = if reparse_matches.get_flag("live-only") {
+            eprintln!("warning: dropping every session whose transcript is no longer on disk");
+            store.clear_sessions()?;
+            ingest::IngestSummary::default()
+        } else {
+            let archive = archive::Archive::new(&db_path.with_file_name("archive"));
+            ingest::reparse_from_archive(&store, &sources, &archive, idle_threshold, &workers)?
+        };
+        let live = refresh(&store, &db_path, &sources, idle_threshold, &workers)?;
+
+        for failure in from_archive.failed.iter().chain(&live.failed) {
+            eprintln!("warning: skipped {}: {}", failure.source_path, failure.message);
+        }
+        println!(
+            "Rebuilt sessions from {} archived and {} on-disk files.",
+            from_archive.updated, live.updated
+        );
+        return Ok(());
+    }
+
+    if let Some(report_matches) = matches.subcommand_matches("report") {
+        ensure_config_exists()?;
+        let config = load_config()?;
+        let idle_threshold = TimeDelta::minutes(config.idle_timeout_minutes as i64);
+
+        let db_path = config_path()?.with_file_name("sessions.db");
+        let store = store::Store::new(&db_path)?;
+        let workers = pool::Workers { threads: scan_threads(&config), ..Default::default() };
+        let ingested = refresh(&store, &db_path, &session_sources(&config)?, idle_threshold, &workers)?;
+        for failure in &ingested.failed {
+            eprintln!("warning: skipped {}: {}", failure.source_path, failure.message);
+        }
+
+        let by = report_matches.get_one::<String>("by").expect("has default");
+        let timeframe = report_matches
+            .get_one::<String>("timeframe")
+            .and_then(|arg| Timeframe::from_arg(arg))
+            .expect("has default");
+        let (start, end) = timeframe.boundaries();
+        return report::run_report(&store, &project_aliases(&config, &store)?, by, timeframe.label(), start, end);
     }
 
     if matches.subcommand_matches("list-projects").is_some() {
@@ -523,28 +1280,34 @@ fn main() -> Result<()> {
         return Ok(());
     }
 
-    let home = std::env::var("HOME").context("HOME env var not set")?;
-    let projects_dir = Path::new(&home).join(".claude").join("projects");
-
     ensure_config_exists()?;
     let config = load_config()?;
     let idle_threshold = TimeDelta::minutes(config.idle_timeout_minutes as i64);
+    let pricing = pricing::PricingTable::new(&config.pricing);
+    let sources = std::sync::Arc::new(session_sources(&config)?);
+    let threads = scan_threads(&config);
 
     let db_path = config_path()?.with_file_name("sessions.db");
     let store = store::Store::new(&db_path)?;
+    let aliases = project_aliases(&config, &store)?;
 
-    for (source_path, session) in scan_and_parse(&projects_dir, idle_threshold)? {
-        store.upsert(&source_path, &session)?;
-    }
-
-    let mut timeframe = Timeframe::Today;
-    let mut summaries = {
-        let (start, end) = timeframe.boundaries();
-        aggregate_sessions(&store.query_range(start, end)?)
-    };
+    // Show what's already stored while the first scan runs in the
+    // background.
+    let mut view = ViewState::new();
+    let mut summaries = load_summaries(&store, &aliases, view.timeframe)?;
     let mut spinner = spinner::Spinner::new();
     let mut last_refresh = Instant::now();
 
+    // Rescan when a session file changes; fall back to fixed polling if no
+    // source directory can be watched (e.g. none exist yet).
+    let roots: Vec<&Path> = sources.iter().map(|s| s.root()).collect();
+    let session_watcher = watcher::SessionWatcher::new(&roots).ok();
+    let refresh_interval = if session_watcher.is_some() {
+        WATCHED_REFRESH_INTERVAL
+    } else {
+        REFRESH_INTERVAL
+    };
+
     // Restore terminal on panic so we don't leave alternate screen active
     let hook = std::panic::take_hook();
     std::panic::set_hook(Box::new(move |info| {
@@ -555,72 +1318,112 @@ fn main() -> Result<()> {
 
     let mut term = setup()?;
 
-    let (tx, rx) = mpsc::channel::<Result<Vec<(String, parser::Session)>>>();
+    let (tx, rx) = mpsc::channel::<ScanEvent>();
     let mut scan_in_progress = false;
-    let mut needs_refresh = false;
+    let mut needs_refresh = true;
+    let mut first_scan = true;
     let mut pending: Option<PendingAction> = None;
 
     loop {
-        term.draw(|f| render(f, &summaries, &spinner, &pending, timeframe.label()))?;
-
-        // Process completed background scan
-        if let Ok(result) = rx.try_recv() {
-            scan_in_progress = false;
-            if let Ok(sessions) = result {
-                for (source_path, session) in sessions {
-                    store.upsert(&source_path, &session)?;
+        term.draw(|f| render(f, &summaries, &spinner, &pending, &view, &pricing))?;
+
+        // Process completed background scan. The scan thread writes through
+        // its own connection, so we just re-query.
+        while let Ok(event) = rx.try_recv() {
+            let result = match event {
+                ScanEvent::Progress(progress) => {
+                    view.progress = Some(progress);
+                    continue;
                 }
-                let (start, end) = timeframe.boundaries();
-                summaries = aggregate_sessions(&store.query_range(start, end)?);
+                ScanEvent::Finished(result) => result,
+            };
+            scan_in_progress = false;
+            view.scan_finished(&result);
+            if result.is_ok() {
+                summaries = load_summaries(&store, &aliases, view.timeframe)?;
+                view.clamp_selection(summaries.len());
             }
             last_refresh = Instant::now();
         }
 
-        if event::poll(TICK_RATE)? {
-            if let Event::Key(k) = event::read()? {
-                let had_pending = pending.is_some();
-                match handle_key(&mut pending, k.code) {
-                    KeyOutcome::Quit => break,
-                    KeyOutcome::OpenConfig => {
-                        teardown(&mut term)?;
-                        open_config_in_editor()?;
-                        return Ok(());
+        if event::poll(TICK_RATE)?
+            && let Event::Key(k) = event::read()?
+        {
+            let had_pending = pending.is_some();
+            match handle_key(&mut pending, k.code) {
+                KeyOutcome::Quit => break,
+                KeyOutcome::OpenConfig => {
+                    teardown(&mut term)?;
+                    open_config_in_editor()?;
+                    return Ok(());
+                }
+                // Only process r/c when the key wasn't consumed by pending logic.
+                KeyOutcome::Continue if !had_pending => match k.code {
+                    KeyCode::Char('r') if !scan_in_progress => {
+                        spinner.reset();
+                        needs_refresh = true;
+                    }
+                    KeyCode::Char('c') => {
+                        pending = Some(PendingAction::Config);
+                    }
+                    KeyCode::Char('t') => {
+                        view.timeframe = view.timeframe.next();
+                        summaries = load_summaries(&store, &aliases, view.timeframe)?;
+                        view.clamp_selection(summaries.len());
+                    }
+                    KeyCode::Char('m') => {
+                        view.show_models = !view.show_models;
+                    }
+                    KeyCode::Char('p') => {
+                        view.full_paths = !view.full_paths;
+                    }
+                    KeyCode::Up | KeyCode::Char('k') => {
+                        view.move_selection(-1, summaries.len());
+                    }
+                    KeyCode::Down | KeyCode::Char('j') => {
+                        view.move_selection(1, summaries.len());
+                    }
+                    KeyCode::Enter => {
+                        view.detail = view.selected.is_some();
+                    }
+                    KeyCode::Esc => {
+                        view.detail = false;
                     }
-                    // Only process r/c when the key wasn't consumed by pending logic.
-                    KeyOutcome::Continue if !had_pending => match k.code {
-                        KeyCode::Char('r') => {
-                            if !scan_in_progress {
-                                spinner.reset();
-                                needs_refresh = true;
-                            }
-                        }
-                        KeyCode::Char('c') => {
-                            pending = Some(PendingAction::Config);
-                        }
-                        KeyCode::Char('t') => {
-                            timeframe = timeframe.next();
-                            let (start, end) = timeframe.boundaries();
-                            summaries = aggregate_sessions(&store.query_range(start, end)?);
-                        }
-                        _ => {}
-                    },
                     _ => {}
-                }
+                },
+                _ => {}
             }
         }
 
         spinner.tick();
 
+        if session_watcher.as_ref().is_some_and(|w| w.changed()) {
+            needs_refresh = true;
+        }
+
         // Spawn background scan if not already running and due
-        if !scan_in_progress && (needs_refresh || last_refresh.elapsed() >= REFRESH_INTERVAL) {
-            let dir = projects_dir.clone();
+        if !scan_in_progress && (needs_refresh || last_refresh.elapsed() >= refresh_interval) {
+            let sources = sources.clone();
+            let db = db_path.clone();
             let threshold = idle_threshold;
             let sender = tx.clone();
+            // Only the initial import is slow enough to be worth a progress
+            // count; later scans keep the spinner.
+            let show_progress = first_scan;
             std::thread::spawn(move || {
-                let _ = sender.send(scan_and_parse(&dir, threshold));
+                let progress = |progress| {
+                    if show_progress {
+                        let _ = sender.send(ScanEvent::Progress(progress));
+                    }
+                };
+                let workers = pool::Workers { threads, progress: &progress };
+                let result = store::Store::new(&db)
+                    .and_then(|store| refresh(&store, &db, &sources, threshold, &workers));
+                let _ = sender.send(ScanEvent::Finished(result));
             });
             scan_in_progress = true;
             needs_refresh = false;
+            first_scan = false;
         }
     }
 
@@ -645,6 +1448,12 @@ mod tests {
             output_tokens: 0,
             cache_creation_input_tokens: 0,
             cache_read_input_tokens: 0,
+            models: Default::default(),
+            intervals: Vec::new(),
+            parent: None,
+            tools: Default::default(),
+            lines: Default::default(),
+            title: None,
         }
     }
 
@@ -667,6 +1476,12 @@ mod tests {
             output_tokens: output,
             cache_creation_input_tokens: cache_create,
             cache_read_input_tokens: cache_read,
+            models: Default::default(),
+            intervals: Vec::new(),
+            parent: None,
+            tools: Default::default(),
+            lines: Default::default(),
+            title: None,
         }
     }
 
@@ -677,7 +1492,7 @@ mod tests {
             session("/work/api", "2026-02-03T11:00:00Z", 1200), // 20m
         ];
 
-        let out = aggregate_sessions(&input);
+        let out = aggregate_sessions(&input, &[]);
 
         assert_eq!(out.len(), 1);
         assert_eq!(out[0].total_minutes, 30);
@@ -692,7 +1507,7 @@ mod tests {
             session("/work/api", "2026-02-03T11:00:00Z", 600),
         ];
 
-        let out = aggregate_sessions(&input);
+        let out = aggregate_sessions(&input, &[]);
 
         assert_eq!(out[0].last_activity, late);
     }
@@ -704,7 +1519,7 @@ mod tests {
             session("/work/cli", "2026-02-03T10:00:00Z", 1800), // 30m
         ];
 
-        let out = aggregate_sessions(&input);
+        let out = aggregate_sessions(&input, &[]);
 
         assert_eq!(out.len(), 2);
         let api = out.iter().find(|s| s.project == "/work/api").unwrap();
@@ -722,7 +1537,7 @@ mod tests {
             session_with_tokens("/work/api", "2026-02-03T11:00:00Z", 600, 50, 25, 100, 150),
         ];
 
-        let out = aggregate_sessions(&input);
+        let out = aggregate_sessions(&input, &[]);
 
         assert_eq!(out.len(), 1);
         assert_eq!(out[0].input_tokens, 150);
@@ -738,7 +1553,7 @@ mod tests {
             session_with_tokens("/work/cli", "2026-02-03T10:00:00Z", 600, 200, 75, 0, 0),
         ];
 
-        let out = aggregate_sessions(&input);
+        let out = aggregate_sessions(&input, &[]);
 
         let api = out.iter().find(|s| s.project == "/work/api").unwrap();
         let cli = out.iter().find(|s| s.project == "/work/cli").unwrap();
@@ -748,6 +1563,149 @@ mod tests {
         assert_eq!(cli.output_tokens, 75);
     }
 
+    #[test]
+    fn aggregates_model_breakdown_per_project() {
+        let usage = |output| parser::TokenUsage { output_tokens: output, ..Default::default() };
+        let mut a = session("/work/api", "2026-02-03T10:00:00Z", 600);
+        a.models.insert("claude-opus-4-1".into(), usage(100));
+        let mut b = session("/work/api", "2026-02-03T11:00:00Z", 600);
+        b.models.insert("claude-opus-4-1".into(), usage(50));
+        b.models.insert("claude-haiku-4-5".into(), usage(7));
+
+        let out = aggregate_sessions(&[a, b], &[]);
+
+        assert_eq!(out[0].models.len(), 2);
+        assert_eq!(out[0].models["claude-opus-4-1"].output_tokens, 150);
+        assert_eq!(out[0].models["claude-haiku-4-5"].output_tokens, 7);
+    }
+
+    #[test]
+    fn aggregates_branch_time_per_project() {
+        let interval = |start: &str, end: &str, branch: &str| parser::Interval {
+            start: start.parse().unwrap(),
+            end: end.parse().unwrap(),
+            branch: Some(branch.to_string()),
+        };
+        let mut a = session("/work/api", "2026-02-03T10:30:00Z", 1800);
+        a.intervals = vec![interval("2026-02-03T10:00:00Z", "2026-02-03T10:30:00Z", "feat/login")];
+        let mut b = session("/work/api", "2026-02-03T11:20:00Z", 1200);
+        b.intervals = vec![
+            interval("2026-02-03T11:00:00Z", "2026-02-03T11:10:00Z", "feat/login"),
+            interval("2026-02-03T11:10:00Z", "2026-02-03T11:20:00Z", "main"),
+        ];
+
+        let out = aggregate_sessions(&[a, b], &[]);
+
+        assert_eq!(out[0].branches["feat/login"].num_minutes(), 40);
+        assert_eq!(out[0].branches["main"].num_minutes(), 10);
+    }
+
+    #[test]
+    fn subagent_tokens_tallied_separately() {
+        let parent = session_with_tokens("/work/api", "2026-02-03T10:00:00Z", 600, 10, 20, 0, 0);
+        let mut agent = session_with_tokens("/work/api", "2026-02-03T10:00:00Z", 0, 100, 200, 5, 1000);
+        agent.parent = Some("-work-api/abc.jsonl".into());
+
+        let out = aggregate_sessions(&[parent, agent], &[]);
+
+        assert_eq!(out[0].total_minutes, 10);
+        assert_eq!(out[0].output_tokens, 220);
+        assert_eq!(out[0].subagent.output_tokens, 200);
+        assert_eq!(subagent_tokens(&out[0].subagent), 305);
+    }
+
+    #[test]
+    fn short_names_grow_until_unique() {
+        let names = short_names(&["/work/client-a/api", "/work/client-b/api", "/work/web", "/api", ""]);
+
+        assert_eq!(names, ["client-a/api", "client-b/api", "web", "/api", ""]);
+    }
+
+    #[test]
+    fn clashing_projects_sort_by_short_name() {
+        let input = vec![
+            session("/work/client-b/api", "2026-02-03T10:00:00Z", 600),
+            session("/work/client-a/api", "2026-02-03T11:00:00Z", 600),
+            session("/work/web", "2026-02-03T12:00:00Z", 600),
+        ];
+
+        let out = aggregate_sessions(&input, &[]);
+
+        let names: Vec<_> = out.iter().map(|s| s.name.as_str()).collect();
+        assert_eq!(names, ["client-a/api", "client-b/api", "web"]);
+    }
+
+    #[test]
+    fn full_paths_toggle_switches_project_labels() {
+        let out = aggregate_sessions(&[session("/work/api", "2026-02-03T10:00:00Z", 600)], &[]);
+        let mut view = ViewState::new();
+
+        assert_eq!(view.project_label(&out[0]), "api");
+        view.full_paths = true;
+        assert_eq!(view.project_label(&out[0]), "/work/api");
+    }
+
+    #[test]
+    fn manual_time_is_kept_apart_from_tracked_time() {
+        let noon: DateTime<Utc> = "2026-02-03T12:00:00Z".parse().unwrap();
+        let manual = |project: &str, minutes, note: Option<&str>| store::ManualEntry {
+            id: 0,
+            project: project.to_string(),
+            start: noon,
+            end: noon,
+            duration: TimeDelta::minutes(minutes),
+            note: note.map(|n| n.to_string()),
+        };
+
+        let out = aggregate_sessions(
+            &[session("/work/api", "2026-02-03T10:00:00Z", 3600)],
+            &[manual("/work/api", -15, None), manual("/work/web", 45, Some("Reviewing offline"))],
+        );
+
+        assert_eq!((out[0].total_minutes, out[0].manual_minutes), (60, -15));
+        assert_eq!((out[1].total_minutes, out[1].manual_minutes), (0, 45));
+        assert!(out[1].sessions[0].manual);
+        assert_eq!(out[1].sessions[0].title.as_deref(), Some("Reviewing offline"));
+        assert_eq!(format_manual(-15), "−15m");
+    }
+
+    #[test]
+    fn sums_lines_changed_per_project() {
+        let mut a = session("/work/api", "2026-02-03T10:00:00Z", 600);
+        a.lines = parser::LineChanges { added: 1000, removed: 50 };
+        let mut b = session("/work/api", "2026-02-03T11:00:00Z", 600);
+        b.lines = parser::LineChanges { added: 200, removed: 250 };
+
+        let out = aggregate_sessions(&[a, b], &[]);
+
+        assert_eq!(out[0].lines, parser::LineChanges { added: 1200, removed: 300 });
+        assert_eq!(format_lines(&out[0].lines), "+1.2k / −300");
+        assert_eq!(format_lines(&parser::LineChanges::default()), "–");
+    }
+
+    #[test]
+    fn lists_sessions_newest_first_without_subagents() {
+        let mut older = session("/work/api", "2026-02-03T10:00:00Z", 600);
+        older.title = Some("Older".into());
+        let mut newer = session("/work/api", "2026-02-03T12:00:00Z", 1200);
+        newer.title = Some("Newer".into());
+        let mut agent = session("/work/api", "2026-02-03T12:00:00Z", 0);
+        agent.parent = Some("-work-api/abc.jsonl".into());
+
+        let out = aggregate_sessions(&[older, newer, agent], &[]);
+
+        let titles: Vec<_> = out[0].sessions.iter().map(|s| s.title.as_deref().unwrap()).collect();
+        assert_eq!(titles, vec!["Newer", "Older"]);
+        assert_eq!(out[0].sessions[0].minutes, 20);
+    }
+
+    #[test]
+    fn model_label_strips_prefix_and_date() {
+        assert_eq!(model_label("claude-opus-4-1-20250805"), "opus-4-1");
+        assert_eq!(model_label("claude-haiku-4-5"), "haiku-4-5");
+        assert_eq!(model_label("<synthetic>"), "<synthetic>");
+    }
+
     // --- Token formatting ----------------------------------------------------
 
     #[test]
@@ -795,6 +1753,49 @@ work_day_end = "17:00"
         assert_eq!(sync.work_day_end, "17:00");
     }
 
+    #[test]
+    fn sync_description_source_defaults_to_fixed() {
+        let toml = r#"
+[sync]
+workspace_id = "ws-123"
+work_day_start = "09:00"
+work_day_end = "17:00"
+description = "titles"
+
+[sync.project_mapping]
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        assert_eq!(config.sync.unwrap().description, sync::DescriptionSource::Titles);
+
+        let config: Config = toml::from_str(&toml.replace("description = \"titles\"", "")).unwrap();
+        assert_eq!(config.sync.unwrap().description, sync::DescriptionSource::Fixed);
+    }
+
+    #[test]
+    fn parses_labelled_projects_dirs() {
+        let toml = r#"
+[[projects_dirs]]
+path = "~/.claude/projects"
+
+[[projects_dirs]]
+path = "/mnt/work/.claude/projects"
+label = "work"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+
+        assert_eq!(config.projects_dirs.len(), 2);
+        assert_eq!(config.projects_dirs[0].label, None);
+        assert_eq!(config.projects_dirs[1].label.as_deref(), Some("work"));
+        assert_eq!(
+            expand_home(&config.projects_dirs[0].path, Path::new("/home/me")),
+            Path::new("/home/me/.claude/projects")
+        );
+        assert_eq!(
+            expand_home(&config.projects_dirs[1].path, Path::new("/home/me")),
+            Path::new("/mnt/work/.claude/projects")
+        );
+    }
+
     #[test]
     fn config_without_sync_is_none() {
         let toml = r#"
@@ -827,6 +1828,60 @@ work_day_end = "17:00"
         assert_eq!(mapping.get("api-service"), Some(&"proj-api".to_string()));
     }
 
+    #[test]
+    fn project_aliases_regroup_aggregated_sessions() {
+        let toml = r#"
+[project_aliases]
+"/old/api" = "/src/api"
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+        let aliases = alias::Aliases::new(&config.project_aliases);
+        let input = vec![
+            session("/old/api", "2026-02-03T10:00:00Z", 600),
+            session("/src/api", "2026-02-03T11:00:00Z", 1200),
+        ];
+
+        let out = aggregate_sessions(&aliases.apply(input), &[]);
+
+        assert_eq!(out.len(), 1);
+        assert_eq!(out[0].project, "/src/api");
+        assert_eq!(out[0].total_minutes, 30);
+    }
+
+    #[test]
+    fn parses_pricing_overrides() {
+        let toml = r#"
+[pricing."claude-opus-4"]
+input = 10.0
+output = 50.0
+cache_write = 12.5
+cache_read = 1.0
+"#;
+        let config: Config = toml::from_str(toml).unwrap();
+
+        assert_eq!(config.pricing["claude-opus-4"].output, 50.0);
+    }
+
+    #[test]
+    fn config_without_pricing_uses_defaults() {
+        let config: Config = toml::from_str("idle_timeout_minutes = 15").unwrap();
+
+        assert!(config.pricing.is_empty());
+    }
+
+    // --- Cost formatting -----------------------------------------------------
+
+    #[test]
+    fn formats_cost_in_dollars_and_cents() {
+        assert_eq!(format_cost(0.0), "$0.00");
+        assert_eq!(format_cost(12.345), "$12.35");
+    }
+
+    #[test]
+    fn formats_large_cost_in_thousands() {
+        assert_eq!(format_cost(1_234.0), "$1.2k");
+    }
+
     // --- Timeframe cycling ---------------------------------------------------
 
     #[test]
@@ -842,4 +1897,48 @@ work_day_end = "17:00"
         tf = tf.next();
         assert_eq!(tf, Timeframe::Today);
     }
+
+    #[test]
+    fn timeframe_from_arg() {
+        assert_eq!(Timeframe::from_arg("today"), Some(Timeframe::Today));
+        assert_eq!(Timeframe::from_arg("7d"), Some(Timeframe::Last7Days));
+        assert_eq!(Timeframe::from_arg("30d"), Some(Timeframe::Last30Days));
+        assert_eq!(Timeframe::from_arg("week"), None);
+    }
+
+    // --- Selection -----------------------------------------------------------
+
+    #[test]
+    fn first_move_selects_top_row() {
+        let mut view = ViewState::new();
+        view.move_selection(1, 3);
+        assert_eq!(view.selected, Some(0));
+    }
+
+    #[test]
+    fn selection_stays_within_rows() {
+        let mut view = ViewState::new();
+        view.move_selection(1, 3);
+        view.move_selection(-1, 3);
+        assert_eq!(view.selected, Some(0));
+        for _ in 0..5 {
+            view.move_selection(1, 3);
+        }
+        assert_eq!(view.selected, Some(2));
+    }
+
+    #[test]
+    fn shrinking_list_clamps_selection_and_closes_empty_detail() {
+        let mut view = ViewState::new();
+        view.selected = Some(4);
+        view.detail = true;
+
+        view.clamp_selection(2);
+        assert_eq!(view.selected, Some(1));
+        assert!(view.detail);
+
+        view.clamp_selection(0);
+        assert_eq!(view.selected, None);
+        assert!(!view.detail);
+    }
 }
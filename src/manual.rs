@@ -0,0 +1,131 @@
+//! Time logged by hand: `add` for work that left no transcript (pairing on
+//! another machine, reviewing output offline), `adjust` to correct what was
+//! tracked.
+//!
+//! Entries are stored against a directory like sessions are, so project
+//! aliases and the sync project mapping apply to them the same way.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, NaiveTime, TimeDelta, TimeZone, Utc};
+use std::path::Path;
+
+use crate::report::format_duration;
+use crate::store::Store;
+
+/// What `add` or `adjust` stored, for the caller to print.
+pub struct Logged {
+    /// One line confirming the entry.
+    pub message: String,
+    /// Days the entry falls on that `sync` already pushed to the configured
+    /// workspace. It won't skip back to them, so the entry never reaches
+    /// Clockify.
+    pub synced: Vec<NaiveDate>,
+}
+
+/// Log `[start, end)` against `project`. Times are local, as "HH:MM" today
+/// or "YYYY-MM-DD HH:MM".
+pub fn run_add(
+    store: &Store,
+    project: &str,
+    start: &str,
+    end: &str,
+    note: Option<&str>,
+    workspace_id: Option<&str>,
+) -> Result<Logged> {
+    let today = Local::now().date_naive();
+    let start = local(parse_time(start, today).context("parsing --start")?)?;
+    let end = local(parse_time(end, today).context("parsing --end")?)?;
+    anyhow::ensure!(start < end, "--end must be after --start");
+
+    let project = project_path(project)?;
+    let id = store.add_manual_entry(&project, start, end, end - start, note)?;
+    // An entry ending at midnight has no time on the day after.
+    let last_moment = end - TimeDelta::milliseconds(1);
+    let (first, last) = (start.with_timezone(&Local).date_naive(), last_moment.with_timezone(&Local).date_naive());
+    Ok(Logged {
+        message: format!("Added {} to {} (entry {}).", format_duration(end - start), project, id),
+        synced: synced_days(store, workspace_id, first.iter_days().take_while(|day| *day <= last))?,
+    })
+}
+
+/// Add `minutes` to `project`'s time on `date` (default today), or take
+/// them away if negative.
+pub fn run_adjust(
+    store: &Store,
+    project: &str,
+    minutes: i64,
+    date: Option<&str>,
+    note: Option<&str>,
+    workspace_id: Option<&str>,
+) -> Result<Logged> {
+    anyhow::ensure!(minutes != 0, "--minutes must not be zero");
+    let date = match date {
+        Some(date) => NaiveDate::parse_from_str(date, "%Y-%m-%d").context("parsing --date")?,
+        None => Local::now().date_naive(),
+    };
+
+    let project = project_path(project)?;
+    let at = local(adjustment_time(date))?;
+    let id = store.add_manual_entry(&project, at, at, TimeDelta::minutes(minutes), note)?;
+    Ok(Logged {
+        message: format!("Adjusted {} by {} on {} (entry {}).", project, crate::format_manual(minutes), date, id),
+        synced: synced_days(store, workspace_id, [date])?,
+    })
+}
+
+/// Which of `days` `sync` has already pushed to `workspace_id`.
+fn synced_days(
+    store: &Store,
+    workspace_id: Option<&str>,
+    days: impl IntoIterator<Item = NaiveDate>,
+) -> Result<Vec<NaiveDate>> {
+    let Some(workspace_id) = workspace_id else {
+        return Ok(Vec::new());
+    };
+    let mut synced = Vec::new();
+    for day in days {
+        if store.is_day_synced(&day.format("%Y-%m-%d").to_string(), workspace_id)? {
+            synced.push(day);
+        }
+    }
+    Ok(synced)
+}
+
+/// "HH:MM" on `today`, or a full "YYYY-MM-DD HH:MM" (a `T` works too).
+fn parse_time(arg: &str, today: NaiveDate) -> Result<NaiveDateTime> {
+    if let Ok(time) = NaiveTime::parse_from_str(arg, "%H:%M") {
+        return Ok(today.and_time(time));
+    }
+    NaiveDateTime::parse_from_str(&arg.replacen('T', " ", 1), "%Y-%m-%d %H:%M")
+        .with_context(|| format!("{:?} is neither HH:MM nor YYYY-MM-DD HH:MM", arg))
+}
+
+/// Adjustments have no time of their own. Noon keeps them well inside
+/// their date; sync picks them by date rather than by work hours.
+fn adjustment_time(date: NaiveDate) -> NaiveDateTime {
+    date.and_hms_opt(12, 0, 0).expect("noon is a valid time")
+}
+
+fn local(time: NaiveDateTime) -> Result<DateTime<Utc>> {
+    Ok(Local
+        .from_local_datetime(&time)
+        .single()
+        .with_context(|| format!("ambiguous local time {}", time))?
+        .with_timezone(&Utc))
+}
+
+/// `arg` as an absolute path, the way a session's working directory is
+/// recorded.
+fn project_path(arg: &str) -> Result<String> {
+    let home = std::env::var("HOME").context("HOME env var not set")?;
+    let cwd = std::env::current_dir().context("reading the current directory")?;
+    Ok(absolute(arg, &cwd, Path::new(&home)))
+}
+
+fn absolute(arg: &str, cwd: &Path, home: &Path) -> String {
+    let path = cwd.join(crate::expand_home(Path::new(arg), home));
+    crate::alias::normalize(&path).to_string_lossy().into_owned()
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,50 @@
+use super::*;
+
+fn date(s: &str) -> NaiveDate {
+    s.parse().unwrap()
+}
+
+#[test]
+fn times_are_today_unless_dated() {
+    let today = date("2026-02-04");
+
+    assert_eq!(parse_time("09:30", today).unwrap(), date("2026-02-04").and_hms_opt(9, 30, 0).unwrap());
+    assert_eq!(parse_time("2026-02-03 17:15", today).unwrap(), date("2026-02-03").and_hms_opt(17, 15, 0).unwrap());
+    assert_eq!(parse_time("2026-02-03T17:15", today).unwrap(), date("2026-02-03").and_hms_opt(17, 15, 0).unwrap());
+    assert!(parse_time("9.30am", today).is_err());
+}
+
+#[test]
+fn adjustments_land_at_noon() {
+    assert_eq!(adjustment_time(date("2026-02-04")), date("2026-02-04").and_hms_opt(12, 0, 0).unwrap());
+}
+
+#[test]
+fn project_paths_are_absolute() {
+    let (cwd, home) = (Path::new("/work/api"), Path::new("/home/me"));
+
+    assert_eq!(absolute(".", cwd, home), "/work/api");
+    assert_eq!(absolute("../web/", cwd, home), "/work/web");
+    assert_eq!(absolute("~/src/site", cwd, home), "/home/me/src/site");
+    assert_eq!(absolute("/srv/app", cwd, home), "/srv/app");
+}
+
+#[test]
+fn entries_on_already_synced_days_are_flagged() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = Store::new(&dir.path().join("test.db")).unwrap();
+    store.mark_day_synced("2026-02-04", "ws").unwrap();
+
+    let adjusted = run_adjust(&store, "/work/api", 30, Some("2026-02-04"), None, Some("ws")).unwrap();
+    assert_eq!(adjusted.synced, [date("2026-02-04")]);
+    assert!(adjusted.message.starts_with("Adjusted /work/api by "));
+
+    let added = run_add(&store, "/work/api", "2026-02-03 22:00", "2026-02-04 01:00", None, Some("ws")).unwrap();
+    assert_eq!(added.synced, [date("2026-02-04")]);
+
+    let elsewhere = run_adjust(&store, "/work/api", 30, Some("2026-02-04"), None, Some("other")).unwrap();
+    assert!(elsewhere.synced.is_empty());
+    let unsynced = run_adjust(&store, "/work/api", 30, Some("2026-02-05"), None, Some("ws")).unwrap();
+    assert!(unsynced.synced.is_empty());
+    assert!(run_adjust(&store, "/work/api", 30, Some("2026-02-04"), None, None).unwrap().synced.is_empty());
+}
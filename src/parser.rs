@@ -1,6 +1,13 @@
 use chrono::{DateTime, TimeDelta, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
 
-#[derive(Debug, PartialEq)]
+/// Bumped whenever the accumulator's fields or what gets folded into it
+/// change, so state persisted by an older build is discarded and the file
+/// re-parsed.
+pub const STATE_VERSION: u32 = 13;
+
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
 pub struct TokenUsage {
     pub input_tokens: u64,
     pub output_tokens: u64,
@@ -8,14 +15,146 @@ pub struct TokenUsage {
     pub cache_read_input_tokens: u64,
 }
 
-#[derive(Debug)]
+impl TokenUsage {
+    pub fn add(&mut self, other: &TokenUsage) {
+        self.input_tokens += other.input_tokens;
+        self.output_tokens += other.output_tokens;
+        self.cache_creation_input_tokens += other.cache_creation_input_tokens;
+        self.cache_read_input_tokens += other.cache_read_input_tokens;
+    }
+
+    pub fn subtract(&mut self, other: &TokenUsage) {
+        self.input_tokens = self.input_tokens.saturating_sub(other.input_tokens);
+        self.output_tokens = self.output_tokens.saturating_sub(other.output_tokens);
+        self.cache_creation_input_tokens =
+            self.cache_creation_input_tokens.saturating_sub(other.cache_creation_input_tokens);
+        self.cache_read_input_tokens =
+            self.cache_read_input_tokens.saturating_sub(other.cache_read_input_tokens);
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct ParsedMessage {
     pub timestamp: DateTime<Utc>,
     pub cwd: Option<String>,
     pub usage: Option<TokenUsage>,
+    /// API response id (`message.id`, else `requestId`). Claude Code writes
+    /// one line per content block of a streamed response, all sharing it.
+    pub message_id: Option<String>,
+    /// Per-line id. Resumed sessions replay earlier lines with the same uuid.
+    pub uuid: Option<String>,
+    /// `message.model` on assistant lines, e.g. "claude-sonnet-4-5-20250929".
+    pub model: Option<String>,
+    /// `gitBranch` of the working directory when the line was written.
+    pub git_branch: Option<String>,
+    /// `sessionId` of the conversation. Subagent transcripts carry their
+    /// parent's.
+    pub session_id: Option<String>,
+    /// `tool_use` blocks on an assistant line.
+    pub tool_uses: Vec<ToolUse>,
+    /// `tool_result` blocks on a user line: (tool_use_id, is_error).
+    pub tool_results: Vec<(String, bool)>,
+    /// First line of text the user typed, if this is a prompt (not a tool
+    /// result, slash command output or other injected content).
+    pub prompt: Option<String>,
+    /// "user" or "assistant", where the source says which.
+    pub role: Option<String>,
+}
+
+#[derive(Debug, PartialEq)]
+pub struct ToolUse {
+    pub id: String,
+    pub name: String,
+    /// Lines written by an Edit, MultiEdit or Write call.
+    pub lines: LineChanges,
+}
+
+/// Lines added and removed by file-editing tool calls.
+#[derive(Debug, Clone, Copy, Default, PartialEq, Serialize, Deserialize)]
+pub struct LineChanges {
+    pub added: u64,
+    pub removed: u64,
+}
+
+impl LineChanges {
+    pub fn add(&mut self, other: &LineChanges) {
+        self.added += other.added;
+        self.removed += other.removed;
+    }
+
+    pub fn subtract(&mut self, other: &LineChanges) {
+        self.added = self.added.saturating_sub(other.added);
+        self.removed = self.removed.saturating_sub(other.removed);
+    }
+
+    /// Lines changed by replacing `old` with `new`. Leading and trailing
+    /// lines the two share are context, not changes.
+    fn between(old: &str, new: &str) -> Self {
+        let old: Vec<_> = old.lines().collect();
+        let new: Vec<_> = new.lines().collect();
+        let prefix = old.iter().zip(&new).take_while(|(a, b)| a == b).count();
+        let suffix = old[prefix..]
+            .iter()
+            .rev()
+            .zip(new[prefix..].iter().rev())
+            .take_while(|(a, b)| a == b)
+            .count();
+        LineChanges {
+            added: (new.len() - prefix - suffix) as u64,
+            removed: (old.len() - prefix - suffix) as u64,
+        }
+    }
+
+    /// Lines changed by a tool call's input, for the tools that edit files.
+    pub(crate) fn from_tool_input(name: &str, input: &serde_json::Value) -> Self {
+        let text = |v: &serde_json::Value, key: &str| v.get(key).and_then(|s| s.as_str()).unwrap_or("").to_string();
+        match name {
+            "Edit" => Self::between(&text(input, "old_string"), &text(input, "new_string")),
+            "MultiEdit" => {
+                let mut total = LineChanges::default();
+                for edit in input.get("edits").and_then(|e| e.as_array()).into_iter().flatten() {
+                    total.add(&Self::between(&text(edit, "old_string"), &text(edit, "new_string")));
+                }
+                total
+            }
+            // A Write may overwrite an existing file, but its old content
+            // isn't in the transcript, so it only counts as added.
+            "Write" => LineChanges { added: text(input, "content").lines().count() as u64, removed: 0 },
+            _ => LineChanges::default(),
+        }
+    }
+
+    /// Lines changed by a unified-style patch: `+`/`-` lines, not counting
+    /// `+++`/`---` file headers.
+    pub(crate) fn from_patch(patch: &str) -> Self {
+        let mut changes = LineChanges::default();
+        for line in patch.lines() {
+            if line.starts_with('+') && !line.starts_with("+++") {
+                changes.added += 1;
+            } else if line.starts_with('-') && !line.starts_with("---") {
+                changes.removed += 1;
+            }
+        }
+        changes
+    }
 }
 
-#[derive(Debug)]
+/// Invocation counts for one tool.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+pub struct ToolStats {
+    pub calls: u64,
+    /// Calls whose `tool_result` came back with `is_error`.
+    pub errors: u64,
+}
+
+impl ToolStats {
+    pub fn add(&mut self, other: &ToolStats) {
+        self.calls += other.calls;
+        self.errors += other.errors;
+    }
+}
+
+#[derive(Debug, Default)]
 pub struct Session {
     pub start: DateTime<Utc>,
     pub end: DateTime<Utc>,
@@ -25,13 +164,78 @@ pub struct Session {
     pub output_tokens: u64,
     pub cache_creation_input_tokens: u64,
     pub cache_read_input_tokens: u64,
+    /// The same token totals broken down by model.
+    pub models: BTreeMap<String, TokenUsage>,
+    /// Active (non-idle) stretches, in order. Their lengths sum to `duration`.
+    pub intervals: Vec<Interval>,
+    /// For a subagent transcript, the source path of the session that
+    /// spawned it (empty if it didn't say). Subagent rows carry tokens only.
+    pub parent: Option<String>,
+    /// Tool invocations keyed by tool name.
+    pub tools: BTreeMap<String, ToolStats>,
+    /// Lines changed by Edit, MultiEdit and Write calls that succeeded.
+    pub lines: LineChanges,
+    /// The conversation's summary, else its first prompt, shortened.
+    pub title: Option<String>,
+}
+
+impl Session {
+    /// Active time per git branch. Time on lines without a branch is keyed
+    /// by "".
+    pub fn branch_durations(&self) -> BTreeMap<String, TimeDelta> {
+        let mut branches = BTreeMap::new();
+        for interval in &self.intervals {
+            let branch = interval.branch.clone().unwrap_or_default();
+            *branches.entry(branch).or_insert(TimeDelta::zero()) += interval.duration();
+        }
+        branches
+    }
+}
+
+/// A stretch of continuous activity on one branch, i.e. a run of gaps below
+/// the idle threshold.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Interval {
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    pub branch: Option<String>,
+}
+
+impl Interval {
+    /// The part of this interval inside `[start, end)`, if any.
+    pub fn clip(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Option<Interval> {
+        let clipped = Interval {
+            start: self.start.max(start),
+            end: self.end.min(end),
+            branch: self.branch.clone(),
+        };
+        (clipped.start < clipped.end).then_some(clipped)
+    }
+
+    pub fn duration(&self) -> TimeDelta {
+        self.end - self.start
+    }
+}
+
+/// A JSONL line worth folding into a session.
+pub enum Line {
+    Message(Box<ParsedMessage>),
+    /// `type: "summary"` line, Claude Code's title for the conversation.
+    Summary(String),
 }
 
-/// Parse a single JSONL line into a ParsedMessage.
-/// Returns None for non-user/assistant messages and unparseable lines.
-pub fn parse_message(line: &str) -> Option<ParsedMessage> {
+/// Parse a single JSONL line. Returns None for other line types and
+/// unparseable lines.
+pub fn parse_line(line: &str) -> Option<Line> {
     let value: serde_json::Value = serde_json::from_str(line).ok()?;
 
+    match value.get("type")?.as_str()? {
+        "summary" => Some(Line::Summary(value.get("summary")?.as_str()?.to_string())),
+        _ => message_from_value(&value).map(|message| Line::Message(Box::new(message))),
+    }
+}
+
+fn message_from_value(value: &serde_json::Value) -> Option<ParsedMessage> {
     let msg_type = value.get("type")?.as_str()?;
 
     match msg_type {
@@ -39,68 +243,613 @@ pub fn parse_message(line: &str) -> Option<ParsedMessage> {
             let timestamp_str = value.get("timestamp")?.as_str()?;
             let timestamp: DateTime<Utc> = timestamp_str.parse().ok()?;
             let cwd = value.get("cwd").and_then(|v| v.as_str()).map(|s| s.to_string());
-            let usage = value
+            let usage = value.get("message").and_then(|m| m.get("usage")).and_then(usage_from_value);
+            let message_id = value
                 .get("message")
-                .and_then(|m| m.get("usage"))
-                .and_then(|u| {
-                    Some(TokenUsage {
-                        input_tokens: u.get("input_tokens")?.as_u64()?,
-                        output_tokens: u.get("output_tokens")?.as_u64()?,
-                        cache_creation_input_tokens: u
-                            .get("cache_creation_input_tokens")
-                            .and_then(|v| v.as_u64())
-                            .unwrap_or(0),
-                        cache_read_input_tokens: u
-                            .get("cache_read_input_tokens")
-                            .and_then(|v| v.as_u64())
-                            .unwrap_or(0),
-                    })
-                });
-            Some(ParsedMessage { timestamp, cwd, usage })
+                .and_then(|m| m.get("id"))
+                .or_else(|| value.get("requestId"))
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
+            let uuid = value.get("uuid").and_then(|v| v.as_str()).map(|s| s.to_string());
+            let model = value
+                .get("message")
+                .and_then(|m| m.get("model"))
+                .and_then(|v| v.as_str())
+                .map(|s| s.to_string());
+            let git_branch = value
+                .get("gitBranch")
+                .and_then(|v| v.as_str())
+                .filter(|s| !s.is_empty())
+                .map(|s| s.to_string());
+            let session_id = value.get("sessionId").and_then(|v| v.as_str()).map(|s| s.to_string());
+            let blocks = value
+                .get("message")
+                .and_then(|m| m.get("content"))
+                .and_then(|c| c.as_array())
+                .map(|a| a.as_slice())
+                .unwrap_or_default();
+            let tool_uses = blocks
+                .iter()
+                .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_use"))
+                .filter_map(|b| {
+                    let name = b.get("name")?.as_str()?.to_string();
+                    let lines = b
+                        .get("input")
+                        .map(|input| LineChanges::from_tool_input(&name, input))
+                        .unwrap_or_default();
+                    Some(ToolUse { id: b.get("id")?.as_str()?.to_string(), name, lines })
+                })
+                .collect();
+            let tool_results = blocks
+                .iter()
+                .filter(|b| b.get("type").and_then(|t| t.as_str()) == Some("tool_result"))
+                .filter_map(|b| {
+                    let id = b.get("tool_use_id")?.as_str()?.to_string();
+                    let is_error = b.get("is_error").and_then(|v| v.as_bool()).unwrap_or(false);
+                    Some((id, is_error))
+                })
+                .collect();
+            let prompt = if msg_type == "user" && value.get("isMeta").and_then(|v| v.as_bool()) != Some(true) {
+                prompt_text(value)
+            } else {
+                None
+            };
+            Some(ParsedMessage {
+                timestamp,
+                cwd,
+                usage,
+                message_id,
+                uuid,
+                model,
+                git_branch,
+                session_id,
+                tool_uses,
+                tool_results,
+                prompt,
+                role: Some(msg_type.to_string()),
+            })
         }
         _ => None,
     }
 }
 
-/// Assemble a list of parsed messages into a Session.
-/// Returns None if the message list is empty.
-/// Gaps between consecutive messages that meet or exceed `idle_threshold`
-/// are excluded from the duration (clock pauses during idle).
-pub fn assemble_session(messages: &[ParsedMessage], idle_threshold: TimeDelta) -> Option<Session> {
-    if messages.is_empty() {
+fn usage_from_value(u: &serde_json::Value) -> Option<TokenUsage> {
+    Some(TokenUsage {
+        input_tokens: u.get("input_tokens")?.as_u64()?,
+        output_tokens: u.get("output_tokens")?.as_u64()?,
+        cache_creation_input_tokens: u
+            .get("cache_creation_input_tokens")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0),
+        cache_read_input_tokens: u
+            .get("cache_read_input_tokens")
+            .and_then(|v| v.as_u64())
+            .unwrap_or(0),
+    })
+}
+
+/// Claude message from a line too long to deserialize: when and where it
+/// was written, and its usage. Its content, and so any tool calls or prompt,
+/// is lost.
+pub fn message_from_fields(fields: &ScannedFields) -> Option<ParsedMessage> {
+    if !matches!(fields.get("type")?, "user" | "assistant") {
         return None;
     }
-
-    let start = messages.first()?.timestamp;
-    let end = messages.last()?.timestamp;
-    let duration = messages
-        .windows(2)
-        .map(|w| w[1].timestamp - w[0].timestamp)
-        .filter(|gap| *gap < idle_threshold)
-        .sum();
-    let project = messages
-        .iter()
-        .filter_map(|m| m.cwd.as_deref())
-        .next()
-        .unwrap_or("")
-        .to_string();
-
-    let input_tokens: u64 = messages.iter().filter_map(|m| m.usage.as_ref()).map(|u| u.input_tokens).sum();
-    let output_tokens: u64 = messages.iter().filter_map(|m| m.usage.as_ref()).map(|u| u.output_tokens).sum();
-    let cache_creation_input_tokens: u64 = messages.iter().filter_map(|m| m.usage.as_ref()).map(|u| u.cache_creation_input_tokens).sum();
-    let cache_read_input_tokens: u64 = messages.iter().filter_map(|m| m.usage.as_ref()).map(|u| u.cache_read_input_tokens).sum();
-
-    Some(Session {
-        start,
-        end,
-        duration,
-        project,
-        input_tokens,
-        output_tokens,
-        cache_creation_input_tokens,
-        cache_read_input_tokens,
+    let text = |path: &str| fields.get(path).map(|s| s.to_string());
+    Some(ParsedMessage {
+        timestamp: fields.get("timestamp")?.parse().ok()?,
+        cwd: text("cwd"),
+        usage: fields
+            .usage
+            .as_deref()
+            .and_then(|raw| serde_json::from_str(raw).ok())
+            .and_then(|u: serde_json::Value| usage_from_value(&u)),
+        message_id: text("message.id").or_else(|| text("requestId")),
+        uuid: text("uuid"),
+        model: text("message.model"),
+        git_branch: text("gitBranch").filter(|b| !b.is_empty()),
+        session_id: text("sessionId"),
+        role: text("type"),
+        ..Default::default()
     })
 }
 
+/// Longest line that is deserialized. Longer ones (usually a huge tool
+/// result) go through a `FieldScanner` instead, a chunk at a time, so
+/// memory stays flat whatever the line's size.
+pub const MAX_LINE_BYTES: usize = 1 << 20;
+
+/// Longest string value a `FieldScanner` keeps, and the most fields.
+const MAX_FIELD_BYTES: usize = 4096;
+const MAX_FIELDS: usize = 64;
+
+/// String fields of a JSON object, by dotted path ("timestamp",
+/// "message.model"), found without building the whole value. Only fields
+/// reached through objects two deep are kept; anything inside an array is
+/// content and skipped.
+#[derive(Debug, Default)]
+pub struct ScannedFields {
+    fields: BTreeMap<String, String>,
+    /// Raw JSON of a `usage` object one level down, if small enough to keep.
+    pub usage: Option<String>,
+}
+
+impl ScannedFields {
+    pub fn get(&self, path: &str) -> Option<&str> {
+        self.fields.get(path).map(|s| s.as_str())
+    }
+}
+
+/// Reads a JSON object byte by byte, keeping just enough state to know
+/// where in the object it is. Fed a line in chunks of any size.
+#[derive(Default)]
+pub struct FieldScanner {
+    /// Open objects and arrays, innermost last.
+    stack: Vec<Frame>,
+    in_string: bool,
+    escaped: bool,
+    /// The string being read, unless it's grown too long to keep.
+    string: Option<Vec<u8>>,
+    /// Bytes of the `usage` object being read, and the depth it opened at.
+    raw: Option<(Vec<u8>, usize)>,
+    scanned: ScannedFields,
+}
+
+struct Frame {
+    object: bool,
+    /// The key whose value is being read, for objects.
+    key: Option<String>,
+    expecting_key: bool,
+}
+
+impl FieldScanner {
+    pub fn feed(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.byte(b);
+        }
+    }
+
+    pub fn finish(self) -> ScannedFields {
+        self.scanned
+    }
+
+    fn byte(&mut self, b: u8) {
+        if self.in_string {
+            match b {
+                _ if self.escaped => self.escaped = false,
+                b'\\' => self.escaped = true,
+                b'"' => {
+                    self.in_string = false;
+                    self.end_string();
+                }
+                _ => {}
+            }
+            if self.in_string
+                && let Some(string) = &mut self.string
+            {
+                string.push(b);
+                if string.len() > MAX_FIELD_BYTES {
+                    self.string = None;
+                }
+            }
+            self.capture(b);
+            return;
+        }
+        match b {
+            b'"' => {
+                self.in_string = true;
+                self.string = Some(Vec::new());
+            }
+            b'{' | b'[' => {
+                if b == b'{' && self.raw.is_none() && self.stack.len() == 2 && self.current_key() == Some("usage") {
+                    self.raw = Some((Vec::new(), self.stack.len()));
+                }
+                self.stack.push(Frame { object: b == b'{', key: None, expecting_key: b == b'{' });
+            }
+            b'}' | b']' => {
+                self.stack.pop();
+            }
+            b':' => {
+                if let Some(frame) = self.stack.last_mut() {
+                    frame.expecting_key = false;
+                }
+            }
+            b',' => {
+                if let Some(frame) = self.stack.last_mut()
+                    && frame.object
+                {
+                    frame.expecting_key = true;
+                    frame.key = None;
+                }
+            }
+            _ => {}
+        }
+        self.capture(b);
+        if b == b'}'
+            && let Some((raw, depth)) = &self.raw
+            && self.stack.len() == *depth
+        {
+            self.scanned.usage = String::from_utf8(raw.clone()).ok();
+            self.raw = None;
+        }
+    }
+
+    /// Key of the value about to be read in the innermost object.
+    fn current_key(&self) -> Option<&str> {
+        let frame = self.stack.last()?;
+        if frame.object && !frame.expecting_key { frame.key.as_deref() } else { None }
+    }
+
+    fn capture(&mut self, b: u8) {
+        if let Some((raw, _)) = &mut self.raw {
+            raw.push(b);
+            if raw.len() > MAX_FIELD_BYTES {
+                self.raw = None;
+            }
+        }
+    }
+
+    fn end_string(&mut self) {
+        let Some(bytes) = self.string.take() else {
+            return;
+        };
+        // Undo JSON escapes by letting serde read the quoted string.
+        let mut quoted = Vec::with_capacity(bytes.len() + 2);
+        quoted.push(b'"');
+        quoted.extend_from_slice(&bytes);
+        quoted.push(b'"');
+        let Ok(text) = serde_json::from_slice::<String>(&quoted) else {
+            return;
+        };
+        let Some(frame) = self.stack.last_mut() else {
+            return;
+        };
+        if frame.object && frame.expecting_key {
+            frame.key = Some(text);
+            return;
+        }
+        if self.stack.len() > 2 || self.scanned.fields.len() >= MAX_FIELDS {
+            return;
+        }
+        let path: Option<Vec<&str>> = self
+            .stack
+            .iter()
+            .map(|frame| frame.key.as_deref().filter(|_| frame.object))
+            .collect();
+        if let Some(path) = path {
+            self.scanned.fields.entry(path.join(".")).or_insert(text);
+        }
+    }
+}
+
+/// First line of a user prompt: the content string, or its first text
+/// block. Slash command wrappers, interruption notices and other injected
+/// text start with `<` or `[` and are skipped.
+fn prompt_text(value: &serde_json::Value) -> Option<String> {
+    let content = value.get("message")?.get("content")?;
+    let text = match content.as_str() {
+        Some(text) => text,
+        None => content
+            .as_array()?
+            .iter()
+            .find(|b| b.get("type").and_then(|t| t.as_str()) == Some("text"))?
+            .get("text")?
+            .as_str()?,
+    };
+    let first_line = text.lines().map(str::trim).find(|l| !l.is_empty())?;
+    if first_line.starts_with('<') || first_line.starts_with('[') {
+        return None;
+    }
+    Some(first_line.to_string())
+}
+
+/// Longest title kept from a prompt, in characters.
+const TITLE_MAX_CHARS: usize = 60;
+
+fn shorten(text: &str) -> String {
+    if text.chars().count() <= TITLE_MAX_CHARS {
+        return text.to_string();
+    }
+    let mut short: String = text.chars().take(TITLE_MAX_CHARS - 1).collect();
+    short.push('…');
+    short
+}
+
+/// Model bucket for usage on lines that don't name one.
+pub const UNKNOWN_MODEL: &str = "unknown";
+
+/// Running aggregation state for one session file.
+///
+/// Messages are folded in one at a time, so a file can be parsed in pieces:
+/// the accumulator is persisted alongside the file's byte offset and resumed
+/// when more lines are appended.
+///
+/// A conversation that `cd`s between repos is split into one segment per
+/// working directory, each with its own active time and tokens.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct SessionAccumulator {
+    pub version: u32,
+    idle_threshold_ms: i64,
+    /// Timestamp of the previous message, whichever segment it landed in.
+    last_timestamp: Option<DateTime<Utc>>,
+    /// Working directory of the previous message. Lines without a cwd stay
+    /// in the current segment.
+    current_project: Option<String>,
+    /// Branch of the previous message that had one.
+    current_branch: Option<String>,
+    /// Model of the previous message that named one. Some tools report
+    /// usage on separate lines from the model.
+    current_model: Option<String>,
+    /// First `sessionId` seen in the file.
+    session_id: Option<String>,
+    /// Latest summary line seen in the file.
+    summary: Option<String>,
+    first_prompt: Option<String>,
+    /// In order of first appearance.
+    segments: Vec<Segment>,
+    /// The most recent API response with usage, already counted in the
+    /// totals. A later line for the same response replaces its usage.
+    last_response: Option<Response>,
+    /// Tool calls still waiting on their result, by tool_use id, so an error
+    /// can be charged to the call's tool and segment.
+    pending_tools: BTreeMap<String, PendingTool>,
+}
+
+/// Where `SessionAccumulator::push` counted a message: the context it
+/// carried forward from earlier lines, as well as the message's own.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Attribution {
+    pub project: String,
+    pub branch: Option<String>,
+    /// Model its usage was charged to.
+    pub model: Option<String>,
+    pub session_id: Option<String>,
+    /// Its usage replaced the previous message's, both being lines of one
+    /// API response.
+    pub replaces_previous: bool,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Segment {
+    project: String,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    duration_ms: i64,
+    tokens: TokenUsage,
+    models: BTreeMap<String, TokenUsage>,
+    intervals: Vec<Interval>,
+    tools: BTreeMap<String, ToolStats>,
+    lines: LineChanges,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct PendingTool {
+    name: String,
+    project: String,
+    /// Taken back off the segment if the call fails.
+    lines: LineChanges,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct Response {
+    id: String,
+    project: String,
+    model: String,
+    usage: TokenUsage,
+}
+
+impl SessionAccumulator {
+    pub fn new(idle_threshold: TimeDelta) -> Self {
+        Self {
+            version: STATE_VERSION,
+            idle_threshold_ms: idle_threshold.num_milliseconds(),
+            last_timestamp: None,
+            current_project: None,
+            current_branch: None,
+            current_model: None,
+            session_id: None,
+            summary: None,
+            first_prompt: None,
+            segments: Vec::new(),
+            last_response: None,
+            pending_tools: BTreeMap::new(),
+        }
+    }
+
+    /// True if this state was built by the current parser with the given
+    /// idle threshold, i.e. it's safe to keep folding into it.
+    pub fn is_compatible(&self, idle_threshold: TimeDelta) -> bool {
+        self.version == STATE_VERSION && self.idle_threshold_ms == idle_threshold.num_milliseconds()
+    }
+
+    /// Fold one message into the running totals, returning where it was
+    /// counted.
+    /// The gap since the previous message counts toward the duration only if
+    /// it's below the idle threshold, and is credited to the segment of the
+    /// message that ends it. Usage is counted once per API response:
+    /// consecutive lines sharing a message id carry the same response, and
+    /// the last one holds its final usage.
+    pub fn push(&mut self, message: &ParsedMessage) -> Attribution {
+        let project = self.project_for(message);
+        if message.git_branch.is_some() {
+            self.current_branch = message.git_branch.clone();
+        }
+        let branch = self.current_branch.clone();
+        if message.model.is_some() {
+            self.current_model = message.model.clone();
+        }
+        if self.session_id.is_none() {
+            self.session_id = message.session_id.clone();
+        }
+        if self.first_prompt.is_none() {
+            self.first_prompt = message.prompt.clone();
+        }
+        let active_gap = self.last_timestamp.filter(|prev| {
+            (message.timestamp - *prev).num_milliseconds() < self.idle_threshold_ms
+        });
+        self.last_timestamp = Some(message.timestamp);
+
+        let segment = self.segment_mut(&project, message.timestamp);
+        if let Some(prev) = active_gap {
+            segment.duration_ms += (message.timestamp - prev).num_milliseconds();
+            segment.start = segment.start.min(prev);
+            // Consecutive active gaps in the same segment and on the same
+            // branch extend one interval.
+            match segment.intervals.last_mut() {
+                Some(last) if last.end == prev && last.branch == branch => last.end = message.timestamp,
+                _ => segment.intervals.push(Interval { start: prev, end: message.timestamp, branch }),
+            }
+        }
+        segment.end = message.timestamp;
+
+        let mut replaces_previous = false;
+        if let Some(usage) = &message.usage {
+            let model = self.current_model.clone().unwrap_or_else(|| UNKNOWN_MODEL.to_string());
+            let superseded = self
+                .last_response
+                .take_if(|last| message.message_id.as_ref() == Some(&last.id));
+            replaces_previous = superseded.is_some();
+            if let Some(last) = superseded
+                && let Some(segment) = self.segments.iter_mut().find(|s| s.project == last.project)
+            {
+                segment.tokens.subtract(&last.usage);
+                segment.models.entry(last.model).or_default().subtract(&last.usage);
+            }
+            let segment = self.segment_mut(&project, message.timestamp);
+            segment.tokens.add(usage);
+            segment.models.entry(model.clone()).or_default().add(usage);
+            self.last_response = message.message_id.clone().map(|id| Response {
+                id,
+                project: project.clone(),
+                model,
+                usage: usage.clone(),
+            });
+        }
+
+        for tool in &message.tool_uses {
+            let segment = self.segment_mut(&project, message.timestamp);
+            segment.tools.entry(tool.name.clone()).or_default().calls += 1;
+            segment.lines.add(&tool.lines);
+            self.pending_tools.insert(
+                tool.id.clone(),
+                PendingTool { name: tool.name.clone(), project: project.clone(), lines: tool.lines },
+            );
+        }
+        for (id, is_error) in &message.tool_results {
+            if let Some(call) = self.pending_tools.remove(id)
+                && *is_error
+                && let Some(segment) = self.segments.iter_mut().find(|s| s.project == call.project)
+            {
+                segment.tools.entry(call.name).or_default().errors += 1;
+                segment.lines.subtract(&call.lines);
+            }
+        }
+
+        Attribution {
+            project,
+            branch: self.current_branch.clone(),
+            model: self.current_model.clone(),
+            session_id: message.session_id.clone().or_else(|| self.session_id.clone()),
+            replaces_previous,
+        }
+    }
+
+    /// The message's cwd, else the current one. Lines seen before any cwd
+    /// are adopted by the first directory that shows up.
+    fn project_for(&mut self, message: &ParsedMessage) -> String {
+        let Some(cwd) = message.cwd.as_ref().filter(|c| !c.is_empty()) else {
+            return self.current_project.clone().unwrap_or_default();
+        };
+        if self.current_project.is_none() {
+            if let Some(orphan) = self.segments.iter_mut().find(|s| s.project.is_empty()) {
+                orphan.project = cwd.clone();
+            }
+            if let Some(last) = self.last_response.as_mut().filter(|r| r.project.is_empty()) {
+                last.project = cwd.clone();
+            }
+            for call in self.pending_tools.values_mut().filter(|c| c.project.is_empty()) {
+                call.project = cwd.clone();
+            }
+        }
+        self.current_project = Some(cwd.clone());
+        cwd.clone()
+    }
+
+    fn segment_mut(&mut self, project: &str, timestamp: DateTime<Utc>) -> &mut Segment {
+        let idx = match self.segments.iter().position(|s| s.project == project) {
+            Some(idx) => idx,
+            None => {
+                self.segments.push(Segment {
+                    project: project.to_string(),
+                    start: timestamp,
+                    end: timestamp,
+                    duration_ms: 0,
+                    tokens: TokenUsage::default(),
+                    models: BTreeMap::new(),
+                    intervals: Vec::new(),
+                    tools: BTreeMap::new(),
+                    lines: LineChanges::default(),
+                });
+                self.segments.len() - 1
+            }
+        };
+        &mut self.segments[idx]
+    }
+
+    pub fn session_id(&self) -> Option<&str> {
+        self.session_id.as_deref()
+    }
+
+    /// Record a summary line. A later summary replaces an earlier one.
+    pub fn push_summary(&mut self, summary: &str) {
+        self.summary = Some(summary.to_string());
+    }
+
+    /// The summary if the file has one, else the shortened first prompt.
+    pub fn title(&self) -> Option<String> {
+        self.summary
+            .clone()
+            .or_else(|| self.first_prompt.as_deref().map(shorten))
+    }
+
+    /// Snapshot the current totals as one Session per working directory.
+    /// Empty until at least one message has been folded in.
+    pub fn sessions(&self) -> Vec<Session> {
+        self.segments
+            .iter()
+            .map(|seg| Session {
+                start: seg.start,
+                end: seg.end,
+                duration: TimeDelta::milliseconds(seg.duration_ms),
+                project: seg.project.clone(),
+                input_tokens: seg.tokens.input_tokens,
+                output_tokens: seg.tokens.output_tokens,
+                cache_creation_input_tokens: seg.tokens.cache_creation_input_tokens,
+                cache_read_input_tokens: seg.tokens.cache_read_input_tokens,
+                models: seg.models.clone(),
+                intervals: seg.intervals.clone(),
+                parent: None,
+                tools: seg.tools.clone(),
+                lines: seg.lines,
+                title: self.title(),
+            })
+            .collect()
+    }
+}
+
+/// Assemble a list of parsed messages into Sessions, one per working
+/// directory. Returns an empty list if there are no messages.
+/// Gaps between consecutive messages that meet or exceed `idle_threshold`
+/// are excluded from the duration (clock pauses during idle).
+/// One-shot form of `SessionAccumulator`, used by tests.
+#[cfg(test)]
+pub fn assemble_sessions(messages: &[ParsedMessage], idle_threshold: TimeDelta) -> Vec<Session> {
+    let mut acc = SessionAccumulator::new(idle_threshold);
+    for message in messages {
+        acc.push(message);
+    }
+    acc.sessions()
+}
+
 #[cfg(test)]
 mod tests;
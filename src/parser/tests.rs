@@ -1,6 +1,14 @@
 use super::*;
 use chrono::{DateTime, TimeDelta, Utc};
 
+/// The message on a line, if it's a user or assistant line that parses.
+fn parse_message(line: &str) -> Option<ParsedMessage> {
+    match parse_line(line)? {
+        Line::Message(message) => Some(*message),
+        Line::Summary(_) => None,
+    }
+}
+
 // Realistic fixtures drawn from actual Claude Code transcript format.
 // Include enough fields to verify the parser ignores irrelevant keys.
 
@@ -123,15 +131,33 @@ fn calculates_session_duration() {
             timestamp: "2026-02-03T10:00:00Z".parse().unwrap(),
             cwd: Some("/Users/etwilson/workdev/project".to_string()),
             usage: None,
+            message_id: None,
+            uuid: None,
+            model: None,
+            git_branch: None,
+            session_id: None,
+            tool_uses: Vec::new(),
+            tool_results: Vec::new(),
+            prompt: None,
+            role: None,
         },
         ParsedMessage {
             timestamp: "2026-02-03T10:05:30Z".parse().unwrap(),
             cwd: Some("/Users/etwilson/workdev/project".to_string()),
             usage: None,
+            message_id: None,
+            uuid: None,
+            model: None,
+            git_branch: None,
+            session_id: None,
+            tool_uses: Vec::new(),
+            tool_results: Vec::new(),
+            prompt: None,
+            role: None,
         },
     ];
 
-    let session = assemble_session(&messages, TimeDelta::minutes(15)).unwrap();
+    let session = assemble_sessions(&messages, TimeDelta::minutes(15)).remove(0);
 
     assert_eq!(session.start, "2026-02-03T10:00:00Z".parse::<DateTime<Utc>>().unwrap());
     assert_eq!(session.end, "2026-02-03T10:05:30Z".parse::<DateTime<Utc>>().unwrap());
@@ -143,7 +169,7 @@ fn calculates_session_duration() {
 fn filters_empty_sessions() {
     let messages: Vec<ParsedMessage> = vec![];
 
-    assert!(assemble_session(&messages, TimeDelta::minutes(15)).is_none());
+    assert!(assemble_sessions(&messages, TimeDelta::minutes(15)).is_empty());
 }
 
 // --- Idle timeout gap tests ---------------------------------------------
@@ -154,6 +180,15 @@ fn msg(timestamp: &str) -> ParsedMessage {
         timestamp: timestamp.parse().unwrap(),
         cwd: Some("/work/project".to_string()),
         usage: None,
+        message_id: None,
+        uuid: None,
+        model: None,
+        git_branch: None,
+        session_id: None,
+        tool_uses: Vec::new(),
+        tool_results: Vec::new(),
+        prompt: None,
+        role: None,
     }
 }
 
@@ -166,7 +201,7 @@ fn gaps_below_threshold_count_fully() {
         msg("2026-02-03T10:08:00Z"),
     ];
 
-    let session = assemble_session(&messages, TimeDelta::minutes(15)).unwrap();
+    let session = assemble_sessions(&messages, TimeDelta::minutes(15)).remove(0);
 
     assert_eq!(session.duration.num_seconds(), 480); // 8 min
 }
@@ -180,7 +215,7 @@ fn gap_above_threshold_is_excluded() {
         msg("2026-02-03T10:35:00Z"),
     ];
 
-    let session = assemble_session(&messages, TimeDelta::minutes(15)).unwrap();
+    let session = assemble_sessions(&messages, TimeDelta::minutes(15)).remove(0);
 
     assert_eq!(session.duration.num_seconds(), 300); // 5 min
 }
@@ -195,7 +230,7 @@ fn mixed_gaps_only_large_ones_dropped() {
         msg("2026-02-03T10:27:00Z"),
     ];
 
-    let session = assemble_session(&messages, TimeDelta::minutes(15)).unwrap();
+    let session = assemble_sessions(&messages, TimeDelta::minutes(15)).remove(0);
 
     assert_eq!(session.duration.num_seconds(), 420); // 7 min
 }
@@ -243,6 +278,15 @@ fn assemble_session_sums_token_usage() {
                 cache_creation_input_tokens: 200,
                 cache_read_input_tokens: 300,
             }),
+            message_id: None,
+            uuid: None,
+            model: None,
+            git_branch: None,
+            session_id: None,
+            tool_uses: Vec::new(),
+            tool_results: Vec::new(),
+            prompt: None,
+            role: None,
         },
         ParsedMessage {
             timestamp: "2026-02-03T10:05:00Z".parse().unwrap(),
@@ -253,10 +297,19 @@ fn assemble_session_sums_token_usage() {
                 cache_creation_input_tokens: 100,
                 cache_read_input_tokens: 150,
             }),
+            message_id: None,
+            uuid: None,
+            model: None,
+            git_branch: None,
+            session_id: None,
+            tool_uses: Vec::new(),
+            tool_results: Vec::new(),
+            prompt: None,
+            role: None,
         },
     ];
 
-    let session = assemble_session(&messages, TimeDelta::minutes(15)).unwrap();
+    let session = assemble_sessions(&messages, TimeDelta::minutes(15)).remove(0);
 
     assert_eq!(session.input_tokens, 150);
     assert_eq!(session.output_tokens, 75);
@@ -271,6 +324,15 @@ fn assemble_session_ignores_messages_without_usage() {
             timestamp: "2026-02-03T10:00:00Z".parse().unwrap(),
             cwd: Some("/work/project".to_string()),
             usage: None,
+            message_id: None,
+            uuid: None,
+            model: None,
+            git_branch: None,
+            session_id: None,
+            tool_uses: Vec::new(),
+            tool_results: Vec::new(),
+            prompt: None,
+            role: None,
         },
         ParsedMessage {
             timestamp: "2026-02-03T10:05:00Z".parse().unwrap(),
@@ -281,18 +343,647 @@ fn assemble_session_ignores_messages_without_usage() {
                 cache_creation_input_tokens: 0,
                 cache_read_input_tokens: 0,
             }),
+            message_id: None,
+            uuid: None,
+            model: None,
+            git_branch: None,
+            session_id: None,
+            tool_uses: Vec::new(),
+            tool_results: Vec::new(),
+            prompt: None,
+            role: None,
         },
         ParsedMessage {
             timestamp: "2026-02-03T10:06:00Z".parse().unwrap(),
             cwd: Some("/work/project".to_string()),
             usage: None,
+            message_id: None,
+            uuid: None,
+            model: None,
+            git_branch: None,
+            session_id: None,
+            tool_uses: Vec::new(),
+            tool_results: Vec::new(),
+            prompt: None,
+            role: None,
         },
     ];
 
-    let session = assemble_session(&messages, TimeDelta::minutes(15)).unwrap();
+    let session = assemble_sessions(&messages, TimeDelta::minutes(15)).remove(0);
 
     assert_eq!(session.input_tokens, 100);
     assert_eq!(session.output_tokens, 50);
     assert_eq!(session.cache_creation_input_tokens, 0);
     assert_eq!(session.cache_read_input_tokens, 0);
 }
+
+// --- Incremental accumulation -------------------------------------------
+
+#[test]
+fn accumulator_resumed_from_saved_state_matches_single_pass() {
+    let messages = vec![
+        msg("2026-02-03T10:00:00Z"),
+        msg("2026-02-03T10:05:00Z"),
+        msg("2026-02-03T10:40:00Z"),
+        msg("2026-02-03T10:42:00Z"),
+    ];
+
+    let mut acc = SessionAccumulator::new(TimeDelta::minutes(15));
+    acc.push(&messages[0]);
+    acc.push(&messages[1]);
+    let saved = serde_json::to_string(&acc).unwrap();
+
+    let mut resumed: SessionAccumulator = serde_json::from_str(&saved).unwrap();
+    resumed.push(&messages[2]);
+    resumed.push(&messages[3]);
+
+    let whole = assemble_sessions(&messages, TimeDelta::minutes(15)).remove(0);
+    let session = resumed.sessions().remove(0);
+    assert_eq!(session.duration, whole.duration);
+    assert_eq!(session.start, whole.start);
+    assert_eq!(session.end, whole.end);
+}
+
+#[test]
+fn accumulator_is_incompatible_with_different_idle_threshold() {
+    let acc = SessionAccumulator::new(TimeDelta::minutes(15));
+
+    assert!(acc.is_compatible(TimeDelta::minutes(15)));
+    assert!(!acc.is_compatible(TimeDelta::minutes(30)));
+}
+
+// --- Streamed response dedup --------------------------------------------
+
+// One streamed API response written as two lines (thinking, then text).
+// Both carry the same message id; only the last has the final output count.
+const STREAMED_BLOCK_1: &str = r#"{
+    "type": "assistant",
+    "timestamp": "2026-02-03T17:37:02.000Z",
+    "cwd": "/work/project",
+    "requestId": "req_011",
+    "message": {
+        "id": "msg_01ABC",
+        "role": "assistant",
+        "content": [{"type": "thinking", "thinking": "..."}],
+        "usage": {"input_tokens": 10, "output_tokens": 1, "cache_creation_input_tokens": 500, "cache_read_input_tokens": 2000}
+    },
+    "uuid": "11111111-0000-0000-0000-000000000001"
+}"#;
+
+const STREAMED_BLOCK_2: &str = r#"{
+    "type": "assistant",
+    "timestamp": "2026-02-03T17:37:40.000Z",
+    "cwd": "/work/project",
+    "requestId": "req_011",
+    "message": {
+        "id": "msg_01ABC",
+        "role": "assistant",
+        "content": [{"type": "text", "text": "Here you go"}],
+        "usage": {"input_tokens": 10, "output_tokens": 1400, "cache_creation_input_tokens": 500, "cache_read_input_tokens": 2000}
+    },
+    "uuid": "11111111-0000-0000-0000-000000000002"
+}"#;
+
+const NEXT_RESPONSE: &str = r#"{
+    "type": "assistant",
+    "timestamp": "2026-02-03T17:38:00.000Z",
+    "cwd": "/work/project",
+    "requestId": "req_012",
+    "message": {
+        "id": "msg_01DEF",
+        "role": "assistant",
+        "content": [{"type": "text", "text": "And more"}],
+        "usage": {"input_tokens": 5, "output_tokens": 20}
+    },
+    "uuid": "11111111-0000-0000-0000-000000000003"
+}"#;
+
+#[test]
+fn parses_message_id() {
+    let msg = parse_message(STREAMED_BLOCK_1).unwrap();
+
+    assert_eq!(msg.message_id.as_deref(), Some("msg_01ABC"));
+}
+
+#[test]
+fn falls_back_to_request_id_without_message_id() {
+    let line = r#"{"type":"assistant","timestamp":"2026-02-03T17:37:02Z","requestId":"req_099","message":{"usage":{"input_tokens":1,"output_tokens":1}}}"#;
+
+    let msg = parse_message(line).unwrap();
+
+    assert_eq!(msg.message_id.as_deref(), Some("req_099"));
+}
+
+#[test]
+fn multi_block_response_counts_final_usage_once() {
+    let messages: Vec<ParsedMessage> = [STREAMED_BLOCK_1, STREAMED_BLOCK_2]
+        .iter()
+        .filter_map(|l| parse_message(l))
+        .collect();
+
+    let session = assemble_sessions(&messages, TimeDelta::minutes(15)).remove(0);
+
+    assert_eq!(session.input_tokens, 10);
+    assert_eq!(session.output_tokens, 1400);
+    assert_eq!(session.cache_creation_input_tokens, 500);
+    assert_eq!(session.cache_read_input_tokens, 2000);
+}
+
+#[test]
+fn distinct_responses_are_summed() {
+    let messages: Vec<ParsedMessage> = [STREAMED_BLOCK_1, STREAMED_BLOCK_2, NEXT_RESPONSE]
+        .iter()
+        .filter_map(|l| parse_message(l))
+        .collect();
+
+    let session = assemble_sessions(&messages, TimeDelta::minutes(15)).remove(0);
+
+    assert_eq!(session.input_tokens, 15);
+    assert_eq!(session.output_tokens, 1420);
+}
+
+#[test]
+fn multi_block_dedup_survives_saved_state() {
+    let mut acc = SessionAccumulator::new(TimeDelta::minutes(15));
+    acc.push(&parse_message(STREAMED_BLOCK_1).unwrap());
+    let saved = serde_json::to_string(&acc).unwrap();
+
+    let mut resumed: SessionAccumulator = serde_json::from_str(&saved).unwrap();
+    resumed.push(&parse_message(STREAMED_BLOCK_2).unwrap());
+
+    assert_eq!(resumed.sessions().remove(0).output_tokens, 1400);
+}
+
+#[test]
+fn duration_still_spans_every_block_of_a_response() {
+    let messages: Vec<ParsedMessage> = [STREAMED_BLOCK_1, STREAMED_BLOCK_2]
+        .iter()
+        .filter_map(|l| parse_message(l))
+        .collect();
+
+    let session = assemble_sessions(&messages, TimeDelta::minutes(15)).remove(0);
+
+    assert_eq!(session.duration.num_seconds(), 38);
+}
+
+#[test]
+fn parses_line_uuid() {
+    let msg = parse_message(USER_MESSAGE).unwrap();
+
+    assert_eq!(msg.uuid.as_deref(), Some("602ff260-e1a6-489f-b3cc-9ec2dac08e6a"));
+}
+
+// --- Per-model breakdown ------------------------------------------------
+
+fn model_line(id: &str, model: &str, timestamp: &str, output_tokens: u64) -> String {
+    format!(
+        r#"{{"type":"assistant","timestamp":"{}","message":{{"id":"{}","model":"{}","usage":{{"input_tokens":10,"output_tokens":{}}}}}}}"#,
+        timestamp, id, model, output_tokens
+    )
+}
+
+#[test]
+fn parses_model() {
+    let msg = parse_message(&model_line("msg_1", "claude-opus-4-1-20250805", "2026-02-03T10:00:00Z", 1)).unwrap();
+
+    assert_eq!(msg.model.as_deref(), Some("claude-opus-4-1-20250805"));
+}
+
+#[test]
+fn tokens_are_broken_down_by_model() {
+    let messages: Vec<ParsedMessage> = [
+        model_line("msg_1", "claude-opus-4-1-20250805", "2026-02-03T10:00:00Z", 100),
+        model_line("msg_2", "claude-haiku-4-5-20251001", "2026-02-03T10:01:00Z", 20),
+        model_line("msg_3", "claude-opus-4-1-20250805", "2026-02-03T10:02:00Z", 50),
+    ]
+    .iter()
+    .filter_map(|l| parse_message(l))
+    .collect();
+
+    let session = assemble_sessions(&messages, TimeDelta::minutes(15)).remove(0);
+
+    assert_eq!(session.models.len(), 2);
+    assert_eq!(session.models["claude-opus-4-1-20250805"].output_tokens, 150);
+    assert_eq!(session.models["claude-opus-4-1-20250805"].input_tokens, 20);
+    assert_eq!(session.models["claude-haiku-4-5-20251001"].output_tokens, 20);
+    assert_eq!(session.output_tokens, 170);
+}
+
+#[test]
+fn model_breakdown_counts_streamed_response_once() {
+    let messages: Vec<ParsedMessage> = [
+        model_line("msg_1", "claude-sonnet-4-5-20250929", "2026-02-03T10:00:00Z", 1),
+        model_line("msg_1", "claude-sonnet-4-5-20250929", "2026-02-03T10:00:30Z", 900),
+    ]
+    .iter()
+    .filter_map(|l| parse_message(l))
+    .collect();
+
+    let session = assemble_sessions(&messages, TimeDelta::minutes(15)).remove(0);
+
+    assert_eq!(session.models["claude-sonnet-4-5-20250929"].output_tokens, 900);
+    assert_eq!(session.models["claude-sonnet-4-5-20250929"].input_tokens, 10);
+}
+
+#[test]
+fn usage_without_model_goes_to_unknown_bucket() {
+    let session = assemble_sessions(&[parse_message(ASSISTANT_MESSAGE).unwrap()], TimeDelta::minutes(15)).remove(0);
+
+    assert_eq!(session.models[UNKNOWN_MODEL].output_tokens, 50);
+}
+
+#[test]
+fn usage_without_model_is_charged_to_the_last_named_model() {
+    let mut context = msg("2026-02-03T10:00:00Z");
+    context.model = Some("gpt-5-codex".to_string());
+    let usage = msg_in(Some("/work/project"), "2026-02-03T10:01:00Z", 40);
+
+    let session = assemble_sessions(&[context, usage], TimeDelta::minutes(15)).remove(0);
+
+    assert_eq!(session.models["gpt-5-codex"].output_tokens, 40);
+    assert!(!session.models.contains_key(UNKNOWN_MODEL));
+}
+
+#[test]
+fn push_reports_carried_context_and_replaced_usage() {
+    let mut acc = SessionAccumulator::new(TimeDelta::minutes(15));
+    let mut first = msg_in(Some("/work/api"), "2026-02-03T10:00:00Z", 10);
+    first.model = Some("claude-opus-4-1".to_string());
+    first.git_branch = Some("main".to_string());
+    first.message_id = Some("msg_1".to_string());
+    let mut second = msg_in(None, "2026-02-03T10:00:01Z", 30);
+    second.message_id = Some("msg_1".to_string());
+
+    assert!(!acc.push(&first).replaces_previous);
+    let attribution = acc.push(&second);
+
+    assert_eq!(attribution.project, "/work/api");
+    assert_eq!(attribution.branch.as_deref(), Some("main"));
+    assert_eq!(attribution.model.as_deref(), Some("claude-opus-4-1"));
+    assert!(attribution.replaces_previous);
+}
+
+// --- Per-cwd segments ---------------------------------------------------
+
+fn msg_in(cwd: Option<&str>, timestamp: &str, output_tokens: u64) -> ParsedMessage {
+    ParsedMessage {
+        timestamp: timestamp.parse().unwrap(),
+        cwd: cwd.map(|c| c.to_string()),
+        usage: Some(TokenUsage {
+            input_tokens: 0,
+            output_tokens,
+            cache_creation_input_tokens: 0,
+            cache_read_input_tokens: 0,
+        }),
+        message_id: None,
+        uuid: None,
+        model: None,
+        git_branch: None,
+        session_id: None,
+        tool_uses: Vec::new(),
+        tool_results: Vec::new(),
+        prompt: None,
+        role: None,
+    }
+}
+
+#[test]
+fn cwd_change_splits_session_into_segments() {
+    let messages = vec![
+        msg_in(Some("/work/api"), "2026-02-03T10:00:00Z", 1),
+        msg_in(Some("/work/api"), "2026-02-03T10:10:00Z", 2),
+        msg_in(Some("/work/web"), "2026-02-03T10:12:00Z", 4),
+        msg_in(Some("/work/web"), "2026-02-03T10:20:00Z", 8),
+    ];
+
+    let sessions = assemble_sessions(&messages, TimeDelta::minutes(15));
+
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[0].project, "/work/api");
+    assert_eq!(sessions[0].duration.num_seconds(), 600);
+    assert_eq!(sessions[0].output_tokens, 3);
+    // The 2m gap leading into the first /work/web message is credited to it.
+    assert_eq!(sessions[1].project, "/work/web");
+    assert_eq!(sessions[1].duration.num_seconds(), 600);
+    assert_eq!(sessions[1].output_tokens, 12);
+    assert_eq!(sessions[1].start, "2026-02-03T10:10:00Z".parse::<DateTime<Utc>>().unwrap());
+}
+
+#[test]
+fn returning_to_a_directory_reuses_its_segment() {
+    let messages = vec![
+        msg_in(Some("/work/api"), "2026-02-03T10:00:00Z", 1),
+        msg_in(Some("/work/web"), "2026-02-03T10:05:00Z", 1),
+        msg_in(Some("/work/api"), "2026-02-03T10:07:00Z", 1),
+    ];
+
+    let sessions = assemble_sessions(&messages, TimeDelta::minutes(15));
+
+    assert_eq!(sessions.len(), 2);
+    assert_eq!(sessions[0].project, "/work/api");
+    assert_eq!(sessions[0].duration.num_seconds(), 120);
+    assert_eq!(sessions[0].output_tokens, 2);
+    assert_eq!(sessions[1].duration.num_seconds(), 300);
+}
+
+#[test]
+fn lines_without_cwd_stay_in_current_segment() {
+    let messages = vec![
+        msg_in(None, "2026-02-03T10:00:00Z", 1),
+        msg_in(Some("/work/api"), "2026-02-03T10:01:00Z", 1),
+        msg_in(None, "2026-02-03T10:02:00Z", 1),
+    ];
+
+    let sessions = assemble_sessions(&messages, TimeDelta::minutes(15));
+
+    assert_eq!(sessions.len(), 1);
+    assert_eq!(sessions[0].project, "/work/api");
+    assert_eq!(sessions[0].output_tokens, 3);
+    assert_eq!(sessions[0].duration.num_seconds(), 120);
+}
+
+// --- Active intervals ---------------------------------------------------
+
+fn interval(start: &str, end: &str) -> Interval {
+    Interval { start: start.parse().unwrap(), end: end.parse().unwrap(), branch: None }
+}
+
+#[test]
+fn idle_gaps_split_active_intervals() {
+    let messages = vec![
+        msg("2026-02-03T10:00:00Z"),
+        msg("2026-02-03T10:05:00Z"),
+        msg("2026-02-03T10:08:00Z"),
+        msg("2026-02-03T11:00:00Z"),
+        msg("2026-02-03T11:04:00Z"),
+    ];
+
+    let session = assemble_sessions(&messages, TimeDelta::minutes(15)).remove(0);
+
+    assert_eq!(
+        session.intervals,
+        vec![
+            interval("2026-02-03T10:00:00Z", "2026-02-03T10:08:00Z"),
+            interval("2026-02-03T11:00:00Z", "2026-02-03T11:04:00Z"),
+        ]
+    );
+    let total: TimeDelta = session.intervals.iter().map(|i| i.duration()).sum();
+    assert_eq!(total, session.duration);
+}
+
+#[test]
+fn single_message_has_no_intervals() {
+    let session = assemble_sessions(&[msg("2026-02-03T10:00:00Z")], TimeDelta::minutes(15)).remove(0);
+
+    assert!(session.intervals.is_empty());
+}
+
+#[test]
+fn clip_keeps_only_the_overlap() {
+    let i = interval("2026-02-03T23:50:00Z", "2026-02-04T00:20:00Z");
+    let midnight: DateTime<Utc> = "2026-02-04T00:00:00Z".parse().unwrap();
+    let next_day: DateTime<Utc> = "2026-02-05T00:00:00Z".parse().unwrap();
+    let day_before: DateTime<Utc> = "2026-02-03T00:00:00Z".parse().unwrap();
+
+    assert_eq!(i.clip(midnight, next_day).unwrap().duration().num_minutes(), 20);
+    assert_eq!(i.clip(day_before, midnight).unwrap().duration().num_minutes(), 10);
+    assert!(i.clip(next_day, next_day + TimeDelta::days(1)).is_none());
+}
+
+// --- Git branch ---------------------------------------------------------
+
+fn msg_on(branch: Option<&str>, timestamp: &str) -> ParsedMessage {
+    ParsedMessage { git_branch: branch.map(|b| b.to_string()), ..msg(timestamp) }
+}
+
+#[test]
+fn parses_git_branch() {
+    let line = r#"{"type":"user","timestamp":"2026-02-03T10:00:00Z","cwd":"/w","gitBranch":"feat/login"}"#;
+    assert_eq!(parse_message(line).unwrap().git_branch.as_deref(), Some("feat/login"));
+
+    let line = r#"{"type":"user","timestamp":"2026-02-03T10:00:00Z","cwd":"/w","gitBranch":""}"#;
+    assert_eq!(parse_message(line).unwrap().git_branch, None);
+}
+
+#[test]
+fn branch_switch_starts_a_new_interval() {
+    let messages = vec![
+        msg_on(Some("main"), "2026-02-03T10:00:00Z"),
+        msg_on(Some("main"), "2026-02-03T10:05:00Z"),
+        msg_on(Some("feat/login"), "2026-02-03T10:08:00Z"),
+        msg_on(None, "2026-02-03T10:10:00Z"),
+    ];
+
+    let session = assemble_sessions(&messages, TimeDelta::minutes(15)).remove(0);
+
+    assert_eq!(session.intervals.len(), 2);
+    assert_eq!(session.intervals[0].branch.as_deref(), Some("main"));
+    // The gap leading into the first feat/login line is credited to it, and
+    // the branchless line after it stays on feat/login.
+    assert_eq!(session.intervals[1].branch.as_deref(), Some("feat/login"));
+    assert_eq!(session.intervals[1].duration().num_minutes(), 5);
+
+    let branches = session.branch_durations();
+    assert_eq!(branches["main"].num_minutes(), 5);
+    assert_eq!(branches["feat/login"].num_minutes(), 5);
+}
+
+// --- Session id ---------------------------------------------------------
+
+#[test]
+fn accumulator_keeps_first_session_id() {
+    let first = ParsedMessage { session_id: Some("parent".into()), ..msg("2026-02-03T10:00:00Z") };
+    let second = ParsedMessage { session_id: Some("other".into()), ..msg("2026-02-03T10:01:00Z") };
+    let mut acc = SessionAccumulator::new(TimeDelta::minutes(15));
+    acc.push(&msg("2026-02-03T09:59:00Z"));
+    acc.push(&first);
+    acc.push(&second);
+
+    assert_eq!(acc.session_id(), Some("parent"));
+    assert_eq!(parse_message(USER_MESSAGE).unwrap().session_id.as_deref(), Some("8e17c8fc-560f-43be-9e19-c99b6a6da169"));
+}
+
+// --- Tool usage ---------------------------------------------------------
+
+const TOOL_USE_LINE: &str = r#"{"type":"assistant","timestamp":"2026-02-03T10:00:00Z","cwd":"/work/api","message":{"role":"assistant","content":[{"type":"text","text":"Running it"},{"type":"tool_use","id":"toolu_1","name":"Bash","input":{"command":"ls"}},{"type":"tool_use","id":"toolu_2","name":"mcp__github__get_issue","input":{}}]}}"#;
+
+const TOOL_RESULT_LINE: &str = r#"{"type":"user","timestamp":"2026-02-03T10:00:05Z","cwd":"/work/api","message":{"role":"user","content":[{"type":"tool_result","tool_use_id":"toolu_1","content":"exit 1","is_error":true},{"type":"tool_result","tool_use_id":"toolu_2","content":"ok"}]}}"#;
+
+#[test]
+fn parses_tool_uses_and_results() {
+    let uses: Vec<_> = parse_message(TOOL_USE_LINE)
+        .unwrap()
+        .tool_uses
+        .into_iter()
+        .map(|t| (t.id, t.name))
+        .collect();
+    assert_eq!(uses, vec![
+        ("toolu_1".to_string(), "Bash".to_string()),
+        ("toolu_2".to_string(), "mcp__github__get_issue".to_string()),
+    ]);
+
+    let results = parse_message(TOOL_RESULT_LINE).unwrap().tool_results;
+    assert_eq!(results, vec![("toolu_1".to_string(), true), ("toolu_2".to_string(), false)]);
+}
+
+#[test]
+fn counts_tool_calls_and_errors() {
+    let messages = vec![parse_message(TOOL_USE_LINE).unwrap(), parse_message(TOOL_RESULT_LINE).unwrap()];
+
+    let session = assemble_sessions(&messages, TimeDelta::minutes(15)).remove(0);
+
+    assert_eq!(session.tools["Bash"], ToolStats { calls: 1, errors: 1 });
+    assert_eq!(session.tools["mcp__github__get_issue"], ToolStats { calls: 1, errors: 0 });
+}
+
+#[test]
+fn tool_error_is_charged_to_the_calling_segment() {
+    let result_elsewhere = TOOL_RESULT_LINE.replace("/work/api", "/work/web");
+    let messages = vec![parse_message(TOOL_USE_LINE).unwrap(), parse_message(&result_elsewhere).unwrap()];
+
+    let sessions = assemble_sessions(&messages, TimeDelta::minutes(15));
+
+    assert_eq!(sessions[0].project, "/work/api");
+    assert_eq!(sessions[0].tools["Bash"].errors, 1);
+    assert!(sessions[1].tools.is_empty());
+}
+
+// --- Lines changed ------------------------------------------------------
+
+fn edit_line(id: &str, name: &str, input: &str) -> String {
+    format!(
+        r#"{{"type":"assistant","timestamp":"2026-02-03T10:00:00Z","cwd":"/work/api","message":{{"content":[{{"type":"tool_use","id":"{}","name":"{}","input":{}}}]}}}}"#,
+        id, name, input
+    )
+}
+
+#[test]
+fn edit_counts_only_the_changed_lines() {
+    let line = edit_line("t1", "Edit", r#"{"file_path":"/a.rs","old_string":"fn a() {\n    1\n}","new_string":"fn a() {\n    2\n    3\n}"}"#);
+
+    let tool = parse_message(&line).unwrap().tool_uses.remove(0);
+
+    assert_eq!(tool.lines, LineChanges { added: 2, removed: 1 });
+}
+
+#[test]
+fn multi_edit_and_write_count_lines() {
+    let multi = edit_line("t1", "MultiEdit", r#"{"file_path":"/a.rs","edits":[{"old_string":"a","new_string":"b\nc"},{"old_string":"x\ny","new_string":""}]}"#);
+    let write = edit_line("t2", "Write", r#"{"file_path":"/b.rs","content":"one\ntwo\nthree\n"}"#);
+    let bash = edit_line("t3", "Bash", r#"{"command":"echo hi"}"#);
+
+    assert_eq!(parse_message(&multi).unwrap().tool_uses[0].lines, LineChanges { added: 2, removed: 3 });
+    assert_eq!(parse_message(&write).unwrap().tool_uses[0].lines, LineChanges { added: 3, removed: 0 });
+    assert_eq!(parse_message(&bash).unwrap().tool_uses[0].lines, LineChanges::default());
+}
+
+#[test]
+fn failed_edit_lines_are_not_counted() {
+    let ok = edit_line("t1", "Write", r#"{"content":"a\nb"}"#);
+    let failed = edit_line("t2", "Edit", r#"{"old_string":"missing","new_string":"x\ny\nz"}"#);
+    let result = r#"{"type":"user","timestamp":"2026-02-03T10:00:01Z","cwd":"/work/api","message":{"content":[{"type":"tool_result","tool_use_id":"t2","content":"String not found","is_error":true}]}}"#;
+    let messages = vec![parse_message(&ok).unwrap(), parse_message(&failed).unwrap(), parse_message(result).unwrap()];
+
+    let session = assemble_sessions(&messages, TimeDelta::minutes(15)).remove(0);
+
+    assert_eq!(session.lines, LineChanges { added: 2, removed: 0 });
+}
+
+// --- Titles -------------------------------------------------------------
+
+#[test]
+fn parses_summary_line() {
+    let line = r#"{"type":"summary","summary":"Fix login redirect loop","leafUuid":"d2c39245"}"#;
+
+    assert!(matches!(parse_line(line), Some(Line::Summary(s)) if s == "Fix login redirect loop"));
+    assert!(parse_message(line).is_none());
+}
+
+#[test]
+fn prompt_is_first_typed_line() {
+    assert_eq!(parse_message(USER_MESSAGE).unwrap().prompt.as_deref(), Some("hello"));
+
+    let plain = r#"{"type":"user","timestamp":"2026-02-03T10:00:00Z","message":{"role":"user","content":"\n  Add a --json flag\nso scripts can read it"}}"#;
+    assert_eq!(parse_message(plain).unwrap().prompt.as_deref(), Some("Add a --json flag"));
+
+    let command = r#"{"type":"user","timestamp":"2026-02-03T10:00:00Z","message":{"role":"user","content":"<command-name>/clear</command-name>"}}"#;
+    assert_eq!(parse_message(command).unwrap().prompt, None);
+
+    let meta = r#"{"type":"user","isMeta":true,"timestamp":"2026-02-03T10:00:00Z","message":{"role":"user","content":"Caveat: the messages below were generated"}}"#;
+    assert_eq!(parse_message(meta).unwrap().prompt, None);
+
+    assert_eq!(parse_message(ASSISTANT_MESSAGE).unwrap().prompt, None);
+}
+
+#[test]
+fn title_prefers_summary_over_first_prompt() {
+    let mut acc = SessionAccumulator::new(TimeDelta::minutes(15));
+    acc.push(&ParsedMessage { prompt: Some("first".into()), ..msg("2026-02-03T10:00:00Z") });
+    acc.push(&ParsedMessage { prompt: Some("second".into()), ..msg("2026-02-03T10:01:00Z") });
+    assert_eq!(acc.sessions()[0].title.as_deref(), Some("first"));
+
+    acc.push_summary("Old summary");
+    acc.push_summary("Refactor the scanner");
+    assert_eq!(acc.sessions()[0].title.as_deref(), Some("Refactor the scanner"));
+}
+
+#[test]
+fn long_prompt_title_is_shortened() {
+    let mut acc = SessionAccumulator::new(TimeDelta::minutes(15));
+    acc.push(&ParsedMessage { prompt: Some("é".repeat(80)), ..msg("2026-02-03T10:00:00Z") });
+
+    let title = acc.title().unwrap();
+
+    assert_eq!(title.chars().count(), 60);
+    assert!(title.ends_with('…'));
+}
+
+// --- Oversized lines ----------------------------------------------------
+
+const LONG_ASSISTANT: &str = r#"{"parentUuid":"p1","cwd":"/work/\"quoted\" dir","sessionId":"s1","gitBranch":"main","message":{"model":"claude-opus-4-1-20250805","id":"msg_1","type":"message","role":"assistant","content":[{"type":"tool_use","id":"toolu_1","name":"Write","input":{"content":"{\"timestamp\":\"1999-01-01T00:00:00Z\",\"usage\":{}}"}}],"usage":{"input_tokens":10,"cache_read_input_tokens":20,"output_tokens":30}},"requestId":"req_1","type":"assistant","uuid":"u1","timestamp":"2026-02-03T10:00:00Z"}"#;
+
+fn scan(chunk_size: usize) -> ScannedFields {
+    let mut scanner = FieldScanner::default();
+    for chunk in LONG_ASSISTANT.as_bytes().chunks(chunk_size) {
+        scanner.feed(chunk);
+    }
+    scanner.finish()
+}
+
+#[test]
+fn scanner_finds_fields_however_the_line_is_split() {
+    for chunk_size in [1, 7, LONG_ASSISTANT.len()] {
+        let fields = scan(chunk_size);
+
+        assert_eq!(fields.get("type"), Some("assistant"));
+        assert_eq!(fields.get("cwd"), Some(r#"/work/"quoted" dir"#));
+        assert_eq!(fields.get("message.model"), Some("claude-opus-4-1-20250805"));
+        assert_eq!(
+            fields.usage.as_deref(),
+            Some(r#"{"input_tokens":10,"cache_read_input_tokens":20,"output_tokens":30}"#)
+        );
+    }
+}
+
+#[test]
+fn scanner_ignores_fields_inside_content() {
+    let fields = scan(64);
+
+    assert_eq!(fields.get("timestamp"), Some("2026-02-03T10:00:00Z"));
+    assert_eq!(fields.get("message.content.type"), None);
+    assert_eq!(fields.get("message.type"), Some("message"));
+}
+
+#[test]
+fn scanned_fields_become_a_message_without_content() {
+    let message = message_from_fields(&scan(64)).unwrap();
+    let full = parse_message(LONG_ASSISTANT).unwrap();
+
+    assert_eq!(message.timestamp, full.timestamp);
+    assert_eq!(message.usage, full.usage);
+    assert_eq!(message.message_id.as_deref(), Some("msg_1"));
+    assert_eq!(message.uuid.as_deref(), Some("u1"));
+    assert_eq!(message.git_branch.as_deref(), Some("main"));
+    assert_eq!(full.tool_uses.len(), 1);
+    assert!(message.tool_uses.is_empty());
+}
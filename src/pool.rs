@@ -0,0 +1,146 @@
+//! Per-file work spread over a few threads, with results handed back in
+//! order on the calling thread.
+//!
+//! Reading and parsing files is independent and runs on the workers; the
+//! store isn't shared between threads, so everything that touches it
+//! happens as results are merged, in the same order a serial pass would
+//! use. That keeps first-claim-wins deduplication deterministic.
+//!
+//! A job can also stream its results in pieces, so a big file is merged
+//! as it's read rather than held whole.
+
+use anyhow::Result;
+use std::collections::BTreeMap;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Condvar, Mutex};
+
+/// How many jobs past the last merged one each worker may run, so results
+/// waiting to be merged stay bounded.
+const RUN_AHEAD: usize = 4;
+
+/// How many of a streaming job's results may wait to be merged before its
+/// worker blocks.
+const STREAM_AHEAD: usize = 4;
+
+/// How far a pass over files has got.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Progress {
+    /// What's being done, e.g. "parsing".
+    pub task: &'static str,
+    pub done: usize,
+    pub total: usize,
+}
+
+pub struct Workers<'a> {
+    pub threads: usize,
+    /// Called on the calling thread after each merged job.
+    pub progress: &'a dyn Fn(Progress),
+}
+
+impl Default for Workers<'_> {
+    fn default() -> Self {
+        Self { threads: default_threads(), progress: &ignore_progress }
+    }
+}
+
+fn ignore_progress(_: Progress) {}
+
+/// One worker per CPU.
+pub fn default_threads() -> usize {
+    std::thread::available_parallelism().map_or(1, |n| n.get())
+}
+
+impl Workers<'_> {
+    /// Run `work` over `jobs` on the workers and `merge` each result, by
+    /// job index, in job order. The first merge error stops the run.
+    pub fn run<J: Sync, T: Send>(
+        &self,
+        task: &'static str,
+        jobs: &[J],
+        work: impl Fn(&J) -> T + Sync,
+        mut merge: impl FnMut(usize, T) -> Result<()>,
+    ) -> Result<()> {
+        self.stream(
+            task,
+            jobs,
+            |job, emit| {
+                emit(work(job));
+            },
+            |index, results| merge(index, results.next().expect("each job emits one result")),
+        )
+    }
+
+    /// Like `run`, but `work` hands each job's results to `emit` as they're
+    /// produced, and `merge` reads them back in order while the job is
+    /// still running. `emit` blocks while a few results are waiting, and
+    /// returns false once the merge has stopped listening.
+    pub fn stream<J: Sync, T: Send>(
+        &self,
+        task: &'static str,
+        jobs: &[J],
+        work: impl Fn(&J, &mut dyn FnMut(T) -> bool) + Sync,
+        mut merge: impl FnMut(usize, &mut dyn Iterator<Item = T>) -> Result<()>,
+    ) -> Result<()> {
+        let total = jobs.len();
+        let next = AtomicUsize::new(0);
+        let stop = AtomicBool::new(false);
+        let merged = (Mutex::new(0usize), Condvar::new());
+        let window = self.threads.max(1) * RUN_AHEAD;
+        let (tx, rx) = mpsc::channel();
+
+        std::thread::scope(|scope| {
+            for _ in 0..self.threads.clamp(1, total.max(1)) {
+                let (tx, next, stop, merged, work) = (tx.clone(), &next, &stop, &merged, &work);
+                scope.spawn(move || {
+                    loop {
+                        let index = next.fetch_add(1, Ordering::Relaxed);
+                        if index >= total {
+                            return;
+                        }
+                        let (lock, advanced) = merged;
+                        let mut done = lock.lock().unwrap();
+                        while index >= *done + window && !stop.load(Ordering::Relaxed) {
+                            done = advanced.wait(done).unwrap();
+                        }
+                        drop(done);
+                        let (results, receiver) = mpsc::sync_channel(STREAM_AHEAD);
+                        if stop.load(Ordering::Relaxed) || tx.send((index, receiver)).is_err() {
+                            return;
+                        }
+                        work(&jobs[index], &mut |result| results.send(result).is_ok());
+                    }
+                });
+            }
+            drop(tx);
+
+            // Dropping a job's receiver, here or when the run stops, tells
+            // its worker nobody's listening any more.
+            let mut waiting = BTreeMap::new();
+            let result = (0..total).try_for_each(|index| {
+                let receiver: mpsc::Receiver<T> = loop {
+                    if let Some(receiver) = waiting.remove(&index) {
+                        break receiver;
+                    }
+                    let (i, receiver) = rx.recv().expect("workers run every job");
+                    waiting.insert(i, receiver);
+                };
+                merge(index, &mut receiver.iter())?;
+                *merged.0.lock().unwrap() = index + 1;
+                merged.1.notify_all();
+                (self.progress)(Progress { task, done: index + 1, total });
+                Ok(())
+            });
+            if result.is_err() {
+                stop.store(true, Ordering::Relaxed);
+                let _lock = merged.0.lock().unwrap();
+                merged.1.notify_all();
+            }
+            drop(waiting);
+            drop(rx);
+            result
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,110 @@
+use super::*;
+use std::cell::RefCell;
+use std::time::Duration;
+
+#[test]
+fn results_are_merged_in_job_order() -> Result<()> {
+    let jobs: Vec<u64> = (0..50).collect();
+    let mut merged = Vec::new();
+
+    // Early jobs take longest, so later ones finish first.
+    Workers { threads: 4, ..Default::default() }.run(
+        "squaring",
+        &jobs,
+        |&n| {
+            std::thread::sleep(Duration::from_millis(10u64.saturating_sub(n)));
+            n * n
+        },
+        |index, square| {
+            merged.push((index, square));
+            Ok(())
+        },
+    )?;
+
+    assert_eq!(merged, jobs.iter().map(|&n| (n as usize, n * n)).collect::<Vec<_>>());
+    Ok(())
+}
+
+#[test]
+fn progress_counts_merged_jobs() -> Result<()> {
+    let seen = RefCell::new(Vec::new());
+    let progress = |p: Progress| seen.borrow_mut().push((p.task, p.done, p.total));
+
+    Workers { threads: 2, progress: &progress }.run("parsing", &[1, 2, 3], |&n| n, |_, _| Ok(()))?;
+
+    assert_eq!(*seen.borrow(), vec![("parsing", 1, 3), ("parsing", 2, 3), ("parsing", 3, 3)]);
+    Ok(())
+}
+
+#[test]
+fn merge_error_stops_the_run() {
+    let jobs: Vec<usize> = (0..1000).collect();
+    let worked = AtomicUsize::new(0);
+
+    let result = Workers { threads: 2, ..Default::default() }.run(
+        "failing",
+        &jobs,
+        |&n| {
+            worked.fetch_add(1, Ordering::Relaxed);
+            n
+        },
+        |index, _| if index == 3 { anyhow::bail!("store is gone") } else { Ok(()) },
+    );
+
+    assert!(result.is_err());
+    assert!(worked.load(Ordering::Relaxed) < jobs.len());
+}
+
+#[test]
+fn streamed_results_are_merged_in_job_order() -> Result<()> {
+    let jobs: Vec<usize> = (0..20).collect();
+    let mut merged = Vec::new();
+
+    Workers { threads: 4, ..Default::default() }.stream(
+        "counting",
+        &jobs,
+        |&n, emit| {
+            for i in 0..n {
+                emit((n, i));
+            }
+        },
+        |_, results| {
+            merged.extend(results);
+            Ok(())
+        },
+    )?;
+
+    let expected: Vec<_> = jobs.iter().flat_map(|&n| (0..n).map(move |i| (n, i))).collect();
+    assert_eq!(merged, expected);
+    Ok(())
+}
+
+#[test]
+fn stream_stops_once_the_merge_stops_reading() -> Result<()> {
+    let emitted = AtomicUsize::new(0);
+
+    Workers { threads: 1, ..Default::default() }.stream(
+        "reading",
+        &[1000],
+        |&n, emit| {
+            for i in 0..n {
+                if !emit(i) {
+                    return;
+                }
+                emitted.fetch_add(1, Ordering::Relaxed);
+            }
+        },
+        |_, results| {
+            assert_eq!(results.next(), Some(0));
+            Ok(())
+        },
+    )?;
+
+    assert!(emitted.load(Ordering::Relaxed) <= 1 + STREAM_AHEAD);
+    Ok(())
+}
+
+#[test]
+fn no_jobs_is_a_no_op() -> Result<()> {
+    Workers::default().run("nothing", &[] as &[u8], |_| (), |_, _| panic!("nothing to merge"))
+}
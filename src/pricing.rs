@@ -0,0 +1,84 @@
+//! Dollar cost estimates from token counts.
+//!
+//! Rates are USD per million tokens, keyed by model id prefix. The most
+//! specific (longest) matching prefix wins, so "claude-opus-4-5" can be
+//! priced apart from the rest of "claude-opus-4".
+
+use serde::Deserialize;
+use std::collections::{BTreeMap, HashMap};
+
+use crate::parser::TokenUsage;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize)]
+pub struct ModelPrice {
+    pub input: f64,
+    pub output: f64,
+    pub cache_write: f64,
+    pub cache_read: f64,
+}
+
+/// Published Anthropic list prices. Overridable per model in `[pricing]`.
+const DEFAULT_PRICES: &[(&str, ModelPrice)] = &[
+    ("claude-opus-4-5", ModelPrice { input: 5.0, output: 25.0, cache_write: 6.25, cache_read: 0.50 }),
+    ("claude-opus-4", ModelPrice { input: 15.0, output: 75.0, cache_write: 18.75, cache_read: 1.50 }),
+    ("claude-3-opus", ModelPrice { input: 15.0, output: 75.0, cache_write: 18.75, cache_read: 1.50 }),
+    ("claude-sonnet-4", ModelPrice { input: 3.0, output: 15.0, cache_write: 3.75, cache_read: 0.30 }),
+    ("claude-3-7-sonnet", ModelPrice { input: 3.0, output: 15.0, cache_write: 3.75, cache_read: 0.30 }),
+    ("claude-3-5-sonnet", ModelPrice { input: 3.0, output: 15.0, cache_write: 3.75, cache_read: 0.30 }),
+    ("claude-haiku-4-5", ModelPrice { input: 1.0, output: 5.0, cache_write: 1.25, cache_read: 0.10 }),
+    ("claude-3-5-haiku", ModelPrice { input: 0.80, output: 4.0, cache_write: 1.0, cache_read: 0.08 }),
+    ("claude-3-haiku", ModelPrice { input: 0.25, output: 1.25, cache_write: 0.30, cache_read: 0.03 }),
+];
+
+pub struct PricingTable {
+    prices: Vec<(String, ModelPrice)>,
+}
+
+impl Default for PricingTable {
+    fn default() -> Self {
+        Self::new(&HashMap::new())
+    }
+}
+
+impl PricingTable {
+    /// Built-in defaults with `overrides` (from config) layered on top.
+    pub fn new(overrides: &HashMap<String, ModelPrice>) -> Self {
+        let mut prices: HashMap<String, ModelPrice> = DEFAULT_PRICES
+            .iter()
+            .map(|(prefix, price)| (prefix.to_string(), *price))
+            .collect();
+        prices.extend(overrides.iter().map(|(k, v)| (k.clone(), *v)));
+
+        // Longest prefix first so the first match is the most specific.
+        let mut prices: Vec<_> = prices.into_iter().collect();
+        prices.sort_by(|a, b| b.0.len().cmp(&a.0.len()).then_with(|| a.0.cmp(&b.0)));
+        Self { prices }
+    }
+
+    pub fn price(&self, model: &str) -> Option<&ModelPrice> {
+        self.prices
+            .iter()
+            .find(|(prefix, _)| model.starts_with(prefix.as_str()))
+            .map(|(_, price)| price)
+    }
+
+    /// Cost of one model's usage. Unpriced models cost nothing.
+    pub fn cost(&self, model: &str, usage: &TokenUsage) -> f64 {
+        let Some(price) = self.price(model) else {
+            return 0.0;
+        };
+        (usage.input_tokens as f64 * price.input
+            + usage.output_tokens as f64 * price.output
+            + usage.cache_creation_input_tokens as f64 * price.cache_write
+            + usage.cache_read_input_tokens as f64 * price.cache_read)
+            / 1_000_000.0
+    }
+
+    /// Total cost of a per-model breakdown.
+    pub fn total_cost(&self, models: &BTreeMap<String, TokenUsage>) -> f64 {
+        models.iter().map(|(model, usage)| self.cost(model, usage)).sum()
+    }
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,65 @@
+use super::*;
+
+fn usage(input: u64, output: u64, cache_write: u64, cache_read: u64) -> TokenUsage {
+    TokenUsage {
+        input_tokens: input,
+        output_tokens: output,
+        cache_creation_input_tokens: cache_write,
+        cache_read_input_tokens: cache_read,
+    }
+}
+
+#[test]
+fn most_specific_prefix_wins() {
+    let table = PricingTable::default();
+
+    assert_eq!(table.price("claude-opus-4-5-20251101").unwrap().input, 5.0);
+    assert_eq!(table.price("claude-opus-4-1-20250805").unwrap().input, 15.0);
+}
+
+#[test]
+fn unknown_model_is_unpriced() {
+    let table = PricingTable::default();
+
+    assert!(table.price("<synthetic>").is_none());
+    assert_eq!(table.cost("<synthetic>", &usage(1_000_000, 0, 0, 0)), 0.0);
+}
+
+#[test]
+fn cost_applies_each_rate_per_million() {
+    let table = PricingTable::default();
+
+    // Sonnet 4.5: $3 in, $15 out, $3.75 cache write, $0.30 cache read.
+    let cost = table.cost("claude-sonnet-4-5-20250929", &usage(1_000_000, 100_000, 200_000, 2_000_000));
+
+    assert!((cost - (3.0 + 1.5 + 0.75 + 0.6)).abs() < 1e-9);
+}
+
+#[test]
+fn overrides_replace_defaults_and_add_models() {
+    let mut overrides = HashMap::new();
+    overrides.insert(
+        "claude-sonnet-4".to_string(),
+        ModelPrice { input: 1.0, output: 2.0, cache_write: 0.0, cache_read: 0.0 },
+    );
+    overrides.insert(
+        "gpt-5".to_string(),
+        ModelPrice { input: 1.25, output: 10.0, cache_write: 0.0, cache_read: 0.125 },
+    );
+    let table = PricingTable::new(&overrides);
+
+    assert_eq!(table.price("claude-sonnet-4-5-20250929").unwrap().input, 1.0);
+    assert_eq!(table.price("gpt-5-codex").unwrap().output, 10.0);
+    assert_eq!(table.price("claude-haiku-4-5").unwrap().input, 1.0, "other defaults kept");
+}
+
+#[test]
+fn total_cost_sums_across_models() {
+    let table = PricingTable::default();
+    let mut models = BTreeMap::new();
+    models.insert("claude-opus-4-1-20250805".to_string(), usage(0, 1_000_000, 0, 0));
+    models.insert("claude-haiku-4-5-20251001".to_string(), usage(0, 1_000_000, 0, 0));
+    models.insert("unknown".to_string(), usage(0, 1_000_000, 0, 0));
+
+    assert!((table.total_cost(&models) - 80.0).abs() < 1e-9);
+}
@@ -0,0 +1,216 @@
+//! Plain-text reports for the `report` subcommand.
+
+use anyhow::Result;
+use chrono::{DateTime, Local, TimeDelta, Utc};
+use std::collections::HashMap;
+
+use crate::alias::Aliases;
+use crate::parser;
+use crate::store::{Bucket, Rollup, Store};
+
+/// Active time on one git branch of one project.
+pub(crate) struct BranchRow {
+    pub(crate) project: String,
+    /// Empty for time on lines without a branch.
+    pub(crate) branch: String,
+    pub(crate) duration: TimeDelta,
+    pub(crate) first_active: DateTime<Utc>,
+    pub(crate) last_active: DateTime<Utc>,
+}
+
+/// Sum interval time per (project, branch). Sorted by the project's short
+/// name, then longest branch first.
+pub(crate) fn by_branch(sessions: &[parser::Session]) -> Vec<BranchRow> {
+    let mut map: HashMap<(String, String), BranchRow> = HashMap::new();
+    for session in sessions {
+        for interval in &session.intervals {
+            let branch = interval.branch.clone().unwrap_or_default();
+            let row = map
+                .entry((session.project.clone(), branch.clone()))
+                .or_insert_with(|| BranchRow {
+                    project: session.project.clone(),
+                    branch,
+                    duration: TimeDelta::zero(),
+                    first_active: interval.start,
+                    last_active: interval.end,
+                });
+            row.duration += interval.duration();
+            row.first_active = row.first_active.min(interval.start);
+            row.last_active = row.last_active.max(interval.end);
+        }
+    }
+
+    let mut rows: Vec<_> = map.into_values().collect();
+    let names = project_names(rows.iter().map(|r| r.project.as_str()));
+    rows.sort_by(|a, b| {
+        names[&a.project]
+            .cmp(&names[&b.project])
+            .then_with(|| a.project.cmp(&b.project))
+            .then_with(|| b.duration.cmp(&a.duration))
+            .then_with(|| a.branch.cmp(&b.branch))
+    });
+    rows
+}
+
+/// Invocation counts for one tool in one project.
+pub(crate) struct ToolRow {
+    pub(crate) project: String,
+    pub(crate) tool: String,
+    pub(crate) stats: parser::ToolStats,
+}
+
+/// Sum tool calls per (project, tool). Sorted by the project's short name,
+/// then most used tool first.
+pub(crate) fn by_tool(sessions: &[parser::Session]) -> Vec<ToolRow> {
+    let mut map: HashMap<(String, String), parser::ToolStats> = HashMap::new();
+    for session in sessions {
+        for (tool, stats) in &session.tools {
+            map.entry((session.project.clone(), tool.clone())).or_default().add(stats);
+        }
+    }
+
+    let mut rows: Vec<_> = map
+        .into_iter()
+        .map(|((project, tool), stats)| ToolRow { project, tool, stats })
+        .collect();
+    let names = project_names(rows.iter().map(|r| r.project.as_str()));
+    rows.sort_by(|a, b| {
+        names[&a.project]
+            .cmp(&names[&b.project])
+            .then_with(|| a.project.cmp(&b.project))
+            .then_with(|| b.stats.calls.cmp(&a.stats.calls))
+            .then_with(|| a.tool.cmp(&b.tool))
+    });
+    rows
+}
+
+/// "2h 05m", or "45m" under an hour.
+pub(crate) fn format_duration(duration: TimeDelta) -> String {
+    let minutes = duration.num_minutes();
+    if minutes >= 60 {
+        format!("{}h {:02}m", minutes / 60, minutes % 60)
+    } else {
+        format!("{}m", minutes)
+    }
+}
+
+/// Display name for a branch key.
+pub(crate) fn branch_label(branch: &str) -> &str {
+    if branch.is_empty() { "(no branch)" } else { branch }
+}
+
+/// Each project's short name, as the TUI shows it.
+fn project_names<'a>(projects: impl Iterator<Item = &'a str>) -> HashMap<String, String> {
+    let mut projects: Vec<_> = projects.collect();
+    projects.sort_unstable();
+    projects.dedup();
+    let names = crate::short_names(&projects);
+    projects.into_iter().map(str::to_string).zip(names).collect()
+}
+
+/// Branch rows grouped under a heading per project.
+pub(crate) fn format_branch_report(rows: &[BranchRow]) -> String {
+    let width = rows.iter().map(|r| branch_label(&r.branch).len()).max().unwrap_or(0);
+    let names = project_names(rows.iter().map(|r| r.project.as_str()));
+    let mut out = String::new();
+    let mut current_project: Option<&str> = None;
+    for row in rows {
+        if current_project != Some(row.project.as_str()) {
+            if current_project.is_some() {
+                out.push('\n');
+            }
+            out.push_str(&format!("{}\n", names[row.project.as_str()]));
+            current_project = Some(&row.project);
+        }
+        out.push_str(&format!(
+            "  {:<width$}  {:>8}  {} → {}\n",
+            branch_label(&row.branch),
+            format_duration(row.duration),
+            row.first_active.with_timezone(&Local).format("%Y-%m-%d"),
+            row.last_active.with_timezone(&Local).format("%Y-%m-%d"),
+        ));
+    }
+    out
+}
+
+/// Tool rows grouped under a heading per project.
+pub(crate) fn format_tool_report(rows: &[ToolRow]) -> String {
+    let width = rows.iter().map(|r| r.tool.len()).max().unwrap_or(0);
+    let names = project_names(rows.iter().map(|r| r.project.as_str()));
+    let mut out = String::new();
+    let mut current_project: Option<&str> = None;
+    for row in rows {
+        if current_project != Some(row.project.as_str()) {
+            if current_project.is_some() {
+                out.push('\n');
+            }
+            out.push_str(&format!("{}\n", names[row.project.as_str()]));
+            current_project = Some(&row.project);
+        }
+        out.push_str(&format!(
+            "  {:<width$}  {:>6} calls  {:>4} errors\n",
+            row.tool, row.stats.calls, row.stats.errors,
+        ));
+    }
+    out
+}
+
+/// One line per hour or day with messages in it.
+pub(crate) fn format_activity_report(rollups: &[Rollup], bucket: Bucket) -> String {
+    let mut out = String::new();
+    for rollup in rollups {
+        let start = match bucket {
+            Bucket::Hour => rollup.start.format("%Y-%m-%d %H:00"),
+            Bucket::Day => rollup.start.format("%Y-%m-%d %a  "),
+        };
+        let tokens = &rollup.tokens;
+        out.push_str(&format!(
+            "  {}  {:>5} messages ({:>4} user)  {:>6} in  {:>6} out  {:>6} cached\n",
+            start,
+            rollup.messages,
+            rollup.user_messages,
+            crate::format_tokens(tokens.input_tokens + tokens.cache_creation_input_tokens),
+            crate::format_tokens(tokens.output_tokens),
+            crate::format_tokens(tokens.cache_read_input_tokens),
+        ));
+    }
+    out
+}
+
+/// Print a report of `[start, end)` grouped by `by`: "branch", "tool",
+/// "hour" or "day".
+pub fn run_report(
+    store: &Store,
+    aliases: &Aliases,
+    by: &str,
+    label: &str,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<()> {
+    let activity = |bucket| -> Result<String> {
+        Ok(format_activity_report(&store.message_rollups(start, end, bucket, None)?, bucket))
+    };
+    let body = match by {
+        "branch" => format_branch_report(&by_branch(&aliases.apply(store.query_range(start, end)?))),
+        "tool" => format_tool_report(&by_tool(&aliases.apply(store.query_range(start, end)?))),
+        "hour" => activity(Bucket::Hour)?,
+        "day" => activity(Bucket::Day)?,
+        other => anyhow::bail!("unknown report grouping: {}", other),
+    };
+
+    let what = match by {
+        "tool" => "Tool calls",
+        "hour" | "day" => "Activity",
+        _ => "Time",
+    };
+    println!("# {} by {} — {}\n", what, by, label);
+    if body.is_empty() {
+        println!("No activity.");
+    } else {
+        print!("{}", body);
+    }
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests;
@@ -0,0 +1,140 @@
+use super::*;
+
+fn interval(start: &str, end: &str, branch: Option<&str>) -> parser::Interval {
+    parser::Interval {
+        start: start.parse().unwrap(),
+        end: end.parse().unwrap(),
+        branch: branch.map(|b| b.to_string()),
+    }
+}
+
+fn session(project: &str, intervals: Vec<parser::Interval>) -> parser::Session {
+    parser::Session {
+        project: project.to_string(),
+        intervals,
+        ..Default::default()
+    }
+}
+
+#[test]
+fn sums_time_per_project_and_branch() {
+    let sessions = vec![
+        session("/work/api", vec![
+            interval("2026-02-03T10:00:00Z", "2026-02-03T10:30:00Z", Some("feat/login")),
+            interval("2026-02-03T11:00:00Z", "2026-02-03T11:10:00Z", Some("main")),
+        ]),
+        session("/work/api", vec![
+            interval("2026-02-05T09:00:00Z", "2026-02-05T09:45:00Z", Some("feat/login")),
+        ]),
+    ];
+
+    let rows = by_branch(&sessions);
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].branch, "feat/login");
+    assert_eq!(rows[0].duration.num_minutes(), 75);
+    assert_eq!(rows[0].first_active, "2026-02-03T10:00:00Z".parse::<DateTime<Utc>>().unwrap());
+    assert_eq!(rows[0].last_active, "2026-02-05T09:45:00Z".parse::<DateTime<Utc>>().unwrap());
+    assert_eq!(rows[1].branch, "main");
+    assert_eq!(rows[1].duration.num_minutes(), 10);
+}
+
+#[test]
+fn same_branch_name_in_two_projects_stays_separate() {
+    let sessions = vec![
+        session("/work/web", vec![interval("2026-02-03T10:00:00Z", "2026-02-03T10:30:00Z", Some("main"))]),
+        session("/work/api", vec![interval("2026-02-03T11:00:00Z", "2026-02-03T11:10:00Z", Some("main"))]),
+    ];
+
+    let rows = by_branch(&sessions);
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].project, "/work/api");
+    assert_eq!(rows[1].project, "/work/web");
+}
+
+#[test]
+fn report_groups_rows_under_project() {
+    let sessions = vec![session("/work/api", vec![
+        interval("2026-02-03T10:00:00Z", "2026-02-03T12:05:00Z", Some("feat/login")),
+        interval("2026-02-03T13:00:00Z", "2026-02-03T13:20:00Z", None),
+    ])];
+
+    let report = format_branch_report(&by_branch(&sessions));
+    let lines: Vec<_> = report.lines().collect();
+
+    assert_eq!(lines[0], "api");
+    assert!(lines[1].starts_with("  feat/login     2h 05m  "));
+    assert!(lines[2].starts_with("  (no branch)       20m  "));
+}
+
+#[test]
+fn projects_with_the_same_last_segment_get_distinct_headings() {
+    let sessions = vec![
+        session("/work/client-a/api", vec![interval("2026-02-03T10:00:00Z", "2026-02-03T10:30:00Z", None)]),
+        session("/work/client-b/api", vec![interval("2026-02-03T11:00:00Z", "2026-02-03T11:10:00Z", None)]),
+    ];
+
+    let report = format_branch_report(&by_branch(&sessions));
+    let headings: Vec<_> = report.lines().filter(|l| !l.starts_with(' ') && !l.is_empty()).collect();
+
+    assert_eq!(headings, ["client-a/api", "client-b/api"]);
+}
+
+#[test]
+fn projects_are_ordered_by_the_name_shown() {
+    let sessions = vec![
+        session("/home/client-b/api", vec![interval("2026-02-03T10:00:00Z", "2026-02-03T10:30:00Z", None)]),
+        session("/work/client-a/api", vec![interval("2026-02-03T11:00:00Z", "2026-02-03T11:10:00Z", None)]),
+    ];
+
+    let branches: Vec<_> = by_branch(&sessions).into_iter().map(|r| r.project).collect();
+    assert_eq!(branches, ["/work/client-a/api", "/home/client-b/api"]);
+
+    let mut a = session("/home/client-b/api", vec![]);
+    a.tools.insert("Bash".into(), parser::ToolStats { calls: 1, errors: 0 });
+    let mut b = session("/work/client-a/api", vec![]);
+    b.tools.insert("Bash".into(), parser::ToolStats { calls: 1, errors: 0 });
+    let tools: Vec<_> = by_tool(&[a, b]).into_iter().map(|r| r.project).collect();
+    assert_eq!(tools, ["/work/client-a/api", "/home/client-b/api"]);
+}
+
+#[test]
+fn sums_tool_calls_per_project() {
+    let mut a = session("/work/api", vec![]);
+    a.tools.insert("Bash".into(), parser::ToolStats { calls: 3, errors: 1 });
+    a.tools.insert("Edit".into(), parser::ToolStats { calls: 5, errors: 0 });
+    let mut b = session("/work/api", vec![]);
+    b.tools.insert("Bash".into(), parser::ToolStats { calls: 4, errors: 2 });
+
+    let rows = by_tool(&[a, b]);
+
+    assert_eq!(rows.len(), 2);
+    assert_eq!(rows[0].tool, "Bash");
+    assert_eq!(rows[0].stats, parser::ToolStats { calls: 7, errors: 3 });
+    assert_eq!(rows[1].tool, "Edit");
+
+    let report = format_tool_report(&rows);
+    assert_eq!(report.lines().nth(1), Some("  Bash       7 calls     3 errors"));
+}
+
+#[test]
+fn activity_report_has_a_line_per_bucket() {
+    let rollups = vec![Rollup {
+        start: "2026-02-03T09:00:00".parse().unwrap(),
+        messages: 42,
+        user_messages: 12,
+        tokens: parser::TokenUsage {
+            input_tokens: 1_000,
+            output_tokens: 3_400,
+            cache_creation_input_tokens: 200,
+            cache_read_input_tokens: 2_000_000,
+        },
+    }];
+
+    assert_eq!(
+        format_activity_report(&rollups, Bucket::Hour),
+        "  2026-02-03 09:00     42 messages (  12 user)    1.2k in    3.4k out      2M cached\n"
+    );
+    assert!(format_activity_report(&rollups, Bucket::Day).starts_with("  2026-02-03 Tue  "));
+}
@@ -1,8 +1,15 @@
+use anyhow::{Context, Result};
 use std::fs;
+use std::io::{BufRead, BufReader};
 use std::path::{Path, PathBuf};
 
-/// Find all session JSONL files under a Claude projects directory.
-/// Skips agent-* files and bare subdirectories.
+use crate::parser;
+
+/// Suffixes of transcripts compressed with gzip and zstd.
+const COMPRESSED_SUFFIXES: &[&str] = &[".gz", ".zst"];
+
+/// Find all session JSONL files, plain or compressed, under a Claude
+/// projects directory. Skips agent-* files and bare subdirectories.
 pub fn find_session_files(projects_dir: &Path) -> Vec<PathBuf> {
     let mut results = Vec::new();
 
@@ -28,10 +35,43 @@ pub fn find_session_files(projects_dir: &Path) -> Vec<PathBuf> {
                 continue;
             }
 
-            if let Some(name) = file_path.file_name().and_then(|n| n.to_str()) {
-                if name.ends_with(".jsonl") && !name.starts_with("agent-") {
-                    results.push(file_path);
-                }
+            if let Some(name) = file_path.file_name().and_then(|n| n.to_str())
+                && is_jsonl(name)
+                && !name.starts_with("agent-")
+            {
+                results.push(file_path);
+            }
+        }
+    }
+
+    results
+}
+
+/// Find subagent transcripts under a Claude projects directory: `agent-*`
+/// files next to the sessions, and under `<session>/subagents/` where newer
+/// Claude Code versions put them.
+pub fn find_subagent_files(projects_dir: &Path) -> Vec<PathBuf> {
+    let mut results = Vec::new();
+
+    let project_dirs = match fs::read_dir(projects_dir) {
+        Ok(entries) => entries,
+        Err(_) => return results,
+    };
+
+    for project_entry in project_dirs.flatten() {
+        let project_path = project_entry.path();
+        if !project_path.is_dir() {
+            continue;
+        }
+        collect_agent_files(&project_path, &mut results);
+
+        let Ok(entries) = fs::read_dir(&project_path) else {
+            continue;
+        };
+        for entry in entries.flatten() {
+            let subagents = entry.path().join("subagents");
+            if subagents.is_dir() {
+                collect_agent_files(&subagents, &mut results);
             }
         }
     }
@@ -39,5 +79,126 @@ pub fn find_session_files(projects_dir: &Path) -> Vec<PathBuf> {
     results
 }
 
+fn collect_agent_files(dir: &Path, results: &mut Vec<PathBuf>) {
+    let Ok(files) = fs::read_dir(dir) else {
+        return;
+    };
+    for file_entry in files.flatten() {
+        let file_path = file_entry.path();
+        if file_path.is_file() && is_subagent_file(&file_path) {
+            results.push(file_path);
+        }
+    }
+}
+
+/// True for `agent-*.jsonl` subagent transcripts.
+pub fn is_subagent_file(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|name| name.starts_with("agent-") && is_jsonl(name))
+}
+
+/// `.jsonl`, optionally followed by a compression suffix.
+pub fn is_jsonl(name: &str) -> bool {
+    let name = COMPRESSED_SUFFIXES
+        .iter()
+        .find_map(|suffix| name.strip_suffix(suffix))
+        .unwrap_or(name);
+    name.ends_with(".jsonl")
+}
+
+pub fn is_compressed(path: &Path) -> bool {
+    path.file_name()
+        .and_then(|n| n.to_str())
+        .is_some_and(|name| COMPRESSED_SUFFIXES.iter().any(|suffix| name.ends_with(suffix)))
+}
+
+/// A transcript's text, decompressed if its name says it's compressed.
+pub fn open_transcript(path: &Path) -> Result<Box<dyn BufRead>> {
+    let file = fs::File::open(path).with_context(|| format!("opening {:?}", path))?;
+    let name = path.to_string_lossy();
+    Ok(if name.ends_with(".gz") {
+        Box::new(BufReader::new(flate2::read::MultiGzDecoder::new(file)))
+    } else if name.ends_with(".zst") {
+        Box::new(BufReader::new(zstd::Decoder::new(file)?))
+    } else {
+        Box::new(BufReader::new(file))
+    })
+}
+
+/// One line of a transcript, without its newline. Bytes that aren't UTF-8
+/// are replaced with U+FFFD.
+pub enum TranscriptLine<'a> {
+    Text(&'a str),
+    /// Longer than `parser::MAX_LINE_BYTES`: scanned as it streamed past
+    /// rather than held.
+    Oversized(parser::ScannedFields),
+}
+
+/// Reads a transcript line by line, holding at most `MAX_LINE_BYTES` of
+/// it at a time.
+pub struct LineReader<R> {
+    inner: R,
+    line: Vec<u8>,
+}
+
+impl<R: BufRead> LineReader<R> {
+    pub fn new(inner: R) -> Self {
+        Self { inner, line: Vec::new() }
+    }
+
+    /// The next line, its length in bytes including the newline, and
+    /// whether it had one: only a partial last line doesn't. None at the
+    /// end.
+    pub fn next_line(&mut self) -> Result<Option<(TranscriptLine<'_>, u64, bool)>> {
+        self.line.clear();
+        let mut len = 0u64;
+        let mut scanner: Option<parser::FieldScanner> = None;
+        let complete = loop {
+            let available = self.inner.fill_buf()?;
+            if available.is_empty() {
+                break false;
+            }
+            let newline = available.iter().position(|&b| b == b'\n');
+            let chunk = &available[..newline.map_or(available.len(), |i| i + 1)];
+            match &mut scanner {
+                Some(scanner) => scanner.feed(chunk),
+                None => {
+                    self.line.extend_from_slice(chunk);
+                    if self.line.len() > parser::MAX_LINE_BYTES {
+                        let mut oversized = parser::FieldScanner::default();
+                        oversized.feed(&self.line);
+                        self.line.clear();
+                        scanner = Some(oversized);
+                    }
+                }
+            }
+            let consumed = chunk.len();
+            len += consumed as u64;
+            self.inner.consume(consumed);
+            if newline.is_some() {
+                break true;
+            }
+        };
+        if len == 0 {
+            return Ok(None);
+        }
+        let line = match scanner {
+            Some(scanner) => TranscriptLine::Oversized(scanner.finish()),
+            None => {
+                // Bad bytes turn up inside string content (a truncated tool
+                // output), so the line usually still parses once they're
+                // replaced; either way the lines after it are read.
+                if std::str::from_utf8(&self.line).is_err() {
+                    self.line = String::from_utf8_lossy(&self.line).into_owned().into_bytes();
+                }
+                let text = std::str::from_utf8(&self.line).expect("replaced invalid UTF-8");
+                TranscriptLine::Text(text.trim_end_matches(['\n', '\r']))
+            }
+        };
+        Ok(Some((line, len, complete)))
+    }
+}
+
 #[cfg(test)]
 mod tests;
@@ -46,3 +46,108 @@ fn skips_subdirectories() {
 
     assert_eq!(results.len(), 1);
 }
+
+#[test]
+fn finds_subagent_files_in_both_layouts() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let proj = dir.path().join("-Users-foo-project1");
+    let subagents = proj.join("aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa").join("subagents");
+    fs::create_dir_all(&subagents).unwrap();
+    fs::File::create(proj.join("aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa.jsonl")).unwrap();
+    fs::File::create(proj.join("agent-a095737.jsonl")).unwrap();
+    fs::File::create(subagents.join("agent-b18c2f0.jsonl")).unwrap();
+    fs::File::create(subagents.join("notes.txt")).unwrap();
+
+    let mut results = find_subagent_files(dir.path());
+    results.sort();
+
+    assert_eq!(results.len(), 2);
+    assert!(results[0].ends_with("aaaaaaaa-aaaa-aaaa-aaaa-aaaaaaaaaaaa/subagents/agent-b18c2f0.jsonl"));
+    assert!(results[1].ends_with("agent-a095737.jsonl"));
+}
+
+#[test]
+fn finds_compressed_session_files() {
+    let dir = tempfile::tempdir().unwrap();
+
+    let proj = dir.path().join("-Users-foo-project1");
+    fs::create_dir(&proj).unwrap();
+    fs::File::create(proj.join("aaaa.jsonl.gz")).unwrap();
+    fs::File::create(proj.join("bbbb.jsonl.zst")).unwrap();
+    fs::File::create(proj.join("cccc.tar.gz")).unwrap();
+    fs::File::create(proj.join("agent-a095737.jsonl.gz")).unwrap();
+
+    let mut results = find_session_files(dir.path());
+    results.sort();
+
+    assert_eq!(results, vec![proj.join("aaaa.jsonl.gz"), proj.join("bbbb.jsonl.zst")]);
+    assert!(is_subagent_file(&proj.join("agent-a095737.jsonl.gz")));
+}
+
+#[test]
+fn reads_compressed_transcripts() {
+    use std::io::{Read, Write};
+    let dir = tempfile::tempdir().unwrap();
+    let text = b"{\"type\":\"user\"}\n";
+
+    let gz = dir.path().join("a.jsonl.gz");
+    let mut encoder = flate2::write::GzEncoder::new(fs::File::create(&gz).unwrap(), flate2::Compression::default());
+    encoder.write_all(text).unwrap();
+    encoder.finish().unwrap();
+    let zst = dir.path().join("b.jsonl.zst");
+    fs::write(&zst, zstd::encode_all(&text[..], 0).unwrap()).unwrap();
+    let plain = dir.path().join("c.jsonl");
+    fs::write(&plain, text).unwrap();
+
+    let read = |path: &Path| {
+        let mut contents = Vec::new();
+        open_transcript(path).unwrap().read_to_end(&mut contents).unwrap();
+        contents
+    };
+    assert_eq!(read(&gz), text);
+    assert_eq!(read(&zst), text);
+    assert_eq!(read(&plain), text);
+    assert!(is_compressed(&gz) && is_compressed(&zst) && !is_compressed(&plain));
+}
+
+#[test]
+fn line_reader_scans_oversized_lines_and_flags_the_partial_one() {
+    let long = format!(
+        r#"{{"type":"user","timestamp":"2026-02-03T10:00:00Z","message":{{"content":"{}"}}}}"#,
+        "x".repeat(parser::MAX_LINE_BYTES)
+    );
+    let text = format!("{{\"a\":1}}\r\n{}\n{{\"b\"", long);
+    let mut lines = LineReader::new(text.as_bytes());
+
+    match lines.next_line().unwrap() {
+        Some((TranscriptLine::Text(line), 9, true)) => assert_eq!(line, r#"{"a":1}"#),
+        _ => panic!("expected a short line"),
+    }
+    match lines.next_line().unwrap() {
+        Some((TranscriptLine::Oversized(fields), len, true)) => {
+            assert_eq!(len, long.len() as u64 + 1);
+            assert_eq!(fields.get("timestamp"), Some("2026-02-03T10:00:00Z"));
+            assert_eq!(fields.get("message.content"), None);
+        }
+        _ => panic!("expected an oversized line"),
+    }
+    assert!(matches!(lines.next_line().unwrap(), Some((TranscriptLine::Text(r#"{"b""#), 4, false))));
+    assert!(lines.next_line().unwrap().is_none());
+}
+
+#[test]
+fn line_reader_replaces_invalid_utf8_and_keeps_reading() {
+    let mut text = br#"{"type":"user","message":{"content":"caf"#.to_vec();
+    text.extend_from_slice(b"\xe9\"}}\n{\"a\":1}\n");
+    let mut lines = LineReader::new(text.as_slice());
+
+    match lines.next_line().unwrap() {
+        Some((TranscriptLine::Text(line), _, true)) => {
+            assert!(line.ends_with("caf\u{FFFD}\"}}"));
+            assert!(serde_json::from_str::<serde_json::Value>(line).is_ok());
+        }
+        _ => panic!("expected the bad line, decoded"),
+    }
+    assert!(matches!(lines.next_line().unwrap(), Some((TranscriptLine::Text(r#"{"a":1}"#), 8, true))));
+}
@@ -0,0 +1,253 @@
+//! Where session transcripts come from. Each agent tool keeps its own logs in
+//! its own format; a `SessionSource` finds them and turns them into the
+//! parser's `Line`s so the rest of the pipeline doesn't care which tool wrote
+//! them.
+
+use anyhow::Result;
+use serde::Deserialize;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::parser;
+use crate::scanner;
+
+mod codex;
+mod gemini;
+
+pub use codex::Codex;
+pub use gemini::Gemini;
+
+pub trait SessionSource: Send + Sync {
+    /// Short name, e.g. "codex". Namespaces the source's rows in the store.
+    fn name(&self) -> &'static str;
+
+    /// Directory the transcripts live under.
+    fn root(&self) -> &Path;
+
+    /// Every transcript under `root`, in any order.
+    fn find_files(&self) -> Vec<PathBuf>;
+
+    /// Parse one line of a line-oriented transcript.
+    fn parse_line(&self, line: &str) -> Option<parser::Line>;
+
+    /// False for formats rewritten in place rather than appended to; those
+    /// are re-read whole with `parse_document` whenever they change.
+    fn append_only(&self) -> bool {
+        true
+    }
+
+    /// Make what we can of a line too long to parse whole. None skips it.
+    fn parse_oversized(&self, _fields: &parser::ScannedFields) -> Option<parser::Line> {
+        None
+    }
+
+    fn parse_document(&self, text: &str) -> Result<Vec<parser::Line>> {
+        Ok(text.lines().filter_map(|line| self.parse_line(line)).collect())
+    }
+
+    /// Line types the tool writes that carry nothing we track, so `doctor`
+    /// doesn't flag them.
+    fn ignored_types(&self) -> &'static [&'static str] {
+        &[]
+    }
+
+    /// Fields the tool always writes that `line` was parsed without, for
+    /// `doctor` to report as format drift.
+    fn missing_fields(&self, _line: &parser::Line) -> Vec<&'static str> {
+        Vec::new()
+    }
+
+    /// True for transcripts of subagents, whose tokens count against the
+    /// session that spawned them.
+    fn is_subagent_file(&self, _path: &Path) -> bool {
+        false
+    }
+
+    /// Key for a file's rows in the store, from its path relative to `root`.
+    fn source_path(&self, relative: &str) -> String {
+        format!("{}:{}", self.name(), relative)
+    }
+}
+
+/// The agent tools we know how to read, as named in config.toml.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Kind {
+    Claude,
+    Codex,
+    Gemini,
+}
+
+impl Kind {
+    /// As written in config.toml, and as the store prefixes the source's rows.
+    fn name(self) -> &'static str {
+        match self {
+            Kind::Claude => "claude",
+            Kind::Codex => "codex",
+            Kind::Gemini => "gemini",
+        }
+    }
+}
+
+pub fn all_kinds() -> Vec<Kind> {
+    vec![Kind::Claude, Kind::Codex, Kind::Gemini]
+}
+
+/// A Claude Code projects directory, as listed under `[[projects_dirs]]`.
+#[derive(Debug, Clone, PartialEq, Deserialize)]
+pub struct ProjectsDir {
+    pub path: PathBuf,
+    /// Prefixes this directory's rows in the store, so the same relative
+    /// path under two directories doesn't collide.
+    #[serde(default)]
+    pub label: Option<String>,
+}
+
+/// Labels must tell the directories apart: at most one may go without. A
+/// label becomes a `label:` prefix on stored paths, so it can't contain the
+/// separator or take a source's name.
+pub fn check_projects_dirs(dirs: &[ProjectsDir]) -> Result<()> {
+    let unlabelled = dirs.iter().filter(|d| d.label.is_none()).count();
+    anyhow::ensure!(unlabelled <= 1, "projects_dirs: all but one directory need a label");
+    let mut labels: Vec<_> = dirs.iter().filter_map(|d| d.label.as_deref()).collect();
+    for label in &labels {
+        anyhow::ensure!(!label.contains(':'), "projects_dirs: label {:?} can't contain ':'", label);
+        anyhow::ensure!(
+            all_kinds().iter().all(|kind| kind.name() != *label),
+            "projects_dirs: label {:?} is the name of a source",
+            label
+        );
+    }
+    labels.sort_unstable();
+    if let Some(pair) = labels.windows(2).find(|pair| pair[0] == pair[1]) {
+        anyhow::bail!("projects_dirs: label {:?} is used twice", pair[0]);
+    }
+    Ok(())
+}
+
+/// Build the enabled sources: Claude Code at each of `claude_dirs`, the
+/// others at their default locations under `home`.
+pub fn from_kinds(kinds: &[Kind], home: &Path, claude_dirs: &[ProjectsDir]) -> Vec<Box<dyn SessionSource>> {
+    let mut sources: Vec<Box<dyn SessionSource>> = Vec::new();
+    for kind in kinds {
+        match kind {
+            Kind::Claude => {
+                for dir in claude_dirs {
+                    sources.push(Box::new(Claude::new(&dir.path, dir.label.as_deref())));
+                }
+            }
+            Kind::Codex => sources.push(Box::new(Codex::new(
+                &std::env::var_os("CODEX_HOME")
+                    .map(PathBuf::from)
+                    .unwrap_or_else(|| home.join(".codex"))
+                    .join("sessions"),
+            ))),
+            Kind::Gemini => sources.push(Box::new(Gemini::new(&home.join(".gemini").join("tmp")))),
+        }
+    }
+    sources
+}
+
+/// Claude Code: `~/.claude/projects/<project>/<session>.jsonl`, plus
+/// subagent transcripts.
+pub struct Claude {
+    root: PathBuf,
+    label: Option<String>,
+}
+
+impl Claude {
+    pub fn new(projects_dir: &Path, label: Option<&str>) -> Self {
+        Self {
+            root: projects_dir.to_path_buf(),
+            label: label.map(|l| l.to_string()),
+        }
+    }
+}
+
+impl SessionSource for Claude {
+    fn name(&self) -> &'static str {
+        "claude"
+    }
+
+    fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn find_files(&self) -> Vec<PathBuf> {
+        let mut files = scanner::find_session_files(&self.root);
+        files.extend(scanner::find_subagent_files(&self.root));
+        files
+    }
+
+    fn parse_line(&self, line: &str) -> Option<parser::Line> {
+        parser::parse_line(line)
+    }
+
+    fn parse_oversized(&self, fields: &parser::ScannedFields) -> Option<parser::Line> {
+        parser::message_from_fields(fields).map(|message| parser::Line::Message(Box::new(message)))
+    }
+
+    fn ignored_types(&self) -> &'static [&'static str] {
+        &["system", "file-history-snapshot", "queue-operation"]
+    }
+
+    /// Every user and assistant line says where and in which session it
+    /// was written, and every response reports its usage.
+    fn missing_fields(&self, line: &parser::Line) -> Vec<&'static str> {
+        let parser::Line::Message(message) = line else {
+            return Vec::new();
+        };
+        let mut missing = Vec::new();
+        if message.cwd.is_none() {
+            missing.push("cwd");
+        }
+        if message.uuid.is_none() {
+            missing.push("uuid");
+        }
+        if message.session_id.is_none() {
+            missing.push("sessionId");
+        }
+        if message.role.as_deref() == Some("assistant") && message.usage.is_none() {
+            missing.push("usage token counts");
+        }
+        missing
+    }
+
+    fn is_subagent_file(&self, path: &Path) -> bool {
+        scanner::is_subagent_file(path)
+    }
+
+    /// Claude rows predate other sources, so an unlabelled directory keeps
+    /// bare relative paths.
+    fn source_path(&self, relative: &str) -> String {
+        match &self.label {
+            Some(label) => format!("{}:{}", label, relative),
+            None => relative.to_string(),
+        }
+    }
+}
+
+/// Files under `dir`, at any depth, whose name satisfies `keep`.
+fn find_files_recursive(dir: &Path, keep: &dyn Fn(&str) -> bool, results: &mut Vec<PathBuf>) {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let path = entry.path();
+        if path.is_dir() {
+            find_files_recursive(&path, keep, results);
+        } else if path.file_name().and_then(|n| n.to_str()).is_some_and(keep) {
+            results.push(path);
+        }
+    }
+}
+
+/// First non-blank line of a prompt, unless it's injected context (which
+/// these tools wrap in `<tags>`).
+fn prompt_line(text: &str) -> Option<String> {
+    let first_line = text.lines().map(str::trim).find(|l| !l.is_empty())?;
+    (!first_line.starts_with('<')).then(|| first_line.to_string())
+}
+
+#[cfg(test)]
+mod tests;
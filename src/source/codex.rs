@@ -0,0 +1,139 @@
+//! OpenAI Codex CLI: `~/.codex/sessions/YYYY/MM/DD/rollout-*.jsonl`.
+//!
+//! Every line is `{"timestamp", "type", "payload"}`. The working directory,
+//! branch and model are stated once up front (`session_meta`, then
+//! `turn_context` per turn) rather than on each line, and token counts come
+//! in separate `event_msg`s; the accumulator carries them forward.
+
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use super::{find_files_recursive, prompt_line, SessionSource};
+use crate::scanner;
+use crate::parser::{self, LineChanges, ParsedMessage, TokenUsage, ToolUse};
+
+pub struct Codex {
+    root: PathBuf,
+}
+
+impl Codex {
+    pub fn new(sessions_dir: &Path) -> Self {
+        Self { root: sessions_dir.to_path_buf() }
+    }
+}
+
+impl SessionSource for Codex {
+    fn name(&self) -> &'static str {
+        "codex"
+    }
+
+    fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn find_files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        find_files_recursive(
+            &self.root,
+            &|name| name.starts_with("rollout-") && scanner::is_jsonl(name),
+            &mut files,
+        );
+        files
+    }
+
+    fn parse_line(&self, line: &str) -> Option<parser::Line> {
+        parse_line(line).map(|message| parser::Line::Message(Box::new(message)))
+    }
+
+    /// Huge lines are tool output; they still show the session was active.
+    fn parse_oversized(&self, fields: &parser::ScannedFields) -> Option<parser::Line> {
+        let timestamp = fields.get("timestamp")?.parse().ok()?;
+        Some(parser::Line::Message(Box::new(ParsedMessage { timestamp, ..Default::default() })))
+    }
+}
+
+/// Every timestamped line counts as activity; some also carry context,
+/// usage or tool calls.
+fn parse_line(line: &str) -> Option<ParsedMessage> {
+    let value: Value = serde_json::from_str(line).ok()?;
+    let timestamp: DateTime<Utc> = value.get("timestamp")?.as_str()?.parse().ok()?;
+    let payload = value.get("payload")?;
+    let text = |v: &Value, key: &str| v.get(key).and_then(|s| s.as_str()).map(|s| s.to_string());
+    let mut message = ParsedMessage { timestamp, ..Default::default() };
+
+    match (value.get("type")?.as_str()?, text(payload, "type").as_deref()) {
+        ("session_meta", _) => {
+            message.cwd = text(payload, "cwd");
+            message.session_id = text(payload, "id");
+            message.git_branch = payload
+                .get("git")
+                .and_then(|git| text(git, "branch"))
+                .filter(|b| !b.is_empty());
+        }
+        ("turn_context", _) => {
+            message.cwd = text(payload, "cwd");
+            message.model = text(payload, "model");
+        }
+        ("event_msg", Some("user_message")) => {
+            message.role = Some("user".to_string());
+            message.prompt = text(payload, "message").as_deref().and_then(prompt_line);
+        }
+        ("event_msg", Some("token_count")) => {
+            // `info` is null until the first response of a session.
+            let Some(info) = payload.get("info").filter(|i| !i.is_null()) else {
+                return Some(message);
+            };
+            message.role = Some("assistant".to_string());
+            let last = info.get("last_token_usage")?;
+            let count = |key: &str| last.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+            // OpenAI counts cached tokens as part of the input.
+            let cached = count("cached_input_tokens");
+            message.usage = Some(TokenUsage {
+                input_tokens: count("input_tokens").saturating_sub(cached),
+                output_tokens: count("output_tokens"),
+                cache_creation_input_tokens: 0,
+                cache_read_input_tokens: cached,
+            });
+            // Codex repeats the last count on some events; the running
+            // total identifies a response, so a repeat replaces rather than
+            // adds.
+            message.message_id = info
+                .get("total_token_usage")
+                .and_then(|t| t.get("total_tokens"))
+                .and_then(|t| t.as_u64())
+                .map(|total| format!("codex-total-{}", total));
+        }
+        ("response_item", Some("function_call")) => {
+            message.role = Some("assistant".to_string());
+            message.tool_uses.push(ToolUse {
+                id: text(payload, "call_id")?,
+                name: text(payload, "name")?,
+                lines: LineChanges::default(),
+            });
+        }
+        ("response_item", Some("custom_tool_call")) => {
+            message.role = Some("assistant".to_string());
+            let name = text(payload, "name")?;
+            let lines = match name.as_str() {
+                "apply_patch" => LineChanges::from_patch(&text(payload, "input").unwrap_or_default()),
+                _ => LineChanges::default(),
+            };
+            message.tool_uses.push(ToolUse { id: text(payload, "call_id")?, name, lines });
+        }
+        ("response_item", Some("function_call_output" | "custom_tool_call_output")) => {
+            let is_error = text(payload, "output").is_some_and(|output| failed(&output));
+            message.tool_results.push((text(payload, "call_id")?, is_error));
+        }
+        _ => {}
+    }
+    Some(message)
+}
+
+/// Tool output is a JSON string whose metadata holds the exit code.
+fn failed(output: &str) -> bool {
+    serde_json::from_str::<Value>(output)
+        .ok()
+        .and_then(|v| v.get("metadata")?.get("exit_code")?.as_i64())
+        .is_some_and(|code| code != 0)
+}
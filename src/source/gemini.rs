@@ -0,0 +1,138 @@
+//! Gemini CLI: `~/.gemini/tmp/<project hash>/chats/session-*.json`.
+//!
+//! Each file is one JSON document, rewritten as the chat grows. Gemini only
+//! records a hash of the project directory, so sessions are filed under
+//! `gemini:<hash>`; map that like any other project path.
+
+use anyhow::{Context, Result};
+use chrono::{DateTime, Utc};
+use serde_json::Value;
+use std::path::{Path, PathBuf};
+
+use super::{find_files_recursive, prompt_line, SessionSource};
+use crate::parser::{self, LineChanges, ParsedMessage, TokenUsage, ToolUse};
+
+pub struct Gemini {
+    root: PathBuf,
+}
+
+impl Gemini {
+    pub fn new(tmp_dir: &Path) -> Self {
+        Self { root: tmp_dir.to_path_buf() }
+    }
+}
+
+impl SessionSource for Gemini {
+    fn name(&self) -> &'static str {
+        "gemini"
+    }
+
+    fn root(&self) -> &Path {
+        &self.root
+    }
+
+    fn find_files(&self) -> Vec<PathBuf> {
+        let mut files = Vec::new();
+        find_files_recursive(
+            &self.root,
+            &|name| name.starts_with("session-") && name.ends_with(".json"),
+            &mut files,
+        );
+        files
+    }
+
+    fn parse_line(&self, _line: &str) -> Option<parser::Line> {
+        None
+    }
+
+    fn append_only(&self) -> bool {
+        false
+    }
+
+    fn parse_document(&self, text: &str) -> Result<Vec<parser::Line>> {
+        let chat: Value = serde_json::from_str(text).context("parsing chat JSON")?;
+        let project = chat
+            .get("projectHash")
+            .and_then(|h| h.as_str())
+            .map(|hash| format!("gemini:{}", hash));
+        let session_id = chat.get("sessionId").and_then(|s| s.as_str()).map(|s| s.to_string());
+        let messages = chat
+            .get("messages")
+            .and_then(|m| m.as_array())
+            .context("chat has no messages array")?;
+        Ok(messages
+            .iter()
+            .filter_map(parse_message)
+            .map(|mut message| {
+                message.cwd = project.clone();
+                message.session_id = session_id.clone();
+                parser::Line::Message(Box::new(message))
+            })
+            .collect())
+    }
+}
+
+fn parse_message(value: &Value) -> Option<ParsedMessage> {
+    let timestamp: DateTime<Utc> = value.get("timestamp")?.as_str()?.parse().ok()?;
+    let text = |v: &Value, key: &str| v.get(key).and_then(|s| s.as_str()).map(|s| s.to_string());
+    let mut message = ParsedMessage {
+        timestamp,
+        message_id: text(value, "id"),
+        uuid: text(value, "id"),
+        model: text(value, "model"),
+        ..Default::default()
+    };
+
+    match value.get("type")?.as_str()? {
+        "user" => {
+            message.role = Some("user".to_string());
+            let content = value.get("content")?;
+            let prompt = match content.as_str() {
+                Some(text) => Some(text),
+                None => content
+                    .as_array()?
+                    .iter()
+                    .find_map(|part| part.get("text").and_then(|t| t.as_str())),
+            };
+            message.prompt = prompt.and_then(prompt_line);
+        }
+        "gemini" => {
+            message.role = Some("assistant".to_string());
+            if let Some(tokens) = value.get("tokens") {
+                let count = |key: &str| tokens.get(key).and_then(|v| v.as_u64()).unwrap_or(0);
+                // Cached tokens are part of the prompt count; thinking is
+                // billed as output.
+                let cached = count("cached");
+                message.usage = Some(TokenUsage {
+                    input_tokens: count("input").saturating_sub(cached),
+                    output_tokens: count("output") + count("thoughts"),
+                    cache_creation_input_tokens: 0,
+                    cache_read_input_tokens: cached,
+                });
+            }
+            // Calls are recorded with their outcome on the same message.
+            for call in value.get("toolCalls").and_then(|c| c.as_array()).into_iter().flatten() {
+                let (Some(id), Some(name)) = (text(call, "id"), text(call, "name")) else {
+                    continue;
+                };
+                let lines = call
+                    .get("args")
+                    .map(|args| LineChanges::from_tool_input(edit_tool(&name), args))
+                    .unwrap_or_default();
+                message.tool_results.push((id.clone(), call.get("status").and_then(|s| s.as_str()) == Some("error")));
+                message.tool_uses.push(ToolUse { id, name, lines });
+            }
+        }
+        _ => {}
+    }
+    Some(message)
+}
+
+/// The Claude Code tool whose input has the same shape, for line counting.
+fn edit_tool(name: &str) -> &str {
+    match name {
+        "replace" => "Edit",
+        "write_file" => "Write",
+        _ => name,
+    }
+}
@@ -0,0 +1,174 @@
+use super::*;
+use crate::parser::{Line, ParsedMessage};
+use std::path::Path;
+
+fn message(line: Option<Line>) -> ParsedMessage {
+    match line {
+        Some(Line::Message(message)) => *message,
+        _ => panic!("expected a message"),
+    }
+}
+
+fn codex(line: &str) -> ParsedMessage {
+    message(Codex::new(Path::new("/codex")).parse_line(line))
+}
+
+#[test]
+fn claude_keeps_bare_source_paths() {
+    let claude = Claude::new(Path::new("/p"), None);
+    let codex = Codex::new(Path::new("/c"));
+
+    assert_eq!(claude.source_path("-work-api/s1.jsonl"), "-work-api/s1.jsonl");
+    assert_eq!(codex.source_path("2025/10/01/rollout-1.jsonl"), "codex:2025/10/01/rollout-1.jsonl");
+}
+
+#[test]
+fn labelled_claude_dir_prefixes_source_paths() {
+    let claude = Claude::new(Path::new("/mnt/work/projects"), Some("work"));
+
+    assert_eq!(claude.source_path("-work-api/s1.jsonl"), "work:-work-api/s1.jsonl");
+}
+
+fn projects_dir(path: &str, label: Option<&str>) -> ProjectsDir {
+    ProjectsDir { path: path.into(), label: label.map(|l| l.to_string()) }
+}
+
+#[test]
+fn projects_dirs_need_distinct_labels() {
+    let home = projects_dir("/home/me/.claude/projects", None);
+    let work = projects_dir("/mnt/work/projects", Some("work"));
+
+    assert!(check_projects_dirs(&[home.clone(), work.clone()]).is_ok());
+    assert!(check_projects_dirs(&[home.clone(), projects_dir("/other", None)]).is_err());
+    assert!(check_projects_dirs(&[work.clone(), work]).is_err());
+}
+
+#[test]
+fn labels_cannot_pose_as_another_source_or_hold_the_separator() {
+    let home = projects_dir("/home/me/.claude/projects", None);
+
+    assert!(check_projects_dirs(&[home.clone(), projects_dir("/w", Some("codex"))]).is_err());
+    assert!(check_projects_dirs(&[home.clone(), projects_dir("/w", Some("gemini"))]).is_err());
+    assert!(check_projects_dirs(&[home.clone(), projects_dir("/w", Some("claude"))]).is_err());
+    assert!(check_projects_dirs(&[home, projects_dir("/w", Some("work:2"))]).is_err());
+}
+
+#[test]
+fn each_projects_dir_is_its_own_source() {
+    let dirs = [projects_dir("/a", None), projects_dir("/b", Some("b"))];
+
+    let sources = from_kinds(&[Kind::Claude], Path::new("/home/me"), &dirs);
+
+    let roots: Vec<_> = sources.iter().map(|s| s.root()).collect();
+    assert_eq!(roots, vec![Path::new("/a"), Path::new("/b")]);
+}
+
+#[test]
+fn codex_finds_rollouts_in_date_folders() {
+    let dir = tempfile::tempdir().unwrap();
+    let day = dir.path().join("2025/10/01");
+    std::fs::create_dir_all(&day).unwrap();
+    std::fs::File::create(day.join("rollout-2025-10-01T10-00-00-abc.jsonl")).unwrap();
+    std::fs::File::create(day.join("notes.jsonl")).unwrap();
+
+    let files = Codex::new(dir.path()).find_files();
+
+    assert_eq!(files, vec![day.join("rollout-2025-10-01T10-00-00-abc.jsonl")]);
+}
+
+#[test]
+fn codex_session_meta_sets_context() {
+    let m = codex(
+        r#"{"timestamp":"2025-10-01T10:00:00.123Z","type":"session_meta","payload":{"id":"c1","cwd":"/work/api","git":{"branch":"main"}}}"#,
+    );
+
+    assert_eq!(m.cwd.as_deref(), Some("/work/api"));
+    assert_eq!(m.session_id.as_deref(), Some("c1"));
+    assert_eq!(m.git_branch.as_deref(), Some("main"));
+}
+
+#[test]
+fn codex_token_count_splits_cached_input() {
+    let m = codex(
+        r#"{"timestamp":"2025-10-01T10:00:00Z","type":"event_msg","payload":{"type":"token_count","info":{"total_token_usage":{"total_tokens":900},"last_token_usage":{"input_tokens":100,"cached_input_tokens":40,"output_tokens":50,"reasoning_output_tokens":20}}}}"#,
+    );
+
+    let usage = m.usage.unwrap();
+    assert_eq!(usage.input_tokens, 60);
+    assert_eq!(usage.cache_read_input_tokens, 40);
+    assert_eq!(usage.output_tokens, 50);
+    assert_eq!(m.message_id.as_deref(), Some("codex-total-900"));
+}
+
+#[test]
+fn codex_token_count_without_info_is_plain_activity() {
+    let m = codex(r#"{"timestamp":"2025-10-01T10:00:00Z","type":"event_msg","payload":{"type":"token_count","info":null}}"#);
+
+    assert!(m.usage.is_none());
+}
+
+#[test]
+fn codex_tool_calls_and_failed_outputs() {
+    let call = codex(
+        r#"{"timestamp":"2025-10-01T10:00:00Z","type":"response_item","payload":{"type":"function_call","name":"shell","arguments":"{}","call_id":"call_1"}}"#,
+    );
+    let patch = codex(
+        r#"{"timestamp":"2025-10-01T10:00:00Z","type":"response_item","payload":{"type":"custom_tool_call","name":"apply_patch","call_id":"call_2","input":"*** Begin Patch\n*** Update File: a.rs\n@@\n-old\n+new\n+more\n*** End Patch"}}"#,
+    );
+    let output = codex(
+        r#"{"timestamp":"2025-10-01T10:00:01Z","type":"response_item","payload":{"type":"function_call_output","call_id":"call_1","output":"{\"output\":\"boom\",\"metadata\":{\"exit_code\":1}}"}}"#,
+    );
+
+    assert_eq!(call.tool_uses[0].name, "shell");
+    assert_eq!(patch.tool_uses[0].lines, parser::LineChanges { added: 2, removed: 1 });
+    assert_eq!(output.tool_results, vec![("call_1".to_string(), true)]);
+}
+
+#[test]
+fn codex_user_message_is_the_prompt() {
+    let m = codex(
+        r#"{"timestamp":"2025-10-01T10:00:00Z","type":"event_msg","payload":{"type":"user_message","message":"Add retries to the client\nthanks"}}"#,
+    );
+    let injected = codex(
+        r#"{"timestamp":"2025-10-01T10:00:00Z","type":"event_msg","payload":{"type":"user_message","message":"<environment_context>…"}}"#,
+    );
+
+    assert_eq!(m.prompt.as_deref(), Some("Add retries to the client"));
+    assert_eq!(injected.prompt, None);
+}
+
+#[test]
+fn gemini_chat_becomes_messages_under_project_hash() {
+    let chat = r#"{
+        "sessionId": "g1",
+        "projectHash": "abc123",
+        "messages": [
+            {"id": "m1", "timestamp": "2025-10-01T10:00:00Z", "type": "user", "content": "Fix the flaky test"},
+            {"id": "m2", "timestamp": "2025-10-01T10:01:00Z", "type": "gemini", "model": "gemini-2.5-pro",
+             "tokens": {"input": 100, "output": 10, "cached": 30, "thoughts": 5},
+             "toolCalls": [
+                {"id": "t1", "name": "replace", "status": "success", "args": {"old_string": "a", "new_string": "b\nc"}},
+                {"id": "t2", "name": "run_shell_command", "status": "error", "args": {}}
+             ]}
+        ]
+    }"#;
+
+    let lines = Gemini::new(Path::new("/g")).parse_document(chat).unwrap();
+    let messages: Vec<_> = lines.into_iter().map(|l| message(Some(l))).collect();
+
+    assert_eq!(messages.len(), 2);
+    assert_eq!(messages[0].cwd.as_deref(), Some("gemini:abc123"));
+    assert_eq!(messages[0].prompt.as_deref(), Some("Fix the flaky test"));
+    let usage = messages[1].usage.as_ref().unwrap();
+    assert_eq!((usage.input_tokens, usage.output_tokens, usage.cache_read_input_tokens), (70, 15, 30));
+    assert_eq!(messages[1].tool_uses[0].lines, parser::LineChanges { added: 2, removed: 1 });
+    assert_eq!(
+        messages[1].tool_results,
+        vec![("t1".to_string(), false), ("t2".to_string(), true)]
+    );
+}
+
+#[test]
+fn gemini_half_written_chat_is_an_error() {
+    assert!(Gemini::new(Path::new("/g")).parse_document(r#"{"sessionId":"g1","messa"#).is_err());
+}
@@ -1,48 +1,137 @@
 use anyhow::{Context, Result};
-use chrono::{DateTime, Local, NaiveDate, TimeDelta, Utc};
+use chrono::{DateTime, Local, NaiveDate, NaiveDateTime, TimeDelta, Timelike, Utc};
 use rusqlite::{Connection, OptionalExtension};
+use std::collections::{BTreeMap, HashMap};
 use std::path::Path;
 
 use crate::parser;
 
+mod migrations;
+
 pub struct Store {
     conn: Connection,
 }
 
+/// What we last saw of a session file on disk, and how far we've parsed it.
+pub struct FileState {
+    pub size: u64,
+    pub mtime_ms: i64,
+    pub offset: u64,
+    pub accumulator: parser::SessionAccumulator,
+}
+
+/// The latest archived copy of a session file.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ArchivedFile {
+    pub source_path: String,
+    pub sha256: String,
+    /// Size and mtime of the file when it was copied.
+    pub size: u64,
+    pub mtime_ms: i64,
+}
+
+/// Time logged by hand, for work that left no transcript or to correct
+/// tracked time.
+#[derive(Debug, Clone, PartialEq)]
+pub struct ManualEntry {
+    pub id: i64,
+    pub project: String,
+    pub start: DateTime<Utc>,
+    pub end: DateTime<Utc>,
+    /// `end - start` for added time, clipped to the queried window. An
+    /// adjustment starts and ends at once and may be negative.
+    pub duration: TimeDelta,
+    pub note: Option<String>,
+}
+
+/// Width of a `message_rollups` bucket, in local time.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Bucket {
+    Hour,
+    Day,
+}
+
+impl Bucket {
+    /// Start of the bucket holding `time`.
+    fn start(self, time: NaiveDateTime) -> NaiveDateTime {
+        let hour = match self {
+            Bucket::Hour => time.hour(),
+            Bucket::Day => 0,
+        };
+        time.date().and_hms_opt(hour, 0, 0).expect("valid hour")
+    }
+}
+
+/// Messages folded in one bucket.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Rollup {
+    /// Local time the bucket starts.
+    pub start: NaiveDateTime,
+    pub messages: usize,
+    /// Of `messages`, the ones the user sent (prompts and tool results).
+    pub user_messages: usize,
+    pub tokens: parser::TokenUsage,
+}
+
+/// What `doctor` reports about the database.
+pub struct Health {
+    /// `PRAGMA integrity_check` output; "ok" when the file is sound.
+    pub integrity: String,
+    /// `PRAGMA user_version`: how many migrations have run.
+    pub schema_version: usize,
+    pub sessions: usize,
+    pub source_files: usize,
+}
+
 impl Store {
     pub fn new(path: &Path) -> Result<Store> {
-        let conn = Connection::open(path)?;
-        conn.execute_batch(
-            "PRAGMA journal_mode=WAL;
-             CREATE TABLE IF NOT EXISTS sessions (
-                 source_path                  TEXT    PRIMARY KEY,
-                 project                      TEXT    NOT NULL,
-                 date                         TEXT    NOT NULL,
-                 start_time                   TEXT    NOT NULL,
-                 end_time                     TEXT    NOT NULL,
-                 duration_seconds             INTEGER NOT NULL,
-                 input_tokens                 INTEGER NOT NULL DEFAULT 0,
-                 output_tokens                INTEGER NOT NULL DEFAULT 0,
-                 cache_creation_input_tokens  INTEGER NOT NULL DEFAULT 0,
-                 cache_read_input_tokens      INTEGER NOT NULL DEFAULT 0
-             );
-             CREATE TABLE IF NOT EXISTS synced_days (
-                 date         TEXT NOT NULL,
-                 workspace_id TEXT NOT NULL,
-                 PRIMARY KEY (date, workspace_id)
-             );
-             CREATE TABLE IF NOT EXISTS synced_entries (
-                 date              TEXT NOT NULL,
-                 workspace_id      TEXT NOT NULL,
-                 project_id        TEXT NOT NULL,
-                 clockify_entry_id TEXT NOT NULL,
-                 PRIMARY KEY (date, workspace_id, project_id)
-             );",
-        )
-        .context("initializing database")?;
+        let mut conn = Connection::open(path)?;
+        // The TUI's background scan writes through its own connection.
+        conn.busy_timeout(std::time::Duration::from_secs(5))?;
+        conn.execute_batch("PRAGMA journal_mode=WAL;")
+            .context("initializing database")?;
+        migrations::migrate(&mut conn, path).context("upgrading database")?;
         Ok(Store { conn })
     }
 
+    /// Run `f` inside a single transaction, committing only if it succeeds.
+    pub fn in_transaction<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        let tx = self.conn.unchecked_transaction().context("starting transaction")?;
+        let value = f()?;
+        tx.commit().context("committing transaction")?;
+        Ok(value)
+    }
+
+    /// Run `f` so that none of its writes are kept if it fails, even inside
+    /// an enclosing transaction.
+    pub fn in_savepoint<T>(&self, f: impl FnOnce() -> Result<T>) -> Result<T> {
+        self.conn.execute_batch("SAVEPOINT scoped").context("starting savepoint")?;
+        match f() {
+            Ok(value) => {
+                self.conn.execute_batch("RELEASE scoped").context("releasing savepoint")?;
+                Ok(value)
+            }
+            Err(e) => {
+                self.conn
+                    .execute_batch("ROLLBACK TO scoped; RELEASE scoped")
+                    .context("rolling back savepoint")?;
+                Err(e)
+            }
+        }
+    }
+
+    /// Replace every segment row for a file with `sessions`.
+    /// An empty list removes the file's rows (e.g. once dedup leaves it with
+    /// no messages of its own).
+    pub fn replace_sessions(&self, source_path: &str, sessions: &[parser::Session]) -> Result<()> {
+        self.remove(source_path)?;
+        for session in sessions {
+            self.upsert(source_path, session)?;
+        }
+        Ok(())
+    }
+
+    /// Insert or overwrite one (file, project) segment row.
     pub fn upsert(&self, source_path: &str, session: &parser::Session) -> Result<()> {
         let date = session
             .start
@@ -57,8 +146,9 @@ impl Store {
                 "INSERT OR REPLACE INTO sessions (
                      source_path, project, date, start_time, end_time,
                      duration_seconds, input_tokens, output_tokens,
-                     cache_creation_input_tokens, cache_read_input_tokens
-                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10)",
+                     cache_creation_input_tokens, cache_read_input_tokens, parent_path,
+                     lines_added, lines_removed, title
+                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13, ?14)",
                 rusqlite::params![
                     source_path,
                     session.project,
@@ -70,20 +160,267 @@ impl Store {
                     session.output_tokens as i64,
                     session.cache_creation_input_tokens as i64,
                     session.cache_read_input_tokens as i64,
+                    session.parent,
+                    session.lines.added as i64,
+                    session.lines.removed as i64,
+                    session.title,
                 ],
             )
             .context("upserting session")?;
+
+        self.conn
+            .execute(
+                "DELETE FROM session_models WHERE source_path = ?1 AND project = ?2",
+                [source_path, &session.project],
+            )
+            .context("clearing session models")?;
+        let mut stmt = self.conn.prepare_cached(
+            "INSERT INTO session_models (
+                 source_path, project, model, input_tokens, output_tokens,
+                 cache_creation_input_tokens, cache_read_input_tokens
+             ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7)",
+        )?;
+        for (model, usage) in &session.models {
+            stmt.execute(rusqlite::params![
+                source_path,
+                session.project,
+                model,
+                usage.input_tokens as i64,
+                usage.output_tokens as i64,
+                usage.cache_creation_input_tokens as i64,
+                usage.cache_read_input_tokens as i64,
+            ])
+            .context("upserting session models")?;
+        }
+
+        self.conn
+            .execute(
+                "DELETE FROM session_intervals WHERE source_path = ?1 AND project = ?2",
+                [source_path, &session.project],
+            )
+            .context("clearing session intervals")?;
+        let mut stmt = self.conn.prepare_cached(
+            "INSERT INTO session_intervals (source_path, project, start_ms, end_ms, branch)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for interval in &session.intervals {
+            stmt.execute(rusqlite::params![
+                source_path,
+                session.project,
+                interval.start.timestamp_millis(),
+                interval.end.timestamp_millis(),
+                interval.branch,
+            ])
+            .context("upserting session intervals")?;
+        }
+
+        self.conn
+            .execute(
+                "DELETE FROM session_tools WHERE source_path = ?1 AND project = ?2",
+                [source_path, &session.project],
+            )
+            .context("clearing session tools")?;
+        let mut stmt = self.conn.prepare_cached(
+            "INSERT INTO session_tools (source_path, project, tool, calls, errors)
+             VALUES (?1, ?2, ?3, ?4, ?5)",
+        )?;
+        for (tool, stats) in &session.tools {
+            stmt.execute(rusqlite::params![
+                source_path,
+                session.project,
+                tool,
+                stats.calls as i64,
+                stats.errors as i64,
+            ])
+            .context("upserting session tools")?;
+        }
+        Ok(())
+    }
+
+    /// Drop all of a file's segment rows.
+    pub fn remove(&self, source_path: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM sessions WHERE source_path = ?1", [source_path])
+            .context("removing session")?;
+        self.conn
+            .execute("DELETE FROM session_models WHERE source_path = ?1", [source_path])
+            .context("removing session models")?;
+        self.conn
+            .execute("DELETE FROM session_intervals WHERE source_path = ?1", [source_path])
+            .context("removing session intervals")?;
+        self.conn
+            .execute("DELETE FROM session_tools WHERE source_path = ?1", [source_path])
+            .context("removing session tools")?;
+        Ok(())
+    }
+
+    /// Drop every parsed session along with the parser state and message
+    /// claims behind them, ahead of a full re-parse. Sync history is kept.
+    pub fn clear_sessions(&self) -> Result<()> {
+        self.conn
+            .execute_batch(
+                "DELETE FROM sessions;
+                 DELETE FROM session_models;
+                 DELETE FROM session_intervals;
+                 DELETE FROM session_tools;
+                 DELETE FROM source_files;
+                 DELETE FROM message_claims;
+                 DELETE FROM messages;",
+            )
+            .context("clearing sessions")
+    }
+
+    /// Record a folded message. If its usage replaced the previous line's
+    /// (two lines of one streamed response), that line's tokens are zeroed
+    /// so rollups count the response once, as the session totals do.
+    pub fn record_message(
+        &self,
+        source_path: &str,
+        message: &parser::ParsedMessage,
+        attribution: &parser::Attribution,
+    ) -> Result<()> {
+        if attribution.replaces_previous {
+            self.conn
+                .execute(
+                    "UPDATE messages SET input_tokens = 0, output_tokens = 0,
+                         cache_creation_input_tokens = 0, cache_read_input_tokens = 0
+                     WHERE source_path = ?1 AND response_id = ?2",
+                    rusqlite::params![source_path, message.message_id],
+                )
+                .context("superseding message usage")?;
+        }
+        let usage = message.usage.clone().unwrap_or_default();
+        self.conn
+            .execute(
+                "INSERT INTO messages (
+                     source_path, uuid, session_id, response_id, timestamp_ms, role, model, project, branch,
+                     input_tokens, output_tokens, cache_creation_input_tokens, cache_read_input_tokens
+                 ) VALUES (?1, ?2, ?3, ?4, ?5, ?6, ?7, ?8, ?9, ?10, ?11, ?12, ?13)",
+                rusqlite::params![
+                    source_path,
+                    message.uuid,
+                    attribution.session_id,
+                    message.message_id,
+                    message.timestamp.timestamp_millis(),
+                    message.role,
+                    // Its own model, else the one its usage was charged to.
+                    message.model.as_deref().or(message.usage.as_ref().and(attribution.model.as_deref())),
+                    attribution.project,
+                    attribution.branch,
+                    usage.input_tokens as i64,
+                    usage.output_tokens as i64,
+                    usage.cache_creation_input_tokens as i64,
+                    usage.cache_read_input_tokens as i64,
+                ],
+            )
+            .context("recording message")?;
         Ok(())
     }
 
+    /// Forget a file's messages before it's parsed again from the start.
+    pub fn remove_messages(&self, source_path: &str) -> Result<()> {
+        self.conn
+            .execute("DELETE FROM messages WHERE source_path = ?1", [source_path])
+            .context("removing messages")?;
+        Ok(())
+    }
+
+    /// Files holding messages from session `session_id`, earliest first.
+    pub fn session_files(&self, session_id: &str) -> Result<Vec<String>> {
+        self.conn
+            .prepare_cached(
+                "SELECT source_path FROM messages WHERE session_id = ?1
+                 GROUP BY source_path ORDER BY MIN(timestamp_ms), source_path",
+            )?
+            .query_map([session_id], |row| row.get(0))?
+            .collect::<rusqlite::Result<_>>()
+            .context("querying session files")
+    }
+
+    /// Subagent files stored before their parent session was, with the
+    /// session id they name.
+    pub fn unlinked_subagents(&self) -> Result<Vec<(String, String)>> {
+        self.conn
+            .prepare(
+                "SELECT DISTINCT s.source_path, m.session_id
+                 FROM sessions s JOIN messages m USING (source_path)
+                 WHERE s.parent_path = '' AND m.session_id IS NOT NULL
+                 ORDER BY s.source_path",
+            )?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()
+            .context("querying unlinked subagents")
+    }
+
+    pub fn set_parent(&self, source_path: &str, parent: &str) -> Result<()> {
+        self.conn
+            .execute("UPDATE sessions SET parent_path = ?2 WHERE source_path = ?1", [source_path, parent])
+            .context("linking subagent")?;
+        Ok(())
+    }
+
+    /// Messages and tokens in `[start, end)` per local hour or day, oldest
+    /// first, optionally for one project. Buckets with no messages are
+    /// left out.
+    pub fn message_rollups(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        bucket: Bucket,
+        project: Option<&str>,
+    ) -> Result<Vec<Rollup>> {
+        let mut stmt = self.conn.prepare(
+            "SELECT timestamp_ms, role, input_tokens, output_tokens,
+                    cache_creation_input_tokens, cache_read_input_tokens
+             FROM messages
+             WHERE timestamp_ms >= ?1 AND timestamp_ms < ?2 AND (?3 IS NULL OR project = ?3)",
+        )?;
+        let rows = stmt.query_map(
+            rusqlite::params![start.timestamp_millis(), end.timestamp_millis(), project],
+            |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, Option<String>>(1)?,
+                    parser::TokenUsage {
+                        input_tokens: row.get::<_, i64>(2)? as u64,
+                        output_tokens: row.get::<_, i64>(3)? as u64,
+                        cache_creation_input_tokens: row.get::<_, i64>(4)? as u64,
+                        cache_read_input_tokens: row.get::<_, i64>(5)? as u64,
+                    },
+                ))
+            },
+        )?;
+
+        let mut rollups: BTreeMap<NaiveDateTime, Rollup> = BTreeMap::new();
+        for row in rows {
+            let (timestamp_ms, role, usage) = row?;
+            let Some(timestamp) = DateTime::from_timestamp_millis(timestamp_ms) else {
+                continue;
+            };
+            let start = bucket.start(timestamp.with_timezone(&Local).naive_local());
+            let rollup = rollups.entry(start).or_insert_with(|| Rollup { start, ..Default::default() });
+            rollup.messages += 1;
+            if role.as_deref() == Some("user") {
+                rollup.user_messages += 1;
+            }
+            rollup.tokens.add(&usage);
+        }
+        Ok(rollups.into_values().collect())
+    }
+
+    /// Sessions overlapping `[start, end)`. Durations and intervals are
+    /// clipped to the window, so a session spanning midnight only counts the
+    /// active time on each side toward that day. Rows stored without
+    /// intervals keep their full duration.
     pub fn query_range(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<parser::Session>> {
         let start_str = start.format("%Y-%m-%dT%H:%M:%SZ").to_string();
         let end_str = end.format("%Y-%m-%dT%H:%M:%SZ").to_string();
 
         let mut stmt = self.conn.prepare(
-            "SELECT project, start_time, end_time, duration_seconds,
+            "SELECT source_path, project, start_time, end_time, duration_seconds,
                     input_tokens, output_tokens,
-                    cache_creation_input_tokens, cache_read_input_tokens
+                    cache_creation_input_tokens, cache_read_input_tokens, parent_path,
+                    lines_added, lines_removed, title
              FROM sessions
              WHERE start_time < ?1 AND end_time >= ?2",
         ).context("preparing query_range")?;
@@ -93,47 +430,339 @@ impl Store {
                 row.get::<_, String>(0)?,
                 row.get::<_, String>(1)?,
                 row.get::<_, String>(2)?,
-                row.get::<_, i64>(3)?,
+                row.get::<_, String>(3)?,
                 row.get::<_, i64>(4)?,
                 row.get::<_, i64>(5)?,
                 row.get::<_, i64>(6)?,
                 row.get::<_, i64>(7)?,
+                row.get::<_, i64>(8)?,
+                row.get::<_, Option<String>>(9)?,
+                row.get::<_, i64>(10)?,
+                row.get::<_, i64>(11)?,
+                row.get::<_, Option<String>>(12)?,
             ))
         }).context("querying sessions")?;
 
         let mut sessions = Vec::new();
         for row in rows {
-            let (project, start_time, end_time, duration_secs, input, output, cache_create, cache_read) = row?;
+            let (source_path, project, start_time, end_time, duration_secs, input, output, cache_create, cache_read, parent, lines_added, lines_removed, title) = row?;
+            let stored = self.session_intervals(&source_path, &project)?;
+            let intervals: Vec<_> = stored.iter().filter_map(|i| i.clip(start, end)).collect();
+            let duration = if stored.is_empty() {
+                TimeDelta::seconds(duration_secs)
+            } else {
+                intervals.iter().map(|i| i.duration()).sum()
+            };
             sessions.push(parser::Session {
                 start: start_time.parse().context("parsing start_time")?,
                 end: end_time.parse().context("parsing end_time")?,
-                duration: TimeDelta::seconds(duration_secs),
-                project,
+                duration,
+                project: project.clone(),
                 input_tokens: input as u64,
                 output_tokens: output as u64,
                 cache_creation_input_tokens: cache_create as u64,
                 cache_read_input_tokens: cache_read as u64,
+                models: self.session_models(&source_path, &project)?,
+                intervals,
+                parent,
+                tools: self.session_tools(&source_path, &project)?,
+                lines: parser::LineChanges {
+                    added: lines_added as u64,
+                    removed: lines_removed as u64,
+                },
+                title,
             });
         }
 
         Ok(sessions)
     }
 
+    fn session_models(&self, source_path: &str, project: &str) -> Result<BTreeMap<String, parser::TokenUsage>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT model, input_tokens, output_tokens,
+                    cache_creation_input_tokens, cache_read_input_tokens
+             FROM session_models
+             WHERE source_path = ?1 AND project = ?2",
+        )?;
+        let rows = stmt.query_map([source_path, project], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                parser::TokenUsage {
+                    input_tokens: row.get::<_, i64>(1)? as u64,
+                    output_tokens: row.get::<_, i64>(2)? as u64,
+                    cache_creation_input_tokens: row.get::<_, i64>(3)? as u64,
+                    cache_read_input_tokens: row.get::<_, i64>(4)? as u64,
+                },
+            ))
+        }).context("querying session models")?;
+        rows.collect::<rusqlite::Result<_>>().context("reading session models")
+    }
+
+    fn session_tools(&self, source_path: &str, project: &str) -> Result<BTreeMap<String, parser::ToolStats>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT tool, calls, errors FROM session_tools
+             WHERE source_path = ?1 AND project = ?2",
+        )?;
+        let rows = stmt.query_map([source_path, project], |row| {
+            Ok((
+                row.get::<_, String>(0)?,
+                parser::ToolStats {
+                    calls: row.get::<_, i64>(1)? as u64,
+                    errors: row.get::<_, i64>(2)? as u64,
+                },
+            ))
+        }).context("querying session tools")?;
+        rows.collect::<rusqlite::Result<_>>().context("reading session tools")
+    }
+
+    fn session_intervals(&self, source_path: &str, project: &str) -> Result<Vec<parser::Interval>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT start_ms, end_ms, branch FROM session_intervals
+             WHERE source_path = ?1 AND project = ?2
+             ORDER BY start_ms",
+        )?;
+        let rows = stmt.query_map([source_path, project], |row| {
+            Ok((row.get::<_, i64>(0)?, row.get::<_, i64>(1)?, row.get::<_, Option<String>>(2)?))
+        }).context("querying session intervals")?;
+        let mut intervals = Vec::new();
+        for row in rows {
+            let (start_ms, end_ms, branch) = row?;
+            intervals.push(parser::Interval {
+                start: DateTime::from_timestamp_millis(start_ms).context("invalid interval start")?,
+                end: DateTime::from_timestamp_millis(end_ms).context("invalid interval end")?,
+                branch,
+            });
+        }
+        Ok(intervals)
+    }
+
+    // --- manual time -------------------------------------------------------
+
+    /// Log `[start, end)` against `project`, or with `start == end`, adjust
+    /// its time by `duration`. Returns the entry's id.
+    pub fn add_manual_entry(
+        &self,
+        project: &str,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        duration: TimeDelta,
+        note: Option<&str>,
+    ) -> Result<i64> {
+        self.conn
+            .execute(
+                "INSERT INTO manual_entries (project, start_ms, end_ms, duration_seconds, note)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![
+                    project,
+                    start.timestamp_millis(),
+                    end.timestamp_millis(),
+                    duration.num_seconds(),
+                    note,
+                ],
+            )
+            .context("adding manual entry")?;
+        Ok(self.conn.last_insert_rowid())
+    }
+
+    /// Manual entries overlapping `[start, end)`, oldest first. Added time
+    /// is clipped to the window like session time.
+    pub fn manual_entries(&self, start: DateTime<Utc>, end: DateTime<Utc>) -> Result<Vec<ManualEntry>> {
+        let mut stmt = self.conn.prepare_cached(
+            "SELECT id, project, start_ms, end_ms, duration_seconds, note FROM manual_entries
+             WHERE start_ms < ?1 AND end_ms >= ?2
+             ORDER BY start_ms, id",
+        )?;
+        let rows = stmt
+            .query_map([end.timestamp_millis(), start.timestamp_millis()], |row| {
+                Ok((
+                    row.get::<_, i64>(0)?,
+                    row.get::<_, String>(1)?,
+                    row.get::<_, i64>(2)?,
+                    row.get::<_, i64>(3)?,
+                    row.get::<_, i64>(4)?,
+                    row.get::<_, Option<String>>(5)?,
+                ))
+            })
+            .context("querying manual entries")?;
+
+        let time = |ms: i64| DateTime::from_timestamp_millis(ms).context("manual entry time out of range");
+        let mut entries = Vec::new();
+        for row in rows {
+            let (id, project, start_ms, end_ms, duration_secs, note) = row?;
+            let (entry_start, entry_end) = (time(start_ms)?, time(end_ms)?);
+            let duration = if entry_end > entry_start {
+                entry_end.min(end) - entry_start.max(start)
+            } else {
+                TimeDelta::seconds(duration_secs)
+            };
+            entries.push(ManualEntry { id, project, start: entry_start, end: entry_end, duration, note });
+        }
+        Ok(entries)
+    }
+
+    // --- worktrees ----------------------------------------------------------
+
+    /// Remember that the git worktree at `dir` belongs to the checkout at
+    /// `main`, so its sessions still group there once it's removed.
+    pub fn remember_worktree(&self, dir: &str, main: &str) -> Result<()> {
+        self.conn
+            .prepare_cached("INSERT OR REPLACE INTO worktrees (dir, main) VALUES (?1, ?2)")?
+            .execute([dir, main])
+            .context("remembering worktree")?;
+        Ok(())
+    }
+
+    /// Every remembered worktree and the checkout it belongs to.
+    pub fn worktrees(&self) -> Result<HashMap<String, String>> {
+        self.conn
+            .prepare("SELECT dir, main FROM worktrees")?
+            .query_map([], |row| Ok((row.get(0)?, row.get(1)?)))?
+            .collect::<rusqlite::Result<_>>()
+            .context("querying worktrees")
+    }
+
+    // --- incremental parsing state ---------------------------------------
+
+    pub fn file_state(&self, source_path: &str) -> Result<Option<FileState>> {
+        let row: Option<(i64, i64, i64, String)> = self.conn.query_row(
+            "SELECT size, mtime_ms, offset, state FROM source_files WHERE source_path = ?1",
+            [source_path],
+            |row| Ok((row.get(0)?, row.get(1)?, row.get(2)?, row.get(3)?)),
+        ).optional().context("querying file state")?;
+
+        let Some((size, mtime_ms, offset, state)) = row else {
+            return Ok(None);
+        };
+        // State written by an incompatible build is treated as missing so the
+        // file gets re-parsed from the start.
+        let Ok(accumulator) = serde_json::from_str(&state) else {
+            return Ok(None);
+        };
+        Ok(Some(FileState {
+            size: size as u64,
+            mtime_ms,
+            offset: offset as u64,
+            accumulator,
+        }))
+    }
+
+    pub fn save_file_state(&self, source_path: &str, state: &FileState) -> Result<()> {
+        let json = serde_json::to_string(&state.accumulator).context("serializing parser state")?;
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO source_files (source_path, size, mtime_ms, offset, state)
+                 VALUES (?1, ?2, ?3, ?4, ?5)",
+                rusqlite::params![
+                    source_path,
+                    state.size as i64,
+                    state.mtime_ms,
+                    state.offset as i64,
+                    json,
+                ],
+            )
+            .context("saving file state")?;
+        Ok(())
+    }
+
+    // --- archive -----------------------------------------------------------
+
+    pub fn archived_file(&self, source_path: &str) -> Result<Option<ArchivedFile>> {
+        self.conn
+            .prepare_cached("SELECT source_path, sha256, size, mtime_ms FROM archived_files WHERE source_path = ?1")?
+            .query_row([source_path], archived_file_from_row)
+            .optional()
+            .context("querying archived file")
+    }
+
+    /// Every archived file, oldest first.
+    pub fn archived_files(&self) -> Result<Vec<ArchivedFile>> {
+        self.conn
+            .prepare("SELECT source_path, sha256, size, mtime_ms FROM archived_files ORDER BY mtime_ms, source_path")?
+            .query_map([], archived_file_from_row)?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("querying archived files")
+    }
+
+    pub fn save_archived_file(&self, file: &ArchivedFile) -> Result<()> {
+        self.conn
+            .execute(
+                "INSERT OR REPLACE INTO archived_files (source_path, sha256, size, mtime_ms)
+                 VALUES (?1, ?2, ?3, ?4)",
+                rusqlite::params![file.source_path, file.sha256, file.size as i64, file.mtime_ms],
+            )
+            .context("saving archived file")?;
+        Ok(())
+    }
+
+    /// True if any file's latest copy has this content.
+    pub fn archive_object_in_use(&self, sha256: &str) -> Result<bool> {
+        self.conn
+            .query_row("SELECT EXISTS (SELECT 1 FROM archived_files WHERE sha256 = ?1)", [sha256], |row| row.get(0))
+            .context("querying archive object")
+    }
+
+    // --- cross-file message dedup ------------------------------------------
+
+    /// Claim a message uuid for `source_path`. Returns false if another file
+    /// already owns it (a resumed session replaying earlier history).
+    pub fn claim_message(&self, uuid: &str, source_path: &str) -> Result<bool> {
+        self.conn
+            .prepare_cached("INSERT OR IGNORE INTO message_claims (uuid, source_path) VALUES (?1, ?2)")?
+            .execute([uuid, source_path])
+            .context("claiming message")?;
+        Ok(self.message_owner(uuid)?.as_deref() == Some(source_path))
+    }
+
+    pub fn message_owner(&self, uuid: &str) -> Result<Option<String>> {
+        self.conn
+            .prepare_cached("SELECT source_path FROM message_claims WHERE uuid = ?1")?
+            .query_row([uuid], |row| row.get(0))
+            .optional()
+            .context("querying message owner")
+    }
+
+    pub fn health(&self) -> Result<Health> {
+        let problems = self
+            .conn
+            .prepare("PRAGMA integrity_check")?
+            .query_map([], |row| row.get::<_, String>(0))?
+            .collect::<rusqlite::Result<Vec<_>>>()
+            .context("checking database integrity")?;
+        let count = |table: &str| -> Result<usize> {
+            self.conn
+                .query_row(&format!("SELECT COUNT(*) FROM {}", table), [], |row| row.get(0))
+                .with_context(|| format!("counting {}", table))
+        };
+        Ok(Health {
+            integrity: problems.join("; "),
+            schema_version: migrations::version(&self.conn)?,
+            sessions: count("sessions")?,
+            source_files: count("source_files")?,
+        })
+    }
+
+    /// UTC date of the first session or manual entry.
     pub fn earliest_session_date(&self) -> Result<Option<NaiveDate>> {
         let result: Option<String> = self.conn.query_row(
             "SELECT start_time FROM sessions ORDER BY start_time ASC LIMIT 1",
             [],
             |row| row.get(0),
         ).optional().context("querying earliest session")?;
+        let manual: Option<i64> = self
+            .conn
+            .query_row("SELECT MIN(start_ms) FROM manual_entries", [], |row| row.get(0))
+            .context("querying earliest manual entry")?;
 
-        match result {
+        let session = match result {
             Some(start_time_str) => {
                 let start_time: DateTime<Utc> = start_time_str.parse()
                     .context("parsing earliest start_time")?;
-                Ok(Some(start_time.date_naive()))
+                Some(start_time.date_naive())
             }
-            None => Ok(None),
-        }
+            None => None,
+        };
+        let manual = manual.and_then(DateTime::from_timestamp_millis).map(|t| t.date_naive());
+        Ok(session.into_iter().chain(manual).min())
     }
 
     // --- sync tracking ----------------------------------------------------
@@ -177,5 +806,14 @@ impl Store {
     }
 }
 
+fn archived_file_from_row(row: &rusqlite::Row) -> rusqlite::Result<ArchivedFile> {
+    Ok(ArchivedFile {
+        source_path: row.get(0)?,
+        sha256: row.get(1)?,
+        size: row.get::<_, i64>(2)? as u64,
+        mtime_ms: row.get(3)?,
+    })
+}
+
 #[cfg(test)]
 mod tests;
@@ -0,0 +1,306 @@
+//! Schema changes, applied in order and tracked by `PRAGMA user_version`.
+//!
+//! The version is the number of migrations applied. Each migration runs in
+//! its own transaction together with the version bump, so an interrupted
+//! upgrade picks up where it stopped. A copy of the database is taken
+//! before any migration runs, and a database from a newer build is left
+//! alone rather than downgraded.
+//!
+//! Released migrations are never edited; changes go in a new one.
+
+use anyhow::{Context, Result};
+use rusqlite::{Connection, TransactionBehavior};
+use std::path::{Path, PathBuf};
+
+struct Migration {
+    description: &'static str,
+    apply: fn(&Connection) -> Result<()>,
+}
+
+const MIGRATIONS: &[Migration] = &[
+    Migration { description: "adopt the unversioned schema", apply: adopt_unversioned },
+    Migration { description: "record individual messages", apply: add_messages },
+    Migration { description: "record manual time", apply: add_manual_entries },
+    Migration { description: "remember worktree checkouts", apply: add_worktrees },
+];
+
+/// Schema version this build writes.
+pub(super) const LATEST: usize = MIGRATIONS.len();
+
+/// Bring the database at `path` up to `LATEST`.
+pub(super) fn migrate(conn: &mut Connection, path: &Path) -> Result<()> {
+    let from = version(conn)?;
+    anyhow::ensure!(
+        from <= LATEST,
+        "{:?} is at schema version {}, but this build only knows up to {}; upgrade claude-tracker to open it",
+        path,
+        from,
+        LATEST
+    );
+    if from == LATEST {
+        return Ok(());
+    }
+    if has_tables(conn)? {
+        backup(conn, &backup_path(path, from))?;
+    }
+    for (index, migration) in MIGRATIONS.iter().enumerate().skip(from) {
+        let tx = conn.transaction_with_behavior(TransactionBehavior::Immediate)?;
+        // Another connection may have run it while we waited for the lock.
+        if version(&tx)? > index {
+            continue;
+        }
+        (migration.apply)(&tx)
+            .with_context(|| format!("migration {} ({})", index + 1, migration.description))?;
+        tx.pragma_update(None, "user_version", index + 1)?;
+        tx.commit()?;
+    }
+    Ok(())
+}
+
+pub(super) fn version(conn: &Connection) -> Result<usize> {
+    let version: i64 = conn.pragma_query_value(None, "user_version", |row| row.get(0))?;
+    Ok(version as usize)
+}
+
+/// Where the copy taken before migrating from `version` goes, next to the
+/// database.
+pub(super) fn backup_path(path: &Path, version: usize) -> PathBuf {
+    let name = path.file_name().map(|n| n.to_string_lossy()).unwrap_or_default();
+    path.with_file_name(format!("{}.v{}.bak", name, version))
+}
+
+fn has_tables(conn: &Connection) -> Result<bool> {
+    Ok(conn.query_row("SELECT COUNT(*) > 0 FROM sqlite_master WHERE type = 'table'", [], |row| row.get(0))?)
+}
+
+/// A consistent copy of the database, WAL included. Replaces an older copy
+/// from the same version.
+fn backup(conn: &Connection, to: &Path) -> Result<()> {
+    if to.exists() {
+        std::fs::remove_file(to).with_context(|| format!("removing old backup {:?}", to))?;
+    }
+    conn.execute("VACUUM INTO ?1", [to.to_string_lossy()])
+        .with_context(|| format!("backing up to {:?}", to))?;
+    Ok(())
+}
+
+// --- 1: adopt the unversioned schema --------------------------------------
+
+// One row per working directory a session file touched.
+const SESSIONS_TABLE: &str = "CREATE TABLE IF NOT EXISTS sessions (
+    source_path                  TEXT    NOT NULL,
+    project                      TEXT    NOT NULL,
+    date                         TEXT    NOT NULL,
+    start_time                   TEXT    NOT NULL,
+    end_time                     TEXT    NOT NULL,
+    duration_seconds             INTEGER NOT NULL,
+    input_tokens                 INTEGER NOT NULL DEFAULT 0,
+    output_tokens                INTEGER NOT NULL DEFAULT 0,
+    cache_creation_input_tokens  INTEGER NOT NULL DEFAULT 0,
+    cache_read_input_tokens      INTEGER NOT NULL DEFAULT 0,
+    parent_path                  TEXT,
+    lines_added                  INTEGER NOT NULL DEFAULT 0,
+    lines_removed                INTEGER NOT NULL DEFAULT 0,
+    title                        TEXT,
+    PRIMARY KEY (source_path, project)
+);";
+
+const SESSION_MODELS_TABLE: &str = "CREATE TABLE IF NOT EXISTS session_models (
+    source_path                  TEXT    NOT NULL,
+    project                      TEXT    NOT NULL,
+    model                        TEXT    NOT NULL,
+    input_tokens                 INTEGER NOT NULL DEFAULT 0,
+    output_tokens                INTEGER NOT NULL DEFAULT 0,
+    cache_creation_input_tokens  INTEGER NOT NULL DEFAULT 0,
+    cache_read_input_tokens      INTEGER NOT NULL DEFAULT 0,
+    PRIMARY KEY (source_path, project, model)
+);";
+
+/// Databases from before versioning were upgraded in place on every open.
+/// This brings one at any of those layouts, or an empty one, up to the
+/// last of them.
+fn adopt_unversioned(conn: &Connection) -> Result<()> {
+    conn.execute_batch(SESSIONS_TABLE)
+        .and_then(|_| conn.execute_batch(SESSION_MODELS_TABLE))
+        .context("creating tables")?;
+    conn.execute_batch(
+        "CREATE TABLE IF NOT EXISTS synced_days (
+             date         TEXT NOT NULL,
+             workspace_id TEXT NOT NULL,
+             PRIMARY KEY (date, workspace_id)
+         );
+         CREATE TABLE IF NOT EXISTS synced_entries (
+             date              TEXT NOT NULL,
+             workspace_id      TEXT NOT NULL,
+             project_id        TEXT NOT NULL,
+             clockify_entry_id TEXT NOT NULL,
+             PRIMARY KEY (date, workspace_id, project_id)
+         );
+         CREATE TABLE IF NOT EXISTS source_files (
+             source_path TEXT    PRIMARY KEY,
+             size        INTEGER NOT NULL,
+             mtime_ms    INTEGER NOT NULL,
+             offset      INTEGER NOT NULL,
+             state       TEXT    NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS message_claims (
+             uuid        TEXT PRIMARY KEY,
+             source_path TEXT NOT NULL
+         );
+         CREATE TABLE IF NOT EXISTS session_intervals (
+             source_path TEXT    NOT NULL,
+             project     TEXT    NOT NULL,
+             start_ms    INTEGER NOT NULL,
+             end_ms      INTEGER NOT NULL,
+             branch      TEXT
+         );
+         CREATE INDEX IF NOT EXISTS session_intervals_segment
+             ON session_intervals (source_path, project);
+         CREATE TABLE IF NOT EXISTS session_tools (
+             source_path TEXT    NOT NULL,
+             project     TEXT    NOT NULL,
+             tool        TEXT    NOT NULL,
+             calls       INTEGER NOT NULL DEFAULT 0,
+             errors      INTEGER NOT NULL DEFAULT 0,
+             PRIMARY KEY (source_path, project, tool)
+         );
+         CREATE TABLE IF NOT EXISTS archived_files (
+             source_path TEXT    PRIMARY KEY,
+             sha256      TEXT    NOT NULL,
+             size        INTEGER NOT NULL,
+             mtime_ms    INTEGER NOT NULL
+         );",
+    )
+    .context("creating tables")?;
+    upgrade_segment_keys(conn)?;
+    add_column(conn, "session_intervals", "branch", "TEXT")
+        .and_then(|_| add_column(conn, "sessions", "parent_path", "TEXT"))
+        .and_then(|_| add_column(conn, "sessions", "lines_added", "INTEGER NOT NULL DEFAULT 0"))
+        .and_then(|_| add_column(conn, "sessions", "lines_removed", "INTEGER NOT NULL DEFAULT 0"))
+        .and_then(|_| add_column(conn, "sessions", "title", "TEXT"))
+        .context("adding columns")?;
+    Ok(())
+}
+
+/// Databases from before sessions were split per working directory key
+/// `sessions` and `session_models` by file alone. Rebuild them keyed by
+/// (file, project); each old row was a single segment, so rows carry over.
+fn upgrade_segment_keys(conn: &Connection) -> Result<()> {
+    let pk_columns = |table: &str| -> Result<i64> {
+        Ok(conn.query_row(
+            "SELECT COUNT(*) FROM pragma_table_info(?1) WHERE pk > 0",
+            [table],
+            |row| row.get(0),
+        )?)
+    };
+
+    if pk_columns("sessions")? == 1 {
+        conn.execute_batch(&format!(
+            "ALTER TABLE sessions RENAME TO sessions_by_file;
+             {SESSIONS_TABLE}
+             INSERT INTO sessions (
+                 source_path, project, date, start_time, end_time, duration_seconds,
+                 input_tokens, output_tokens, cache_creation_input_tokens, cache_read_input_tokens
+             ) SELECT * FROM sessions_by_file;
+             DROP TABLE sessions_by_file;"
+        ))?;
+    }
+    if pk_columns("session_models")? == 2 {
+        conn.execute_batch(&format!(
+            "ALTER TABLE session_models RENAME TO session_models_by_file;
+             {SESSION_MODELS_TABLE}
+             INSERT INTO session_models
+                 SELECT m.source_path, s.project, m.model, m.input_tokens, m.output_tokens,
+                        m.cache_creation_input_tokens, m.cache_read_input_tokens
+                 FROM session_models_by_file m JOIN sessions s USING (source_path);
+             DROP TABLE session_models_by_file;"
+        ))?;
+    }
+    Ok(())
+}
+
+/// Add a column to a table created by an older build. No-op if it exists.
+fn add_column(conn: &Connection, table: &str, column: &str, decl: &str) -> Result<()> {
+    let exists: bool = conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info(?1) WHERE name = ?2",
+        [table, column],
+        |row| row.get(0),
+    )?;
+    if !exists {
+        conn.execute_batch(&format!("ALTER TABLE {table} ADD COLUMN {column} {decl}"))?;
+    }
+    Ok(())
+}
+
+// --- 2: record individual messages ----------------------------------------
+
+/// One row per folded line, for questions finer than a session. Filled in
+/// as files are next parsed; the parser version bump that came with it
+/// re-reads every file still on disk.
+fn add_messages(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE messages (
+             source_path                  TEXT    NOT NULL,
+             uuid                         TEXT,
+             session_id                   TEXT,
+             response_id                  TEXT,
+             timestamp_ms                 INTEGER NOT NULL,
+             role                         TEXT,
+             model                        TEXT,
+             project                      TEXT    NOT NULL,
+             branch                       TEXT,
+             input_tokens                 INTEGER NOT NULL DEFAULT 0,
+             output_tokens                INTEGER NOT NULL DEFAULT 0,
+             cache_creation_input_tokens  INTEGER NOT NULL DEFAULT 0,
+             cache_read_input_tokens      INTEGER NOT NULL DEFAULT 0
+         );
+         CREATE INDEX messages_by_time ON messages (timestamp_ms);
+         CREATE INDEX messages_by_file ON messages (source_path);",
+    )?;
+    Ok(())
+}
+
+// --- 3: record manual time ------------------------------------------------
+
+/// Time entered by hand with `add` and `adjust`. An adjustment starts and
+/// ends at the same moment and carries a signed duration.
+fn add_manual_entries(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE manual_entries (
+             id                INTEGER PRIMARY KEY,
+             project           TEXT    NOT NULL,
+             start_ms          INTEGER NOT NULL,
+             end_ms            INTEGER NOT NULL,
+             duration_seconds  INTEGER NOT NULL,
+             note              TEXT
+         );
+         CREATE INDEX manual_entries_by_time ON manual_entries (start_ms);",
+    )?;
+    Ok(())
+}
+
+// --- 4: remember worktree checkouts ---------------------------------------
+
+/// The main checkout of each git worktree sessions were recorded in, so
+/// they still group with it once the worktree is removed. Worktrees behind
+/// sessions already stored are looked up now, while they may still exist;
+/// later ones are recorded as their files are parsed.
+fn add_worktrees(conn: &Connection) -> Result<()> {
+    conn.execute_batch(
+        "CREATE TABLE worktrees (
+             dir   TEXT PRIMARY KEY,
+             main  TEXT NOT NULL
+         );",
+    )?;
+    let dirs = conn
+        .prepare("SELECT project FROM sessions UNION SELECT project FROM manual_entries")?
+        .query_map([], |row| row.get::<_, String>(0))?
+        .collect::<rusqlite::Result<Vec<_>>>()?;
+    let mut insert = conn.prepare("INSERT OR REPLACE INTO worktrees (dir, main) VALUES (?1, ?2)")?;
+    for dir in dirs.iter().filter(|dir| !dir.is_empty()) {
+        if let Some((root, main)) = crate::alias::linked_worktree(Path::new(dir)) {
+            insert.execute([root.to_string_lossy(), main.to_string_lossy()])?;
+        }
+    }
+    Ok(())
+}
@@ -13,6 +13,12 @@ fn make_session(start: &str, end: &str, duration_secs: i64) -> parser::Session {
         output_tokens: 0,
         cache_creation_input_tokens: 0,
         cache_read_input_tokens: 0,
+        models: Default::default(),
+        intervals: Vec::new(),
+        parent: None,
+        tools: Default::default(),
+        lines: Default::default(),
+        title: None,
     }
 }
 
@@ -109,6 +115,12 @@ fn query_range_returns_sessions_overlapping_range() -> Result<()> {
         output_tokens: 50,
         cache_creation_input_tokens: 200,
         cache_read_input_tokens: 300,
+        models: Default::default(),
+        intervals: Vec::new(),
+        parent: None,
+        tools: Default::default(),
+        lines: Default::default(),
+        title: None,
     };
     store.upsert("proj/session-1.jsonl", &session)?;
 
@@ -277,3 +289,572 @@ fn partial_day_is_not_synced_but_entries_are() -> Result<()> {
     assert!(!store.is_entry_synced("2026-02-04", "ws-123", "proj-c")?);
     Ok(())
 }
+
+// --- incremental parsing state -------------------------------------------
+
+#[test]
+fn file_state_roundtrips() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+
+    assert!(store.file_state("proj/session-1.jsonl")?.is_none());
+
+    store.save_file_state(
+        "proj/session-1.jsonl",
+        &FileState {
+            size: 2048,
+            mtime_ms: 1_770_000_000_000,
+            offset: 2000,
+            accumulator: parser::SessionAccumulator::new(TimeDelta::minutes(15)),
+        },
+    )?;
+
+    let state = store.file_state("proj/session-1.jsonl")?.unwrap();
+    assert_eq!(state.size, 2048);
+    assert_eq!(state.mtime_ms, 1_770_000_000_000);
+    assert_eq!(state.offset, 2000);
+    assert!(state.accumulator.is_compatible(TimeDelta::minutes(15)));
+    Ok(())
+}
+
+#[test]
+fn unreadable_file_state_is_treated_as_missing() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+
+    store.conn.execute(
+        "INSERT INTO source_files (source_path, size, mtime_ms, offset, state)
+         VALUES ('proj/session-1.jsonl', 10, 0, 10, '{\"bogus\": true}')",
+        [],
+    )?;
+
+    assert!(store.file_state("proj/session-1.jsonl")?.is_none());
+    Ok(())
+}
+
+// --- cross-file message dedup --------------------------------------------
+
+#[test]
+fn first_file_to_claim_a_message_owns_it() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+
+    assert!(store.claim_message("u1", "proj/original.jsonl")?);
+    assert!(!store.claim_message("u1", "proj/resumed.jsonl")?);
+    assert!(store.claim_message("u1", "proj/original.jsonl")?, "re-claiming own message");
+    assert_eq!(store.message_owner("u1")?.as_deref(), Some("proj/original.jsonl"));
+    assert_eq!(store.message_owner("u2")?, None);
+    Ok(())
+}
+
+#[test]
+fn remove_deletes_session_row() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+    store.upsert("proj/session-1.jsonl", &make_session("2026-02-04T10:00:00Z", "2026-02-04T10:30:00Z", 1800))?;
+
+    store.remove("proj/session-1.jsonl")?;
+
+    let count: i64 = store.conn.query_row("SELECT COUNT(*) FROM sessions", [], |row| row.get(0))?;
+    assert_eq!(count, 0);
+    Ok(())
+}
+
+// --- per-model breakdown -------------------------------------------------
+
+#[test]
+fn model_breakdown_roundtrips_and_is_replaced_on_upsert() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+    let usage = |output| parser::TokenUsage { input_tokens: 1, output_tokens: output, ..Default::default() };
+
+    let mut session = make_session("2026-02-04T10:00:00Z", "2026-02-04T10:30:00Z", 1800);
+    session.models.insert("claude-opus-4-1".to_string(), usage(100));
+    session.models.insert("claude-haiku-4-5".to_string(), usage(20));
+    store.upsert("proj/session-1.jsonl", &session)?;
+
+    session.models.remove("claude-haiku-4-5");
+    session.models.insert("claude-opus-4-1".to_string(), usage(150));
+    store.upsert("proj/session-1.jsonl", &session)?;
+
+    let start: DateTime<Utc> = "2026-02-04T00:00:00Z".parse().unwrap();
+    let end: DateTime<Utc> = "2026-02-05T00:00:00Z".parse().unwrap();
+    let results = store.query_range(start, end)?;
+
+    assert_eq!(results[0].models.len(), 1);
+    assert_eq!(results[0].models["claude-opus-4-1"].output_tokens, 150);
+    Ok(())
+}
+
+// --- per-cwd segments ----------------------------------------------------
+
+#[test]
+fn replace_sessions_keeps_one_row_per_segment() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+    let mut api = make_session("2026-02-04T10:00:00Z", "2026-02-04T10:30:00Z", 1800);
+    api.project = "/work/api".to_string();
+    let mut web = make_session("2026-02-04T10:30:00Z", "2026-02-04T11:00:00Z", 1800);
+    web.project = "/work/web".to_string();
+
+    store.replace_sessions("proj/session-1.jsonl", &[api, web])?;
+    let start: DateTime<Utc> = "2026-02-04T00:00:00Z".parse().unwrap();
+    let end: DateTime<Utc> = "2026-02-05T00:00:00Z".parse().unwrap();
+    assert_eq!(store.query_range(start, end)?.len(), 2);
+
+    // A reparse that no longer sees /work/web drops its row.
+    let mut api = make_session("2026-02-04T10:00:00Z", "2026-02-04T10:30:00Z", 1800);
+    api.project = "/work/api".to_string();
+    store.replace_sessions("proj/session-1.jsonl", &[api])?;
+    let results = store.query_range(start, end)?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].project, "/work/api");
+    Ok(())
+}
+
+#[test]
+fn upgrades_file_keyed_tables_and_keeps_rows() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("test.db");
+    {
+        let conn = Connection::open(&path)?;
+        conn.execute_batch(
+            "CREATE TABLE sessions (
+                 source_path TEXT PRIMARY KEY, project TEXT NOT NULL, date TEXT NOT NULL,
+                 start_time TEXT NOT NULL, end_time TEXT NOT NULL, duration_seconds INTEGER NOT NULL,
+                 input_tokens INTEGER NOT NULL DEFAULT 0, output_tokens INTEGER NOT NULL DEFAULT 0,
+                 cache_creation_input_tokens INTEGER NOT NULL DEFAULT 0,
+                 cache_read_input_tokens INTEGER NOT NULL DEFAULT 0
+             );
+             CREATE TABLE session_models (
+                 source_path TEXT NOT NULL, model TEXT NOT NULL,
+                 input_tokens INTEGER NOT NULL DEFAULT 0, output_tokens INTEGER NOT NULL DEFAULT 0,
+                 cache_creation_input_tokens INTEGER NOT NULL DEFAULT 0,
+                 cache_read_input_tokens INTEGER NOT NULL DEFAULT 0,
+                 PRIMARY KEY (source_path, model)
+             );
+             INSERT INTO sessions VALUES ('proj/old.jsonl', '/work/old', '2026-02-04',
+                 '2026-02-04T10:00:00Z', '2026-02-04T10:30:00Z', 1800, 1, 2, 3, 4);
+             INSERT INTO session_models VALUES ('proj/old.jsonl', 'claude-opus-4-1', 1, 2, 3, 4);",
+        )?;
+    }
+
+    let store = Store::new(&path)?;
+
+    let start: DateTime<Utc> = "2026-02-04T00:00:00Z".parse().unwrap();
+    let end: DateTime<Utc> = "2026-02-05T00:00:00Z".parse().unwrap();
+    let results = store.query_range(start, end)?;
+    assert_eq!(results.len(), 1);
+    assert_eq!(results[0].project, "/work/old");
+    assert_eq!(results[0].cache_read_input_tokens, 4);
+    assert_eq!(results[0].models["claude-opus-4-1"].output_tokens, 2);
+
+    // New key accepts a second segment for the same file.
+    let mut web = make_session("2026-02-04T11:00:00Z", "2026-02-04T11:30:00Z", 1800);
+    web.project = "/work/web".to_string();
+    store.upsert("proj/old.jsonl", &web)?;
+    assert_eq!(store.query_range(start, end)?.len(), 2);
+    Ok(())
+}
+
+// --- active intervals ----------------------------------------------------
+
+#[test]
+fn query_range_clips_duration_to_window() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+    // Active 23:30–00:15 across midnight, then idle, then 00:45–01:00.
+    let mut session = make_session("2026-02-04T23:30:00Z", "2026-02-05T01:00:00Z", 3600);
+    session.intervals = vec![
+        parser::Interval { start: "2026-02-04T23:30:00Z".parse()?, end: "2026-02-05T00:15:00Z".parse()?, branch: None },
+        parser::Interval { start: "2026-02-05T00:45:00Z".parse()?, end: "2026-02-05T01:00:00Z".parse()?, branch: None },
+    ];
+    store.upsert("proj/session-1.jsonl", &session)?;
+
+    let day = |d: &str| -> Result<DateTime<Utc>> { Ok(format!("{d}T00:00:00Z").parse()?) };
+    let first = store.query_range(day("2026-02-04")?, day("2026-02-05")?)?;
+    let second = store.query_range(day("2026-02-05")?, day("2026-02-06")?)?;
+
+    assert_eq!(first[0].duration.num_minutes(), 30);
+    assert_eq!(first[0].intervals.len(), 1);
+    assert_eq!(second[0].duration.num_minutes(), 30);
+    assert_eq!(second[0].intervals.len(), 2);
+    Ok(())
+}
+
+#[test]
+fn rows_without_intervals_keep_stored_duration() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+    let session = make_session("2026-02-04T23:30:00Z", "2026-02-05T00:30:00Z", 3600);
+    store.upsert("proj/session-1.jsonl", &session)?;
+
+    let results = store.query_range("2026-02-05T00:00:00Z".parse()?, "2026-02-06T00:00:00Z".parse()?)?;
+
+    assert_eq!(results[0].duration.num_seconds(), 3600);
+    Ok(())
+}
+
+#[test]
+fn replacing_sessions_drops_old_intervals() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+    let mut session = make_session("2026-02-04T10:00:00Z", "2026-02-04T10:30:00Z", 1800);
+    session.intervals = vec![parser::Interval { start: session.start, end: session.end, branch: None }];
+    store.replace_sessions("proj/session-1.jsonl", &[session])?;
+
+    store.replace_sessions("proj/session-1.jsonl", &[])?;
+
+    let count: i64 = store.conn.query_row("SELECT COUNT(*) FROM session_intervals", [], |row| row.get(0))?;
+    assert_eq!(count, 0);
+    Ok(())
+}
+
+#[test]
+fn interval_branches_roundtrip() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+    let mut session = make_session("2026-02-04T10:00:00Z", "2026-02-04T10:30:00Z", 1800);
+    session.intervals = vec![
+        parser::Interval { start: "2026-02-04T10:00:00Z".parse()?, end: "2026-02-04T10:20:00Z".parse()?, branch: Some("feat/login".into()) },
+        parser::Interval { start: "2026-02-04T10:20:00Z".parse()?, end: "2026-02-04T10:30:00Z".parse()?, branch: None },
+    ];
+    store.upsert("proj/session-1.jsonl", &session)?;
+
+    let results = store.query_range("2026-02-04T00:00:00Z".parse()?, "2026-02-05T00:00:00Z".parse()?)?;
+
+    assert_eq!(results[0].intervals, session.intervals);
+    Ok(())
+}
+
+#[test]
+fn adds_branch_column_to_existing_intervals_table() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("test.db");
+    {
+        let conn = Connection::open(&path)?;
+        conn.execute_batch(
+            "CREATE TABLE session_intervals (
+                 source_path TEXT NOT NULL, project TEXT NOT NULL,
+                 start_ms INTEGER NOT NULL, end_ms INTEGER NOT NULL
+             );",
+        )?;
+    }
+
+    let store = Store::new(&path)?;
+    drop(store);
+    // Reopening doesn't try to add it twice.
+    let store = Store::new(&path)?;
+
+    let has_branch: bool = store.conn.query_row(
+        "SELECT COUNT(*) > 0 FROM pragma_table_info('session_intervals') WHERE name = 'branch'",
+        [],
+        |row| row.get(0),
+    )?;
+    assert!(has_branch);
+    Ok(())
+}
+
+#[test]
+fn subagent_parent_roundtrips() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+    let mut session = make_session("2026-02-04T10:00:00Z", "2026-02-04T10:30:00Z", 0);
+    session.parent = Some("proj/abc.jsonl".to_string());
+    store.upsert("proj/agent-1.jsonl", &session)?;
+    store.upsert("proj/abc.jsonl", &make_session("2026-02-04T10:00:00Z", "2026-02-04T10:30:00Z", 1800))?;
+
+    let mut results = store.query_range("2026-02-04T00:00:00Z".parse()?, "2026-02-05T00:00:00Z".parse()?)?;
+    results.sort_by_key(|s| s.parent.is_some());
+
+    assert_eq!(results[0].parent, None);
+    assert_eq!(results[1].parent.as_deref(), Some("proj/abc.jsonl"));
+    Ok(())
+}
+
+#[test]
+fn session_tools_roundtrip_and_replace() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+    let mut session = make_session("2026-02-04T10:00:00Z", "2026-02-04T10:30:00Z", 1800);
+    session.tools.insert("Bash".into(), parser::ToolStats { calls: 4, errors: 1 });
+    session.tools.insert("Read".into(), parser::ToolStats { calls: 9, errors: 0 });
+    store.upsert("proj/session-1.jsonl", &session)?;
+
+    session.tools.remove("Read");
+    store.upsert("proj/session-1.jsonl", &session)?;
+
+    let results = store.query_range("2026-02-04T00:00:00Z".parse()?, "2026-02-05T00:00:00Z".parse()?)?;
+    assert_eq!(results[0].tools, session.tools);
+    Ok(())
+}
+
+#[test]
+fn lines_changed_roundtrip() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+    let mut session = make_session("2026-02-04T10:00:00Z", "2026-02-04T10:30:00Z", 1800);
+    session.lines = parser::LineChanges { added: 120, removed: 30 };
+    store.upsert("proj/session-1.jsonl", &session)?;
+
+    let results = store.query_range("2026-02-04T00:00:00Z".parse()?, "2026-02-05T00:00:00Z".parse()?)?;
+
+    assert_eq!(results[0].lines, session.lines);
+    Ok(())
+}
+
+#[test]
+fn title_roundtrips() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+    let mut session = make_session("2026-02-04T10:00:00Z", "2026-02-04T10:30:00Z", 1800);
+    session.title = Some("Fix login redirect loop".to_string());
+    store.upsert("proj/session-1.jsonl", &session)?;
+
+    let results = store.query_range("2026-02-04T00:00:00Z".parse()?, "2026-02-05T00:00:00Z".parse()?)?;
+
+    assert_eq!(results[0].title, session.title);
+    Ok(())
+}
+
+#[test]
+fn health_reports_integrity_and_counts() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+    store.upsert("a.jsonl", &make_session("2024-01-15T10:00:00Z", "2024-01-15T11:00:00Z", 3600))?;
+
+    let health = store.health()?;
+
+    assert_eq!(health.integrity, "ok");
+    assert_eq!(health.sessions, 1);
+    assert_eq!(health.source_files, 0);
+    Ok(())
+}
+
+// --- migrations ----------------------------------------------------------
+
+fn user_version(path: &Path) -> Result<usize> {
+    Ok(Connection::open(path)?.pragma_query_value(None, "user_version", |row| row.get::<_, i64>(0))? as usize)
+}
+
+#[test]
+fn new_database_starts_at_the_latest_version_without_a_backup() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("test.db");
+
+    let store = Store::new(&path)?;
+
+    assert_eq!(store.health()?.schema_version, migrations::LATEST);
+    assert!(!migrations::backup_path(&path, 0).exists());
+    Ok(())
+}
+
+#[test]
+fn unversioned_database_is_backed_up_then_migrated() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("test.db");
+    {
+        let conn = Connection::open(&path)?;
+        conn.execute_batch(
+            "CREATE TABLE synced_days (date TEXT NOT NULL, workspace_id TEXT NOT NULL,
+                 PRIMARY KEY (date, workspace_id));
+             INSERT INTO synced_days VALUES ('2026-02-04', 'ws1');",
+        )?;
+    }
+
+    let store = Store::new(&path)?;
+
+    assert_eq!(user_version(&path)?, migrations::LATEST);
+    assert!(store.is_day_synced("2026-02-04", "ws1")?);
+    // The backup is the database as it was: unversioned, without the new tables.
+    let backup = Connection::open(migrations::backup_path(&path, 0))?;
+    let tables: i64 = backup.query_row("SELECT COUNT(*) FROM sqlite_master WHERE type = 'table'", [], |row| row.get(0))?;
+    assert_eq!(tables, 1);
+    assert_eq!(user_version(&migrations::backup_path(&path, 0))?, 0);
+    Ok(())
+}
+
+#[test]
+fn reopening_a_current_database_runs_nothing() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("test.db");
+    drop(Store::new(&path)?);
+    std::fs::write(migrations::backup_path(&path, 0), "sentinel")?;
+
+    drop(Store::new(&path)?);
+
+    assert_eq!(std::fs::read_to_string(migrations::backup_path(&path, 0))?, "sentinel");
+    Ok(())
+}
+
+#[test]
+fn database_from_a_newer_build_is_refused() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("test.db");
+    drop(Store::new(&path)?);
+    Connection::open(&path)?.pragma_update(None, "user_version", migrations::LATEST as i64 + 1)?;
+
+    let error = Store::new(&path).err().expect("newer schema is refused");
+
+    assert!(format!("{:#}", error).contains("upgrade claude-tracker"));
+    assert_eq!(user_version(&path)?, migrations::LATEST + 1);
+    Ok(())
+}
+
+#[test]
+fn database_at_an_older_version_runs_only_the_newer_migrations() -> Result<()> {
+    let dir = tempdir()?;
+    let path = dir.path().join("test.db");
+    drop(Store::new(&path)?);
+    {
+        let conn = Connection::open(&path)?;
+        conn.execute_batch("DROP TABLE messages; DROP TABLE manual_entries; DROP TABLE worktrees;")?;
+        conn.pragma_update(None, "user_version", 1)?;
+    }
+
+    let store = Store::new(&path)?;
+
+    assert_eq!(store.health()?.schema_version, migrations::LATEST);
+    assert!(migrations::backup_path(&path, 1).exists());
+    assert!(!migrations::backup_path(&path, 0).exists());
+    Ok(())
+}
+
+#[test]
+fn upgrade_remembers_worktrees_behind_stored_sessions() -> Result<()> {
+    let dir = tempdir()?;
+    let (main, worktree) = (dir.path().join("api"), dir.path().join("api-fix"));
+    std::fs::create_dir_all(main.join(".git/worktrees/api-fix"))?;
+    std::fs::create_dir_all(&worktree)?;
+    std::fs::write(worktree.join(".git"), format!("gitdir: {}\n", main.join(".git/worktrees/api-fix").display()))?;
+    std::fs::write(main.join(".git/worktrees/api-fix/commondir"), "../..\n")?;
+
+    // A database from before worktrees were remembered, with a session
+    // already stored from the worktree.
+    let path = dir.path().join("test.db");
+    {
+        let store = Store::new(&path)?;
+        let mut session = make_session("2026-02-04T10:00:00Z", "2026-02-04T11:00:00Z", 3600);
+        session.project = worktree.join("src").display().to_string();
+        store.replace_sessions("-api-fix/s.jsonl", &[session])?;
+        store.conn.execute_batch("DROP TABLE worktrees;")?;
+        store.conn.pragma_update(None, "user_version", 3)?;
+    }
+
+    let store = Store::new(&path)?;
+
+    let worktrees = store.worktrees()?;
+    assert_eq!(worktrees.get(&worktree.display().to_string()), Some(&main.display().to_string()));
+    Ok(())
+}
+
+// --- messages ------------------------------------------------------------
+
+/// UTC instant of a local wall-clock time, so buckets line up in any zone.
+fn local(time: &str) -> DateTime<Utc> {
+    time.parse::<chrono::NaiveDateTime>()
+        .unwrap()
+        .and_local_timezone(Local)
+        .unwrap()
+        .with_timezone(&Utc)
+}
+
+fn message(time: &str, role: &str, output_tokens: u64, response: Option<&str>) -> parser::ParsedMessage {
+    parser::ParsedMessage {
+        timestamp: local(time),
+        role: Some(role.to_string()),
+        message_id: response.map(|r| r.to_string()),
+        usage: (output_tokens > 0).then(|| parser::TokenUsage { output_tokens, ..Default::default() }),
+        ..Default::default()
+    }
+}
+
+fn attributed(project: &str, replaces_previous: bool) -> parser::Attribution {
+    parser::Attribution {
+        project: project.to_string(),
+        branch: None,
+        model: None,
+        session_id: None,
+        replaces_previous,
+    }
+}
+
+#[test]
+fn rollups_bucket_messages_by_local_hour_and_day() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+    store.record_message("a.jsonl", &message("2026-02-03T09:10:00", "user", 0, None), &attributed("/work/api", false))?;
+    store.record_message("a.jsonl", &message("2026-02-03T09:50:00", "assistant", 5, Some("r1")), &attributed("/work/api", false))?;
+    // A second line of the same response replaces, rather than adds to, its usage.
+    store.record_message("a.jsonl", &message("2026-02-03T09:50:01", "assistant", 7, Some("r1")), &attributed("/work/api", true))?;
+    store.record_message("b.jsonl", &message("2026-02-03T11:00:00", "assistant", 3, None), &attributed("/work/web", false))?;
+    let (start, end) = (local("2026-02-03T00:00:00"), local("2026-02-04T00:00:00"));
+
+    let hours = store.message_rollups(start, end, Bucket::Hour, None)?;
+    let days = store.message_rollups(start, end, Bucket::Day, None)?;
+    let web = store.message_rollups(start, end, Bucket::Day, Some("/work/web"))?;
+
+    assert_eq!(hours.len(), 2);
+    assert_eq!(hours[0].start, "2026-02-03T09:00:00".parse()?);
+    assert_eq!((hours[0].messages, hours[0].user_messages, hours[0].tokens.output_tokens), (3, 1, 7));
+    assert_eq!(hours[1].start, "2026-02-03T11:00:00".parse()?);
+    assert_eq!((days.len(), days[0].messages, days[0].tokens.output_tokens), (1, 4, 10));
+    assert_eq!((web[0].messages, web[0].tokens.output_tokens), (1, 3));
+    Ok(())
+}
+
+#[test]
+fn removed_and_cleared_messages_leave_rollups() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+    store.record_message("a.jsonl", &message("2026-02-03T09:10:00", "user", 0, None), &attributed("/work/api", false))?;
+    store.record_message("b.jsonl", &message("2026-02-03T09:20:00", "user", 0, None), &attributed("/work/api", false))?;
+    let (start, end) = (local("2026-02-03T00:00:00"), local("2026-02-04T00:00:00"));
+
+    store.remove_messages("a.jsonl")?;
+    assert_eq!(store.message_rollups(start, end, Bucket::Day, None)?[0].messages, 1);
+
+    store.clear_sessions()?;
+    assert!(store.message_rollups(start, end, Bucket::Day, None)?.is_empty());
+    Ok(())
+}
+
+// --- manual time ---------------------------------------------------------
+
+fn utc(time: &str) -> DateTime<Utc> {
+    time.parse().unwrap()
+}
+
+#[test]
+fn manual_time_is_clipped_to_the_window_and_adjustments_are_kept_whole() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+    let (start, end) = (utc("2026-02-03T09:00:00Z"), utc("2026-02-03T17:00:00Z"));
+    let span = store.add_manual_entry("/work/api", utc("2026-02-03T08:00:00Z"), utc("2026-02-03T10:00:00Z"), TimeDelta::hours(2), Some("pairing"))?;
+    let noon = utc("2026-02-03T12:00:00Z");
+    store.add_manual_entry("/work/api", noon, noon, TimeDelta::minutes(-30), None)?;
+    store.add_manual_entry("/work/web", utc("2026-02-04T09:00:00Z"), utc("2026-02-04T10:00:00Z"), TimeDelta::hours(1), None)?;
+
+    let entries = store.manual_entries(start, end)?;
+
+    assert_eq!(entries.len(), 2);
+    assert_eq!(entries[0].id, span);
+    assert_eq!(entries[0].duration, TimeDelta::hours(1));
+    assert_eq!(entries[0].note.as_deref(), Some("pairing"));
+    assert_eq!(entries[1].duration, TimeDelta::minutes(-30));
+    Ok(())
+}
+
+#[test]
+fn manual_time_survives_a_reparse_and_counts_towards_the_earliest_date() -> Result<()> {
+    let dir = tempdir()?;
+    let store = Store::new(&dir.path().join("test.db"))?;
+    store.upsert("a.jsonl", &make_session("2026-02-05T10:00:00Z", "2026-02-05T11:00:00Z", 3600))?;
+    store.add_manual_entry("/work/api", utc("2026-02-03T09:00:00Z"), utc("2026-02-03T10:00:00Z"), TimeDelta::hours(1), None)?;
+
+    assert_eq!(store.earliest_session_date()?, Some("2026-02-03".parse()?));
+    store.clear_sessions()?;
+    assert_eq!(store.earliest_session_date()?, Some("2026-02-03".parse()?));
+    assert_eq!(store.manual_entries(utc("2026-02-03T00:00:00Z"), utc("2026-02-04T00:00:00Z"))?.len(), 1);
+    Ok(())
+}
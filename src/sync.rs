@@ -2,28 +2,65 @@ use anyhow::{Context, Result};
 use chrono::{DateTime, Datelike, Local, NaiveDate, NaiveTime, TimeDelta, TimeZone, Utc, Weekday};
 use std::collections::HashMap;
 
+use crate::alias::Aliases;
 use crate::parser;
-use crate::store::Store;
+use crate::store::{ManualEntry, Store};
 use crate::SyncConfig;
 
 pub(crate) struct Allocation {
     pub(crate) project_id: String,
     pub(crate) start: DateTime<Utc>,
     pub(crate) end: DateTime<Utc>,
+    /// Git branches worked on under this project, most time first.
+    pub(crate) branches: Vec<String>,
+    /// Titles of the sessions behind this allocation, most time first.
+    pub(crate) titles: Vec<String>,
+}
+
+/// `description` in `[sync]`.
+#[derive(Debug, Default, PartialEq, serde::Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub(crate) enum DescriptionSource {
+    /// Always "Development".
+    #[default]
+    Fixed,
+    /// Git branches worked on.
+    Branches,
+    /// Session titles.
+    Titles,
+}
+
+/// Clockify description for an allocation, falling back to a generic label
+/// when the configured source has nothing to say.
+pub(crate) fn description(allocation: &Allocation, config: &SyncConfig) -> String {
+    let described = match config.description {
+        DescriptionSource::Fixed => String::new(),
+        DescriptionSource::Branches => allocation.branches.join(", "),
+        DescriptionSource::Titles => allocation.titles.join("; "),
+    };
+    if described.is_empty() {
+        "Development".to_string()
+    } else {
+        described
+    }
 }
 
 pub(crate) struct AllocResult {
     pub(crate) allocations: Vec<Allocation>,
+    /// Unmapped projects left out when there's no "other" project. Sync
+    /// doesn't report them; the allocation tests check them.
+    #[cfg_attr(not(test), expect(dead_code))]
     pub(crate) skipped: Vec<String>,
 }
 
-/// Sessions for one day + config + date → allocations.
+/// Sessions and manual time for one day + config + date → allocations.
 pub(crate) fn allocate(
     sessions: &[parser::Session],
+    manual: &[ManualEntry],
     config: &SyncConfig,
     date: NaiveDate,
 ) -> Result<AllocResult> {
-    if sessions.is_empty() {
+    if sessions.is_empty() && manual.is_empty() {
         return Ok(AllocResult {
             allocations: vec![],
             skipped: vec![],
@@ -32,6 +69,7 @@ pub(crate) fn allocate(
     let (start, end) = work_day_boundaries(&config.work_day_start, &config.work_day_end, date)?;
     Ok(compute_allocations(
         sessions,
+        manual,
         &config.project_mapping,
         &config.other_project_id,
         start,
@@ -39,6 +77,17 @@ pub(crate) fn allocate(
     ))
 }
 
+/// Catch config mistakes that would otherwise only surface mid-sync.
+pub fn check_config(config: &SyncConfig) -> Result<()> {
+    let (start, end) = work_day_boundaries(
+        &config.work_day_start,
+        &config.work_day_end,
+        Local::now().date_naive(),
+    )?;
+    anyhow::ensure!(start < end, "work_day_start must be before work_day_end");
+    Ok(())
+}
+
 /// Parse work_day_start/end strings and convert to UTC for a given date.
 fn work_day_boundaries(
     start: &str,
@@ -62,15 +111,44 @@ fn work_day_boundaries(
     Ok((start_utc, end_utc))
 }
 
+/// Manual time for `date`'s work day `[start, end)`: logged spans that
+/// overlap it, and every adjustment made for the date. Adjustments have no
+/// time of day, so they count wherever the work day falls.
+fn manual_for_day(
+    store: &Store,
+    date: NaiveDate,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+) -> Result<Vec<ManualEntry>> {
+    let midnight = |date: NaiveDate| {
+        Local
+            .from_local_datetime(&date.and_time(NaiveTime::MIN))
+            .earliest()
+            .context("no local midnight")
+            .map(|t| t.with_timezone(&Utc))
+    };
+    let next = date.succ_opt().context("Date overflow")?;
+    let mut entries: Vec<_> = store.manual_entries(start, end)?.into_iter().filter(|e| e.end > e.start).collect();
+    entries.extend(
+        store
+            .manual_entries(midnight(date)?, midnight(next)?)?
+            .into_iter()
+            .filter(|e| e.end == e.start),
+    );
+    Ok(entries)
+}
+
 /// Core allocation logic. Pure: operates on pre-converted UTC boundaries.
+/// Manual time counts like tracked time; an adjustment can take some away.
 fn compute_allocations(
     sessions: &[parser::Session],
+    manual: &[ManualEntry],
     project_mapping: &HashMap<String, String>,
     other_project_id: &Option<String>,
     work_day_start: DateTime<Utc>,
     work_day_end: DateTime<Utc>,
 ) -> AllocResult {
-    if sessions.is_empty() {
+    if sessions.is_empty() && manual.is_empty() {
         return AllocResult {
             allocations: vec![],
             skipped: vec![],
@@ -79,29 +157,33 @@ fn compute_allocations(
 
     // Step 1: Build buckets - sum duration per project ID, track skipped
     let mut buckets: HashMap<String, i64> = HashMap::new();
+    let mut labels: HashMap<String, Labels> = HashMap::new();
     let mut skipped = Vec::new();
-    let mut total_included = 0i64; // Only count time that will be allocated
+    // Mapped project, else "other"; if other is disabled, skip the project.
+    let mut bucket_for = |project: &String| -> Option<String> {
+        let project_id = project_mapping.get(project).or(other_project_id.as_ref()).cloned();
+        if project_id.is_none() && !skipped.contains(project) {
+            skipped.push(project.clone());
+        }
+        project_id
+    };
 
     for session in sessions {
-        let duration_secs = session.duration.num_seconds();
-
-        if let Some(project_id) = project_mapping.get(&session.project) {
-            // Mapped project
-            *buckets.entry(project_id.clone()).or_insert(0) += duration_secs;
-            total_included += duration_secs;
-        } else {
-            // Unmapped project
-            if let Some(other_id) = other_project_id {
-                *buckets.entry(other_id.clone()).or_insert(0) += duration_secs;
-                total_included += duration_secs;
-            } else {
-                // Other disabled - skip this project
-                if !skipped.contains(&session.project) {
-                    skipped.push(session.project.clone());
-                }
-            }
+        if let Some(project_id) = bucket_for(&session.project) {
+            *buckets.entry(project_id.clone()).or_insert(0) += session.duration.num_seconds();
+            labels.entry(project_id).or_default().add(session);
         }
     }
+    for entry in manual {
+        if let Some(project_id) = bucket_for(&entry.project) {
+            *buckets.entry(project_id.clone()).or_insert(0) += entry.duration.num_seconds();
+            labels.entry(project_id).or_default().add_manual(entry);
+        }
+    }
+
+    // Adjusted down to nothing, a bucket gets no time.
+    buckets.retain(|_, secs| *secs > 0);
+    let total_included: i64 = buckets.values().sum(); // Only count time that will be allocated
 
     if buckets.is_empty() {
         // All sessions were unmapped and other is disabled
@@ -138,10 +220,13 @@ fn compute_allocations(
         .into_iter()
         .map(|(project_id, duration)| {
             let end = current_start + TimeDelta::seconds(duration);
+            let labels = labels.remove(&project_id).unwrap_or_default();
             let alloc = Allocation {
                 project_id,
                 start: current_start,
                 end,
+                branches: ranked(labels.branches),
+                titles: ranked(labels.titles),
             };
             current_start = end;
             alloc
@@ -154,6 +239,42 @@ fn compute_allocations(
     }
 }
 
+/// Time per branch and per session title within one Clockify project bucket.
+#[derive(Default)]
+struct Labels {
+    branches: HashMap<String, TimeDelta>,
+    titles: HashMap<String, TimeDelta>,
+}
+
+impl Labels {
+    fn add(&mut self, session: &parser::Session) {
+        for (branch, time) in session.branch_durations() {
+            if !branch.is_empty() {
+                *self.branches.entry(branch).or_insert(TimeDelta::zero()) += time;
+            }
+        }
+        if let Some(title) = &session.title {
+            *self.titles.entry(title.clone()).or_insert(TimeDelta::zero()) += session.duration;
+        }
+    }
+
+    /// A manual entry's note reads like a session title.
+    fn add_manual(&mut self, entry: &ManualEntry) {
+        if let Some(note) = &entry.note
+            && entry.duration > TimeDelta::zero()
+        {
+            *self.titles.entry(note.clone()).or_insert(TimeDelta::zero()) += entry.duration;
+        }
+    }
+}
+
+/// Labels ordered by time spent, most first.
+fn ranked(times: HashMap<String, TimeDelta>) -> Vec<String> {
+    let mut times: Vec<_> = times.into_iter().collect();
+    times.sort_by(|a, b| b.1.cmp(&a.1).then_with(|| a.0.cmp(&b.0)));
+    times.into_iter().map(|(label, _)| label).collect()
+}
+
 /// Check if a date is a weekday (Mon-Fri)
 pub(crate) fn is_weekday(date: NaiveDate) -> bool {
     matches!(
@@ -163,7 +284,7 @@ pub(crate) fn is_weekday(date: NaiveDate) -> bool {
 }
 
 /// Run the sync loop: process all unsynced workdays from earliest session to yesterday
-pub fn run_sync(store: &Store, config: &SyncConfig, dry_run: bool) -> Result<()> {
+pub fn run_sync(store: &Store, config: &SyncConfig, aliases: &Aliases, dry_run: bool) -> Result<()> {
     // Get earliest session date
     let start_date = match store.earliest_session_date()? {
         Some(date) => date,
@@ -215,16 +336,17 @@ pub fn run_sync(store: &Store, config: &SyncConfig, dry_run: bool) -> Result<()>
         )?;
 
         // Query sessions for this day
-        let sessions = store.query_range(start_utc, end_utc)?;
+        let sessions = aliases.apply(store.query_range(start_utc, end_utc)?);
+        let manual = aliases.apply_manual(manual_for_day(store, current_date, start_utc, end_utc)?);
 
-        // Skip days with zero sessions (don't mark as synced)
-        if sessions.is_empty() {
+        // Skip days with nothing tracked (don't mark as synced)
+        if sessions.is_empty() && manual.is_empty() {
             current_date = current_date.succ_opt().context("Date overflow")?;
             continue;
         }
 
         // Transform sessions → allocations
-        let alloc_result = allocate(&sessions, config, current_date)?;
+        let alloc_result = allocate(&sessions, &manual, config, current_date)?;
 
         if alloc_result.allocations.is_empty() {
             if dry_run {
@@ -243,11 +365,12 @@ pub fn run_sync(store: &Store, config: &SyncConfig, dry_run: bool) -> Result<()>
                 let duration = allocation.end - allocation.start;
                 let hours = duration.num_minutes() as f64 / 60.0;
                 println!(
-                    "    • project_id {}: {} - {} ({:.1}h)",
+                    "    • project_id {}: {} - {} ({:.1}h) \"{}\"",
                     allocation.project_id,
                     allocation.start.format("%H:%M"),
                     allocation.end.format("%H:%M"),
-                    hours
+                    hours,
+                    description(allocation, config)
                 );
             }
             total_days += 1;
@@ -268,6 +391,7 @@ pub fn run_sync(store: &Store, config: &SyncConfig, dry_run: bool) -> Result<()>
                     allocation.start,
                     allocation.end,
                     &config.workspace_id,
+                    &description(allocation, config),
                 )
                 .with_context(|| format!("Failed to post entry for project_id: {}", allocation.project_id))?;
 
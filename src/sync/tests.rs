@@ -1,8 +1,9 @@
-use super::{compute_allocations, is_weekday};
+use super::{check_config, compute_allocations, description, is_weekday, manual_for_day, work_day_boundaries, DescriptionSource};
 use chrono::{DateTime, NaiveDate, TimeDelta, Utc};
 use std::collections::HashMap;
 
 use crate::parser;
+use crate::store::ManualEntry;
 
 fn session(project: &str, duration_secs: i64) -> parser::Session {
     parser::Session {
@@ -14,6 +15,12 @@ fn session(project: &str, duration_secs: i64) -> parser::Session {
         output_tokens: 0,
         cache_creation_input_tokens: 0,
         cache_read_input_tokens: 0,
+        models: Default::default(),
+        intervals: Vec::new(),
+        parent: None,
+        tools: Default::default(),
+        lines: Default::default(),
+        title: None,
     }
 }
 
@@ -37,6 +44,7 @@ const WORK_DAY_SECS: i64 = 28800;
 #[test]
 fn zero_sessions_returns_empty() {
     let result = compute_allocations(
+        &[],
         &[],
         &mapping(&[("/work/foo", "proj-foo")]),
         &Some("proj-other".into()),
@@ -52,6 +60,7 @@ fn single_mapped_project_fills_entire_work_day() {
     let sessions = vec![session("/work/myapp", 3600)];
     let result = compute_allocations(
         &sessions,
+        &[],
         &mapping(&[("/work/myapp", "proj-myapp")]),
         &Some("proj-other".into()),
         utc(START),
@@ -75,6 +84,7 @@ fn two_projects_split_proportionally() {
 
     let result = compute_allocations(
         &sessions,
+        &[],
         &mapping(&[("/work/alpha", "proj-a"), ("/work/beta", "proj-b")]),
         &Some("proj-other".into()),
         utc(START),
@@ -102,6 +112,7 @@ fn unmapped_projects_aggregate_into_other() {
 
     let result = compute_allocations(
         &sessions,
+        &[],
         &mapping(&[("/work/mapped", "proj-mapped")]),
         &Some("proj-other".into()),
         utc(START),
@@ -131,6 +142,7 @@ fn unmapped_projects_skipped_when_other_disabled() {
 
     let result = compute_allocations(
         &sessions,
+        &[],
         &mapping(&[("/work/mapped", "proj-mapped")]),
         &None,
         utc(START),
@@ -150,6 +162,7 @@ fn no_other_entry_when_all_projects_mapped() {
 
     let result = compute_allocations(
         &sessions,
+        &[],
         &mapping(&[("/work/alpha", "proj-a"), ("/work/beta", "proj-b")]),
         &Some("proj-other".into()),
         utc(START),
@@ -172,6 +185,7 @@ fn allocations_are_contiguous_from_work_day_start() {
 
     let result = compute_allocations(
         &sessions,
+        &[],
         &mapping(&[
             ("/work/alpha", "proj-a"),
             ("/work/beta", "proj-b"),
@@ -210,6 +224,7 @@ fn last_entry_absorbs_rounding_remainder() {
 
     let result = compute_allocations(
         &sessions,
+        &[],
         &mapping(&[
             ("/work/alpha", "proj-a"),
             ("/work/beta", "proj-b"),
@@ -259,6 +274,7 @@ fn is_weekday_filters_correctly() {
 /// - Clockify API key in keyring
 /// - Valid sync config in config.toml
 /// - Test sessions in the database
+///
 /// Run with: cargo test -- --ignored
 #[test]
 #[ignore]
@@ -271,3 +287,177 @@ fn test_run_sync_multiple_days() {
     // 5. Clean up: delete test entries from Clockify
     assert!(true == false, "Scaffold: implement integration test");
 }
+
+// --- Branch descriptions ---------------------------------------------------
+
+fn on_branch(mut session: parser::Session, branches: &[(&str, i64)]) -> parser::Session {
+    let mut start = session.start;
+    for (branch, secs) in branches {
+        let end = start + TimeDelta::seconds(*secs);
+        session.intervals.push(parser::Interval { start, end, branch: Some(branch.to_string()) });
+        start = end;
+    }
+    session
+}
+
+#[test]
+fn allocations_list_branches_by_time() {
+    let sessions = vec![
+        on_branch(session("/work/api", 1800), &[("main", 600), ("feat/login", 1200)]),
+        on_branch(session("/work/web", 600), &[("fix/nav", 600)]),
+        on_branch(session("/work/api", 600), &[("main", 900)]),
+    ];
+    let result = compute_allocations(
+        &sessions,
+        &[],
+        &mapping(&[("/work/api", "proj-api")]),
+        &Some("proj-other".into()),
+        utc(START),
+        utc(END),
+    );
+
+    assert_eq!(result.allocations[0].project_id, "proj-api");
+    assert_eq!(result.allocations[0].branches, vec!["main", "feat/login"]);
+    assert_eq!(result.allocations[1].project_id, "proj-other");
+    assert_eq!(result.allocations[1].branches, vec!["fix/nav"]);
+}
+
+// --- Manual time -----------------------------------------------------------
+
+fn manual(project: &str, duration: TimeDelta, note: Option<&str>) -> ManualEntry {
+    ManualEntry {
+        id: 0,
+        project: project.to_string(),
+        start: utc(START),
+        end: utc(START),
+        duration,
+        note: note.map(|n| n.to_string()),
+    }
+}
+
+#[test]
+fn manual_time_is_allocated_like_tracked_time() {
+    let result = compute_allocations(
+        &[session("/work/api", 3600)],
+        &[manual("/work/web", TimeDelta::hours(1), Some("Pairing on checkout"))],
+        &mapping(&[("/work/api", "proj-api"), ("/work/web", "proj-web")]),
+        &None,
+        utc(START),
+        utc(END),
+    );
+
+    assert_eq!(result.allocations.len(), 2);
+    assert_eq!((result.allocations[1].end - result.allocations[1].start).num_seconds(), WORK_DAY_SECS / 2);
+    assert_eq!(result.allocations[1].titles, vec!["Pairing on checkout"]);
+}
+
+#[test]
+fn adjustment_can_take_a_project_out_of_the_day() {
+    let result = compute_allocations(
+        &[session("/work/api", 1800), session("/work/web", 3600)],
+        &[manual("/work/api", TimeDelta::minutes(-45), None)],
+        &mapping(&[("/work/api", "proj-api"), ("/work/web", "proj-web")]),
+        &None,
+        utc(START),
+        utc(END),
+    );
+
+    assert_eq!(result.allocations.len(), 1);
+    assert_eq!(result.allocations[0].project_id, "proj-web");
+    assert_eq!((result.allocations[0].end - result.allocations[0].start).num_seconds(), WORK_DAY_SECS);
+}
+
+#[test]
+fn day_with_only_manual_time_is_allocated() {
+    let result = compute_allocations(
+        &[],
+        &[manual("/work/api", TimeDelta::hours(2), None)],
+        &mapping(&[("/work/api", "proj-api")]),
+        &None,
+        utc(START),
+        utc(END),
+    );
+
+    assert_eq!(result.allocations.len(), 1);
+    assert_eq!(result.allocations[0].project_id, "proj-api");
+}
+
+#[test]
+fn adjustments_count_towards_a_work_day_that_skips_noon() -> anyhow::Result<()> {
+    let dir = tempfile::tempdir()?;
+    let store = crate::store::Store::new(&dir.path().join("test.db"))?;
+    let date = NaiveDate::from_ymd_opt(2026, 2, 4).unwrap();
+    crate::manual::run_adjust(&store, "/work/api", 30, Some("2026-02-04"), None, None)?;
+    crate::manual::run_adjust(&store, "/work/api", 45, Some("2026-02-05"), None, None)?;
+    crate::manual::run_add(&store, "/work/web", "2026-02-04 10:00", "2026-02-04 11:00", None, None)?;
+    crate::manual::run_add(&store, "/work/web", "2026-02-04 14:00", "2026-02-04 15:00", None, None)?;
+
+    let (start, end) = work_day_boundaries("13:00", "21:00", date)?;
+    let entries = manual_for_day(&store, date, start, end)?;
+
+    let mut found: Vec<_> = entries.iter().map(|e| (e.project.as_str(), e.duration.num_minutes())).collect();
+    found.sort();
+    assert_eq!(found, [("/work/api", 30), ("/work/web", 60)]);
+    Ok(())
+}
+
+// --- Session titles --------------------------------------------------------
+
+fn sync_config(description: DescriptionSource) -> crate::SyncConfig {
+    crate::SyncConfig {
+        workspace_id: "ws".into(),
+        other_project_id: None,
+        work_day_start: "09:00".into(),
+        work_day_end: "17:00".into(),
+        project_mapping: mapping(&[("/work/api", "proj-api")]),
+        description,
+    }
+}
+
+#[test]
+fn descriptions_follow_configured_source() {
+    let mut short = on_branch(session("/work/api", 600), &[("main", 600)]);
+    short.title = Some("Bump deps".into());
+    let mut long = session("/work/api", 3600);
+    long.title = Some("Fix login redirect loop".into());
+    let result = compute_allocations(
+        &[short, long],
+        &[],
+        &mapping(&[("/work/api", "proj-api")]),
+        &None,
+        utc(START),
+        utc(END),
+    );
+    let allocation = &result.allocations[0];
+
+    assert_eq!(allocation.titles, vec!["Fix login redirect loop", "Bump deps"]);
+    assert_eq!(description(allocation, &sync_config(DescriptionSource::Titles)), "Fix login redirect loop; Bump deps");
+    assert_eq!(description(allocation, &sync_config(DescriptionSource::Branches)), "main");
+    assert_eq!(description(allocation, &sync_config(DescriptionSource::Fixed)), "Development");
+}
+
+#[test]
+fn empty_description_source_falls_back() {
+    let result = compute_allocations(
+        &[session("/work/api", 600)],
+        &[],
+        &mapping(&[("/work/api", "proj-api")]),
+        &None,
+        utc(START),
+        utc(END),
+    );
+
+    assert_eq!(description(&result.allocations[0], &sync_config(DescriptionSource::Titles)), "Development");
+}
+
+#[test]
+fn check_config_rejects_bad_work_day() {
+    let mut config = sync_config(DescriptionSource::Fixed);
+    assert!(check_config(&config).is_ok());
+
+    config.work_day_end = "5pm".into();
+    assert!(check_config(&config).is_err());
+
+    config.work_day_end = "08:00".into();
+    assert!(check_config(&config).is_err());
+}
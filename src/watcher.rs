@@ -0,0 +1,72 @@
+//! Filesystem watcher that wakes the refresh loop when a session file is
+//! created or appended to (inotify on Linux, FSEvents on macOS).
+
+use anyhow::{Context, Result};
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::Path;
+use std::sync::mpsc;
+
+pub struct SessionWatcher {
+    // Dropping the watcher stops the OS subscription, so keep it alive.
+    _watcher: RecommendedWatcher,
+    rx: mpsc::Receiver<()>,
+}
+
+impl SessionWatcher {
+    /// Watch each of `dirs` recursively, skipping any that don't exist. Fails
+    /// if none can be watched or the platform can't provide a watcher;
+    /// callers fall back to polling.
+    pub fn new(dirs: &[&Path]) -> Result<Self> {
+        let (tx, rx) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<Event>| {
+            if let Ok(event) = res
+                && is_session_event(&event)
+            {
+                let _ = tx.send(());
+            }
+        })
+        .context("creating file watcher")?;
+        let mut watched = 0;
+        let mut last_error = None;
+        for dir in dirs {
+            match watcher.watch(dir, RecursiveMode::Recursive) {
+                Ok(()) => watched += 1,
+                Err(e) => last_error = Some(anyhow::Error::new(e).context(format!("watching {:?}", dir))),
+            }
+        }
+        if watched == 0 {
+            return Err(last_error.unwrap_or_else(|| anyhow::anyhow!("no directories to watch")));
+        }
+        Ok(Self {
+            _watcher: watcher,
+            rx,
+        })
+    }
+
+    /// Drain pending notifications. True if any session file changed since
+    /// the last call.
+    pub fn changed(&self) -> bool {
+        let mut changed = false;
+        while self.rx.try_recv().is_ok() {
+            changed = true;
+        }
+        changed
+    }
+}
+
+/// A create or write touching at least one transcript: `.jsonl` (possibly
+/// compressed) or `.json`.
+fn is_session_event(event: &Event) -> bool {
+    matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_))
+        && event
+            .paths
+            .iter()
+            .any(|p| {
+                p.file_name()
+                    .and_then(|n| n.to_str())
+                    .is_some_and(|name| crate::scanner::is_jsonl(name) || name.ends_with(".json"))
+            })
+}
+
+#[cfg(test)]
+mod tests;
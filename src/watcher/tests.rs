@@ -0,0 +1,63 @@
+use super::*;
+use notify::event::{AccessKind, CreateKind, DataChange, ModifyKind};
+use std::path::PathBuf;
+use std::time::{Duration, Instant};
+
+fn event(kind: EventKind, path: &str) -> Event {
+    Event::new(kind).add_path(PathBuf::from(path))
+}
+
+#[test]
+fn jsonl_create_and_modify_are_session_events() {
+    assert!(is_session_event(&event(
+        EventKind::Create(CreateKind::File),
+        "/p/-work-api/aaaa.jsonl"
+    )));
+    assert!(is_session_event(&event(
+        EventKind::Modify(ModifyKind::Data(DataChange::Content)),
+        "/p/-work-api/aaaa.jsonl"
+    )));
+}
+
+#[test]
+fn ignores_other_files_and_access_events() {
+    assert!(!is_session_event(&event(
+        EventKind::Modify(ModifyKind::Data(DataChange::Content)),
+        "/p/-work-api/notes.txt"
+    )));
+    assert!(!is_session_event(&event(
+        EventKind::Access(AccessKind::Read),
+        "/p/-work-api/aaaa.jsonl"
+    )));
+}
+
+#[test]
+fn reports_change_when_session_file_appended() {
+    let dir = tempfile::tempdir().unwrap();
+    let proj = dir.path().join("-work-api");
+    std::fs::create_dir(&proj).unwrap();
+    let watcher = SessionWatcher::new(&[dir.path()]).unwrap();
+
+    std::fs::write(proj.join("aaaa.jsonl"), "{}\n").unwrap();
+
+    let deadline = Instant::now() + Duration::from_secs(5);
+    while !watcher.changed() {
+        assert!(Instant::now() < deadline, "no change notification within 5s");
+        std::thread::sleep(Duration::from_millis(20));
+    }
+}
+
+#[test]
+fn missing_directory_fails_so_caller_can_poll() {
+    let dir = tempfile::tempdir().unwrap();
+
+    assert!(SessionWatcher::new(&[&dir.path().join("does-not-exist")]).is_err());
+}
+
+#[test]
+fn compressed_transcripts_are_session_events() {
+    assert!(is_session_event(&event(
+        EventKind::Create(CreateKind::File),
+        "/p/-work-api/aaaa.jsonl.gz"
+    )));
+}